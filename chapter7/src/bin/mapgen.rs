@@ -0,0 +1,202 @@
+// src/bin/mapgen.rs
+//! Generate maps without a window or renderer, for reviewing rule changes and batch runs.
+//!
+//! ```text
+//! cargo run --bin mapgen -- --seed 42 --size 64x48 --count 10 --out maps
+//! ```
+//!
+//! Writes `seed_<seed>.txt` (one `TileType` character per cell) and `seed_<seed>.png`
+//! (rendered from `tilemap.png`) for each seed. The area is centered on the spawn point,
+//! so it matches the start of a game played with the same seed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::{CompressedImageFormats, ImageSampler, ImageType};
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use serde::de::DeserializeOwned;
+
+use chapter7::config::map::FALLBACK_CHUNK_MODEL;
+use chapter7::map::export::GeneratedArea;
+use chapter7::map::generate::{build_chunk_generator, ASSETS_PATH, TILEMAP_FILE};
+use chapter7::map::rules::TerrainRules;
+use chapter7::map::seed::WorldSeed;
+use chapter7::map::tilemap::TilemapDefinition;
+
+const USAGE: &str = "\
+Usage: mapgen [options]
+
+Options:
+  --seed <u64>        First seed (default: WORLD_SEED or random)
+  --size <W>x<H>      Map size in tiles (default: 64x64)
+  --count <n>         Number of maps, using consecutive seeds (default: 1)
+  --out <dir>         Output directory (default: maps)
+  --assets <dir>      Asset directory (default: src/assets)
+  --no-png            Only write the ASCII grids
+
+ASCII legend: . empty  : dirt  , grass  ; yellow grass  - shore  % mud  ~ water  T tree  # rock";
+
+struct Options {
+    seed: WorldSeed,
+    size: UVec2,
+    count: u64,
+    out: PathBuf,
+    assets: PathBuf,
+    png: bool,
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut seed = None;
+        let mut options = Self {
+            seed: WorldSeed(0),
+            size: UVec2::new(64, 64),
+            count: 1,
+            out: PathBuf::from("maps"),
+            assets: PathBuf::from("src/assets"),
+            png: true,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("missing value for {}", name));
+            match arg.as_str() {
+                "--seed" => seed = Some(parse(&value("--seed")?, "seed")?),
+                "--size" => options.size = parse_size(&value("--size")?)?,
+                "--count" => options.count = parse(&value("--count")?, "count")?,
+                "--out" => options.out = PathBuf::from(value("--out")?),
+                "--assets" => options.assets = PathBuf::from(value("--assets")?),
+                "--no-png" => options.png = false,
+                "-h" | "--help" => return Err(String::new()),
+                _ => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+
+        if options.size.x == 0 || options.size.y == 0 {
+            return Err("the map size must not be empty".to_string());
+        }
+        // Without --seed, fall back to WORLD_SEED or a random seed like the game
+        options.seed = seed.map_or_else(WorldSeed::from_args_or_env, WorldSeed);
+        Ok(options)
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid {} '{}'", name, value))
+}
+
+fn parse_size(value: &str) -> Result<UVec2, String> {
+    let (width, height) = value
+        .split_once('x')
+        .ok_or(format!("invalid size '{}', expected <width>x<height>", value))?;
+    Ok(UVec2::new(parse(width, "width")?, parse(height, "height")?))
+}
+
+fn read_ron<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let bytes = fs::read(path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+    ron::de::from_bytes(&bytes).map_err(|err| format!("invalid {}: {}", path.display(), err))
+}
+
+fn read_atlas(path: &Path) -> Result<Image, String> {
+    let bytes = fs::read(path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+    let image = Image::from_buffer(
+        &bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::MAIN_WORLD,
+    )
+    .map_err(|err| format!("invalid {}: {}", path.display(), err))?;
+    image
+        .convert(TextureFormat::Rgba8UnormSrgb)
+        .ok_or(format!("unsupported pixel format in {}", path.display()))
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let layers_dir = options.assets.join(ASSETS_PATH);
+    let terrain_rules: TerrainRules = read_ron(&layers_dir.join("terrain.rules.ron"))?;
+    let tilemap: TilemapDefinition = read_ron(&layers_dir.join("tilemap.ron"))?;
+
+    let (assets_definitions, generator) = build_chunk_generator(&terrain_rules).map_err(|errors| {
+        let errors: Vec<String> = errors.iter().map(|err| format!("  {}", err)).collect();
+        format!("invalid terrain rules:\n{}", errors.join("\n"))
+    })?;
+
+    let referenced = assets_definitions
+        .iter()
+        .flatten()
+        .map(|asset| asset.sprite_name());
+    let errors = tilemap.validate(referenced);
+    if !errors.is_empty() {
+        let errors: Vec<String> = errors.iter().map(|err| format!("  {}", err)).collect();
+        return Err(format!("invalid tile atlas:\n{}", errors.join("\n")));
+    }
+
+    let atlas = if options.png {
+        Some(read_atlas(&layers_dir.join(TILEMAP_FILE))?)
+    } else {
+        None
+    };
+
+    fs::create_dir_all(&options.out)
+        .map_err(|err| format!("cannot create {}: {}", options.out.display(), err))?;
+
+    // Centered on the spawn point, at the world origin
+    let min_cell = -(options.size / 2).as_ivec2();
+
+    for offset in 0..options.count {
+        let seed = WorldSeed(options.seed.0.wrapping_add(offset));
+        let (area, failed) = GeneratedArea::generate(&generator, seed, min_cell, options.size);
+        for (coord, err) in failed {
+            eprintln!(
+                "mapgen: seed {}: failed to generate chunk {}: contradiction at node {}, filled with {}",
+                seed.0, coord, err.node_index, FALLBACK_CHUNK_MODEL
+            );
+        }
+
+        let ascii_path = options.out.join(format!("seed_{}.txt", seed.0));
+        fs::write(&ascii_path, area.to_ascii(&assets_definitions))
+            .map_err(|err| format!("cannot write {}: {}", ascii_path.display(), err))?;
+
+        if let Some(atlas) = &atlas {
+            let png_path = options.out.join(format!("seed_{}.png", seed.0));
+            area.render(&assets_definitions, &tilemap, atlas)
+                .try_into_dynamic()
+                .map_err(|err| format!("cannot convert {}: {}", png_path.display(), err))?
+                .save(&png_path)
+                .map_err(|err| format!("cannot write {}: {}", png_path.display(), err))?;
+        }
+
+        println!("seed {}: {}x{} tiles written to {}", seed.0, area.size().x, area.size().y, options.out.display());
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        // An empty message means --help was asked for
+        Err(message) if message.is_empty() => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("mapgen: {}\n\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("mapgen: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 
-use crate::collision::CollisionMap;
+use crate::collision::spatial::{SpatialIndex, SpatialKind, SpatialShape};
+use crate::collision::{CollisionLayers, CollisionMap, SweepHit};
 use crate::characters::physics::Velocity;
 use crate::config::player::{COLLIDER_RADIUS};
 
@@ -14,6 +15,9 @@ pub struct Collider {
     pub radius: f32,
     /// Offset from entity center (e.g., Vec2(0, -25) for feet)
     pub offset: Vec2,
+    /// What the character is and what it collides with (other characters, projectiles,
+    /// pickups, and water for flying ones)
+    pub layers: CollisionLayers,
 }
 
 impl Default for Collider {
@@ -21,6 +25,7 @@ impl Default for Collider {
         Self {
             radius: COLLIDER_RADIUS,
             offset: Vec2::ZERO,
+            layers: CollisionLayers::default(),
         }
     }
 }
@@ -32,14 +37,35 @@ impl Collider {
     }
 }
 
+impl SpatialShape for Collider {
+    const KIND: SpatialKind = SpatialKind::Character;
+
+    fn circle(&self, transform: &GlobalTransform) -> (Vec2, f32) {
+        (transform.translation().truncate() + self.offset, self.radius)
+    }
+
+    fn layers(&self) -> CollisionLayers {
+        self.layers
+    }
+}
+
+/// Sent when a moving character runs into a blocking tile, every frame it pushes
+/// against it (e.g. for bump sounds).
+#[derive(Message, Debug, Clone, Copy)]
+pub struct TileHit {
+    pub entity: Entity,
+    pub hit: SweepHit,
+}
+
 pub fn validate_movement(
     map: Option<Res<CollisionMap>>,
     time: Res<Time>,
-    mut query: Query<(&Transform, &mut Velocity, &Collider)>,
+    mut query: Query<(Entity, &Transform, &mut Velocity, &Collider)>,
+    mut tile_hits: MessageWriter<TileHit>,
 ) {
     let Some(map) = map else { return };
 
-    for (transform, mut velocity, collider) in query.iter_mut() {
+    for (entity, transform, mut velocity, collider) in query.iter_mut() {
         // Skip if not moving
         if !velocity.is_moving() {
             continue;
@@ -52,11 +78,14 @@ pub fn validate_movement(
         let delta = velocity.0 * time.delta_secs();
         let desired_pos = current_pos + delta;
 
-        // Use swept collision to find valid position
-        let valid_pos = map.sweep_circle(current_pos, desired_pos, collider.radius);
+        // Use swept collision to find valid position, sliding along what we hit
+        let sweep = map.sweep_circle_as(current_pos, desired_pos, collider.radius, collider.layers.layers);
+        if let Some(hit) = sweep.hit {
+            tile_hits.write(TileHit { entity, hit });
+        }
 
         // Calculate what velocity would get us to valid_pos
-        let actual_delta = valid_pos - current_pos;
+        let actual_delta = sweep.position - current_pos;
         
         // Only update velocity if collision modified our path
         if (actual_delta - delta).length_squared() > 0.001 {
@@ -70,17 +99,13 @@ pub fn validate_movement(
 }
 
 /// Resolve collisions between entities (player and enemies)
-/// Prevents entities from moving into each other
+/// Prevents entities from moving into each other, unless their layers let them pass
+/// through (see `CollisionLayers`)
 pub fn resolve_entity_collisions(
+    index: Res<SpatialIndex>,
     mut query: Query<(Entity, &Transform, &mut Velocity, &Collider)>,
 ) {
-    // Collect all entity positions first to avoid multiple mutable borrows
-    let entities: Vec<_> = query
-        .iter()
-        .map(|(e, t, _, c)| (e, c.world_position(t), c.radius))
-        .collect();
-
-    // Check each entity against all others
+    // Check each entity against the characters around it
     for (entity, transform, mut velocity, collider) in query.iter_mut() {
         // Skip if not moving
         if !velocity.is_moving() {
@@ -90,15 +115,21 @@ pub fn resolve_entity_collisions(
         let pos = collider.world_position(transform);
         let radius = collider.radius;
 
-        for &(other_entity, other_pos, other_radius) in &entities {
+        // Indexed positions are the ones of the end of the last frame: nothing has moved since
+        // (`apply_velocity` runs after this system).
+        // The extra margin covers the 10% added to both radii below, for colliders up to our size.
+        let nearby = index
+            .query_radius(pos, radius * 1.2)
+            .filter(|other| other.kind == SpatialKind::Character && collider.layers.interacts_with(&other.layers));
+        for other in nearby {
             // Skip self
-            if entity == other_entity {
+            if entity == other.entity {
                 continue;
             }
 
-            let delta = other_pos - pos;
+            let delta = other.position - pos;
             let distance = delta.length();
-            let min_distance = radius + other_radius;
+            let min_distance = radius + other.radius;
 
             // Check if entities are overlapping or very close
             if distance < min_distance * 1.1 {
@@ -3,6 +3,9 @@ use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::collision::CollisionLayers;
+use crate::config::player::COLLIDER_RADIUS;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum AnimationType {
     #[default] 
@@ -25,12 +28,23 @@ pub struct CharacterEntry {
     pub max_health: f32,
     pub base_move_speed: f32,
     pub run_speed_multiplier: f32,
+    /// Radius of the character's `Collider`, also used to plan paths it fits through
+    #[serde(default = "default_collider_radius")]
+    pub collider_radius: f32,
+    /// Collision layers of the character, instead of the defaults of its role
+    /// (`CollisionLayers::PLAYER` or `CollisionLayers::ENEMY`)
+    #[serde(default)]
+    pub collision: Option<CollisionLayers>,
     pub texture_path: String,
     pub tile_size: u32,
     pub atlas_columns: usize,
     pub animations: HashMap<AnimationType, AnimationDefinition>,
 }
 
+fn default_collider_radius() -> f32 {
+    COLLIDER_RADIUS
+}
+
 impl CharacterEntry {
     pub fn calculate_max_animation_row(&self) -> usize {
         self.animations
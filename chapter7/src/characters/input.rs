@@ -6,6 +6,7 @@ use super::{
     config::CharacterEntry,
     animation::{AnimationController, AnimationTimer},
 };
+use crate::collision::CollisionMap;
 
 #[derive(Component)]
 pub struct Player;
@@ -49,14 +50,16 @@ fn determine_new_state(
 
 pub fn handle_player_input(
     input: Res<ButtonInput<KeyCode>>,
+    collision_map: Option<Res<CollisionMap>>,
     mut query: Query<(
+        &Transform,
         &mut CharacterState,
         &mut Velocity,
         &mut Facing,
         &CharacterEntry,
     ), With<Player>>,
 ) {
-    let Ok((mut state, mut velocity, mut facing, character)) = query.single_mut() else {
+    let Ok((transform, mut state, mut velocity, mut facing, character)) = query.single_mut() else {
         return;
     };
     
@@ -80,9 +83,11 @@ pub fn handle_player_input(
         *state = new_state;  // This triggers Changed<CharacterState>!
     }
     
-    // Step 4: Calculate velocity based on state
+    // Step 4: Calculate velocity based on state and the ground under the player
     // Idle and Jumping = no movement, Walking/Running = movement
-    *velocity = super::physics::calculate_velocity(*state, direction, character);
+    let terrain_speed = collision_map
+        .map_or(1.0, |map| map.speed_multiplier_at(transform.translation.truncate()));
+    *velocity = super::physics::calculate_velocity(*state, direction, character, terrain_speed);
 }
 
 pub fn update_jump_state(
@@ -14,6 +14,8 @@ use config::CharactersList;
 use crate::state::GameState;
 use spawn::PlayerSpawned; // Add this line
 use crate::collision::CollisionMapBuilt; // Add this line
+use crate::collision::spatial::index_shapes;
+use collider::Collider;
 
 pub struct CharactersPlugin;
 
@@ -22,6 +24,7 @@ impl Plugin for CharactersPlugin {
         app.add_plugins(RonAssetPlugin::<CharactersList>::new(&["characters.ron"]))
             .init_resource::<spawn::CurrentCharacterIndex>()
             .init_resource::<PlayerSpawned>() // Add this line
+            .add_message::<collider::TileHit>()
             // Load character assets at startup (before collision map)
             .add_systems(Startup, spawn::load_character_assets) // Change function name
             // Spawn player at valid position AFTER collision map is built
@@ -47,6 +50,10 @@ impl Plugin for CharactersPlugin {
                 )
                     .chain()
                     .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                PostUpdate,
+                index_shapes::<Collider>.after(TransformSystems::Propagate),
             );
     }
 }
\ No newline at end of file
@@ -15,19 +15,23 @@ impl Velocity {
     }
 }
 
+/// `terrain_speed` is the speed multiplier of the ground under the character
+/// (see `CollisionMap::speed_multiplier_at`).
 pub fn calculate_velocity(
     state: CharacterState,
     direction: Vec2,
     character: &CharacterEntry,
+    terrain_speed: f32,
 ) -> Velocity {
+    let speed = character.base_move_speed * terrain_speed;
     match state {
         CharacterState::Idle => Velocity::ZERO,
         CharacterState::Jumping => Velocity::ZERO,  // No movement during jump
         CharacterState::Walking => {
-            Velocity(direction.normalize_or_zero() * character.base_move_speed)
+            Velocity(direction.normalize_or_zero() * speed)
         }
         CharacterState::Running => {
-            Velocity(direction.normalize_or_zero() * character.base_move_speed * character.run_speed_multiplier)
+            Velocity(direction.normalize_or_zero() * speed * character.run_speed_multiplier)
         }
     }
 }
@@ -7,8 +7,8 @@ use crate::characters::physics::Velocity;  // Line update alert
 use crate::characters::facing::Facing;  // Line update alert
 use crate::characters::collider::Collider; 
 use crate::config::player::{PLAYER_SCALE, PLAYER_Z_POSITION}; 
-use crate::combat::PlayerCombat;
-use crate::collision::CollisionMap;
+use crate::combat::{Health, PlayerCombat};
+use crate::collision::{CollisionLayers, CollisionMap};
 
 #[derive(Resource, Default)]
 pub struct CurrentCharacterIndex {
@@ -43,6 +43,8 @@ pub fn switch_character(
     mut query: Query<(
         &mut CharacterEntry,
         &mut Sprite,
+        &mut Collider,
+        &mut Health,
     ), With<Player>>,
     mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     asset_server: Res<AssetServer>,
@@ -78,7 +80,7 @@ pub fn switch_character(
     character_index.index = new_index;
     
     // Update player entity
-    let Ok((mut current_entry, mut sprite)) = query.single_mut() else {
+    let Ok((mut current_entry, mut sprite, mut collider, mut health)) = query.single_mut() else {
         return;
     };
     
@@ -86,6 +88,9 @@ pub fn switch_character(
     
     // Update character entry
     *current_entry = character_entry.clone();
+    collider.radius = character_entry.collider_radius;
+    collider.layers = character_entry.collision.unwrap_or(CollisionLayers::PLAYER);
+    health.set_max(character_entry.max_health);
     
     // Update sprite with new texture
     let texture = asset_server.load(&character_entry.texture_path);
@@ -105,17 +110,15 @@ pub struct PlayerSpawned(pub bool);
 
 // Add this helper function after create_character_atlas_layout
 /// Get a valid spawn position, checking collision map and adjusting if needed
-fn get_valid_spawn_position(collision_map: &CollisionMap, desired_pos: Vec2) -> Vec2 {
-    let player_radius = 12.0; // Approximate player collision radius
-    
+fn get_valid_spawn_position(collision_map: &CollisionMap, desired_pos: Vec2, player_radius: f32) -> Vec2 {
     // Check if the desired position is clear
     if collision_map.is_circle_clear(desired_pos, player_radius) {
         return desired_pos;
     }
     
-    // Find nearest walkable tile
+    // Find nearest tile the player fits in
     let grid_pos = collision_map.world_to_grid(desired_pos);
-    if let Some(walkable) = collision_map.find_nearest_walkable(grid_pos) {
+    if let Some(walkable) = collision_map.find_nearest_clear(grid_pos, player_radius) {
         let world_pos = collision_map.grid_to_world(walkable.x, walkable.y);
         info!(
             "Adjusted player spawn from {:?} to {:?} (was on obstacle)",
@@ -187,7 +190,7 @@ pub fn spawn_player_at_valid_position(
     
     // Calculate valid spawn position
     let desired_pos = Vec2::new(0.0, 0.0);
-    let valid_pos = get_valid_spawn_position(&collision_map, desired_pos);
+    let valid_pos = get_valid_spawn_position(&collision_map, desired_pos, character_entry.collider_radius);
     
     // Create sprite
     let texture = asset_server.load(&character_entry.texture_path);
@@ -204,7 +207,12 @@ pub fn spawn_player_at_valid_position(
         CharacterState::default(),
         Velocity::default(),
         Facing::default(),
-        Collider::default(),
+        Collider {
+            radius: character_entry.collider_radius,
+            layers: character_entry.collision.unwrap_or(CollisionLayers::PLAYER),
+            ..default()
+        },
+        Health::new(character_entry.max_health),
         PlayerCombat::default(),
         AnimationTimer(Timer::from_seconds(
             DEFAULT_ANIMATION_FRAME_TIME,
@@ -0,0 +1,601 @@
+// src/collision/connectivity.rs
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+use bevy::prelude::*;
+
+use super::{CollisionMap, CollisionMapBuilt};
+use crate::config::connectivity::{MAX_REGENERATIONS, MIN_SPAWN_REGION_SHARE, POLICY};
+use crate::map::chunks::ChunkEditor;
+use crate::map::seed::WorldSeed;
+
+/// How the connectivity pass fixes a spawn region that covers too little of the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectivityPolicy {
+    /// Carve paths from the spawn region to the other regions, largest first,
+    /// clearing props and draining water along the way
+    Carve,
+    /// Generate the world again with another seed (carving after too many attempts)
+    Regenerate,
+}
+
+/// Set for a world loaded from a map file: it is played as it was saved (hand-made
+/// fixtures, earlier carves), so the connectivity pass accepts it without editing it.
+/// Cleared when a new world is generated.
+#[derive(Resource, Default, PartialEq, Eq)]
+pub struct SkipConnectivity(pub bool);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Unloaded,
+    Walkable,
+    Blocked,
+}
+
+/// Walkability of the loaded part of the collision map, editable while planning a carve.
+struct WalkGrid {
+    min: IVec2,
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+}
+
+impl WalkGrid {
+    fn from_map(map: &CollisionMap) -> Self {
+        let min = map.min_cell();
+        let (width, height) = (map.width(), map.height());
+        let mut cells = Vec::with_capacity((width * height) as usize);
+        for y in min.y..min.y + height {
+            for x in min.x..min.x + width {
+                cells.push(match map.get_tile(x, y) {
+                    None => Cell::Unloaded,
+                    Some(tile) if tile.is_walkable() => Cell::Walkable,
+                    Some(_) => Cell::Blocked,
+                });
+            }
+        }
+        Self { min, width, height, cells }
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        let local = cell - self.min;
+        (local.x >= 0 && local.y >= 0 && local.x < self.width && local.y < self.height)
+            .then(|| (local.y * self.width + local.x) as usize)
+    }
+
+    fn cell_at(&self, index: usize) -> IVec2 {
+        self.min + IVec2::new(index as i32 % self.width, index as i32 / self.width)
+    }
+
+    /// Loaded cells next to `index`. Only straight moves: `CollisionMap::get_neighbors`
+    /// allows diagonals only when both sides are clear, so they never connect more cells.
+    fn neighbors(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        let cell = self.cell_at(index);
+        [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]
+            .into_iter()
+            .filter_map(move |dir| self.index(cell + dir))
+            .filter(|&neighbor| self.cells[neighbor] != Cell::Unloaded)
+    }
+
+    /// Blocked cells on the cheapest path (fewest blocked cells) from region `from` to region `to`.
+    fn cheapest_carve(&self, regions: &Regions, from: usize, to: usize) -> Vec<IVec2> {
+        let mut cost = vec![u32::MAX; self.cells.len()];
+        let mut previous = vec![usize::MAX; self.cells.len()];
+        let mut queue = VecDeque::new();
+
+        for (index, label) in regions.labels.iter().enumerate() {
+            if *label == Some(from) {
+                cost[index] = 0;
+                queue.push_back(index);
+            }
+        }
+
+        // 0-1 BFS: walkable cells are free, blocked cells cost one carve
+        while let Some(index) = queue.pop_front() {
+            if regions.labels[index] == Some(to) {
+                let mut path = Vec::new();
+                let mut current = index;
+                while cost[current] > 0 {
+                    if self.cells[current] == Cell::Blocked {
+                        path.push(self.cell_at(current));
+                    }
+                    current = previous[current];
+                }
+                return path;
+            }
+
+            for neighbor in self.neighbors(index) {
+                let step = u32::from(self.cells[neighbor] == Cell::Blocked);
+                if cost[index] + step < cost[neighbor] {
+                    cost[neighbor] = cost[index] + step;
+                    previous[neighbor] = index;
+                    if step == 0 {
+                        queue.push_front(neighbor);
+                    } else {
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+        Vec::new()
+    }
+
+    /// Carve paths from the spawn region to the largest other regions until it covers
+    /// `share` of the walkable cells (see [`RegionStats::spawn_share`]). Returns the carved cells.
+    fn carve_until(&mut self, spawn: IVec2, share: f32) -> Vec<IVec2> {
+        let mut carved = Vec::new();
+        let Some(spawn_index) = self.index(spawn) else {
+            return carved;
+        };
+        if self.cells[spawn_index] == Cell::Blocked {
+            self.cells[spawn_index] = Cell::Walkable;
+            carved.push(spawn);
+        }
+
+        loop {
+            let regions = Regions::label(self);
+            if regions.stats(self, spawn).spawn_share() >= share {
+                break;
+            }
+            let Some(spawn_region) = regions.labels[spawn_index] else {
+                break;
+            };
+            let Some(target) = regions.largest_except(Some(spawn_region)) else {
+                break;
+            };
+
+            let path = self.cheapest_carve(&regions, spawn_region, target);
+            if path.is_empty() {
+                break;
+            }
+            for cell in path {
+                if let Some(index) = self.index(cell) {
+                    self.cells[index] = Cell::Walkable;
+                }
+                carved.push(cell);
+            }
+        }
+        carved
+    }
+}
+
+/// Walkable regions: groups of walkable cells connected to each other.
+struct Regions {
+    /// Region of each cell of the grid, `None` for cells that are not walkable
+    labels: Vec<Option<usize>>,
+    /// Number of cells in each region
+    sizes: Vec<usize>,
+    /// Whether each region reaches the edge of the loaded area, where it may go on
+    /// and join other regions in chunks that are not loaded yet
+    at_edge: Vec<bool>,
+}
+
+impl Regions {
+    fn label(grid: &WalkGrid) -> Self {
+        let mut labels = vec![None; grid.cells.len()];
+        let mut sizes = Vec::new();
+        let mut at_edge = Vec::new();
+        let mut stack = Vec::new();
+
+        for start in 0..grid.cells.len() {
+            if grid.cells[start] != Cell::Walkable || labels[start].is_some() {
+                continue;
+            }
+
+            // Flood fill a new region
+            let region = sizes.len();
+            let mut size = 0;
+            let mut edge = false;
+            labels[start] = Some(region);
+            stack.push(start);
+            while let Some(index) = stack.pop() {
+                size += 1;
+                edge |= grid.neighbors(index).count() < 4;
+                for neighbor in grid.neighbors(index) {
+                    if grid.cells[neighbor] == Cell::Walkable && labels[neighbor].is_none() {
+                        labels[neighbor] = Some(region);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            sizes.push(size);
+            at_edge.push(edge);
+        }
+
+        Self { labels, sizes, at_edge }
+    }
+
+    /// Regions counted in the stats: the spawn region, and the others unless they reach
+    /// the edge of the loaded area (they may still connect to it beyond).
+    fn counted(&self, spawn_region: Option<usize>) -> impl Iterator<Item = usize> + '_ {
+        (0..self.sizes.len()).filter(move |&region| Some(region) == spawn_region || !self.at_edge[region])
+    }
+
+    /// Largest region, other than `excluded`, that doesn't reach the edge of the loaded area.
+    fn largest_except(&self, excluded: Option<usize>) -> Option<usize> {
+        self.counted(None)
+            .filter(|&region| Some(region) != excluded)
+            .max_by_key(|&region| self.sizes[region])
+    }
+
+    fn stats(&self, grid: &WalkGrid, spawn: IVec2) -> RegionStats {
+        let spawn_region = grid.index(spawn).and_then(|index| self.labels[index]);
+        let walkable: usize = self.counted(spawn_region).map(|region| self.sizes[region]).sum();
+        RegionStats {
+            regions: self.sizes.len(),
+            walkable,
+            at_edge: self.sizes.iter().sum::<usize>() - walkable,
+            largest: self.counted(spawn_region).map(|region| self.sizes[region]).max().unwrap_or(0),
+            spawn_region: spawn_region.map_or(0, |region| self.sizes[region]),
+        }
+    }
+}
+
+/// Summary of the walkable regions, logged by the connectivity pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionStats {
+    /// Number of separate walkable regions
+    pub regions: usize,
+    /// Walkable tiles in the counted regions: the spawn region and the ones inside the
+    /// loaded area
+    pub walkable: usize,
+    /// Walkable tiles left out, in other regions reaching the edge of the loaded area
+    pub at_edge: usize,
+    /// Tiles in the largest region
+    pub largest: usize,
+    /// Tiles in the region the player spawns in
+    pub spawn_region: usize,
+}
+
+impl RegionStats {
+    /// Share of the counted walkable tiles reachable from the spawn point (0.0 - 1.0).
+    pub fn spawn_share(&self) -> f32 {
+        if self.walkable == 0 {
+            return 0.0;
+        }
+        self.spawn_region as f32 / self.walkable as f32
+    }
+}
+
+impl fmt::Display for RegionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} regions, {} walkable tiles ({} more at the edge), largest {}, spawn region {} ({:.1}%)",
+            self.regions,
+            self.walkable,
+            self.at_edge,
+            self.largest,
+            self.spawn_region,
+            self.spawn_share() * 100.0
+        )
+    }
+}
+
+/// Cell the player spawns on: the world origin, or the walkable cell nearest to it
+/// (like `spawn_player_at_valid_position`).
+fn spawn_cell(map: &CollisionMap) -> IVec2 {
+    let origin = map.world_to_grid(Vec2::ZERO);
+    if map.is_walkable(origin.x, origin.y) {
+        return origin;
+    }
+    map.find_nearest_walkable(origin).unwrap_or(origin)
+}
+
+/// Once the chunks around the spawn point are in the collision map, label its walkable
+/// regions and make sure the player's covers `MIN_SPAWN_REGION_SHARE` of the walkable tiles,
+/// using the configured [`ConnectivityPolicy`]. The map counts as built only after that.
+/// Loaded maps are accepted as they are (see [`SkipConnectivity`]).
+/// Regions cut by the edge of the loaded area are left out: they may still join the spawn
+/// region in chunks not loaded yet.
+pub fn check_connectivity(
+    mut built: ResMut<CollisionMapBuilt>,
+    map: Res<CollisionMap>,
+    mut editor: ChunkEditor,
+    mut seed: ResMut<WorldSeed>,
+    mut regenerations: Local<u32>,
+    skip: Res<SkipConnectivity>,
+) {
+    if !editor.is_settled() {
+        return;
+    }
+
+    if skip.0 {
+        *regenerations = 0;
+        built.0 = true;
+        info!("Collision map built (loaded map, connectivity not checked)");
+        return;
+    }
+
+    let mut grid = WalkGrid::from_map(&map);
+    let spawn = spawn_cell(&map);
+    let stats = Regions::label(&grid).stats(&grid, spawn);
+    info!("Connectivity ({:?} policy): {}", POLICY, stats);
+
+    if stats.spawn_share() >= MIN_SPAWN_REGION_SHARE {
+        *regenerations = 0;
+        built.0 = true;
+        info!("Collision map built");
+        return;
+    }
+
+    if POLICY == ConnectivityPolicy::Regenerate {
+        if *regenerations < MAX_REGENERATIONS {
+            *regenerations += 1;
+            *seed = seed.reroll();
+            info!(
+                "Spawn region too small, regenerating with seed {} (attempt {}/{})",
+                seed.0, *regenerations, MAX_REGENERATIONS
+            );
+            editor.reset();
+            return;
+        }
+        warn!("Spawn region still too small after {} regenerations, carving instead", MAX_REGENERATIONS);
+    }
+
+    let carved = grid.carve_until(spawn, MIN_SPAWN_REGION_SHARE);
+    let changed: HashSet<IVec2> = carved
+        .iter()
+        .flat_map(|cell| editor.clear_obstacles(*cell))
+        .collect();
+
+    if changed.is_empty() {
+        // Nothing left to carve, keep what we have rather than checking forever
+        warn!("Could not connect the spawn region any further");
+        *regenerations = 0;
+        built.0 = true;
+        info!("Collision map built");
+        return;
+    }
+
+    info!("Carved {} tiles in {} chunks to connect the spawn region", carved.len(), changed.len());
+    for coord in changed {
+        // The collision map picks up the respawned chunks, then this pass checks again
+        editor.respawn(coord);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    use bevy::state::app::StatesPlugin;
+    use bevy_procedural_tilemaps::prelude::*;
+    use bevy_procedural_tilemaps::proc_gen::generator::model::ModelInstance;
+
+    use super::*;
+    use crate::characters::spawn::PlayerSpawned;
+    use crate::collision::systems::sync_collision_map;
+    use crate::config::map::{CHUNK_SIZE, TILE_SIZE};
+    use crate::enemy::spawn::EnemiesSpawned;
+    use crate::map::assets::SpawnableAsset;
+    use crate::map::chunks::{
+        chunk_min_cell, stream_chunks, ChunkGenerator, ChunkLoaded, ChunkNodes, ChunkUnloaded, TerrainGenerator,
+        WorldChunks,
+    };
+    use crate::map::generate::game_chunk_generator;
+    use crate::map::save::{load_map, LoadMap, MapFile};
+    use crate::state::GameState;
+
+    /// Grid from rows of `.` (walkable), `#` (blocked) and ` ` (unloaded), bottom row first.
+    fn grid(rows: &[&str]) -> WalkGrid {
+        let cells = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|c| match c {
+                '.' => Cell::Walkable,
+                '#' => Cell::Blocked,
+                _ => Cell::Unloaded,
+            })
+            .collect();
+        WalkGrid {
+            min: IVec2::ZERO,
+            width: rows[0].len() as i32,
+            height: rows.len() as i32,
+            cells,
+        }
+    }
+
+    /// Spawn room (6 tiles) at (1..=3, 1..=2), a closed pocket (2 tiles) above it and a
+    /// corridor (4 tiles) running out of the grid on the right.
+    fn rooms() -> WalkGrid {
+        grid(&[
+            "#######",
+            "#...#.#",
+            "#...#.#",
+            "#####..",
+            "#.#####",
+            "#.#####",
+            "#######",
+        ])
+    }
+
+    #[test]
+    fn regions_reaching_the_edge_are_left_out() {
+        let grid = rooms();
+        let regions = Regions::label(&grid);
+        let spawn = IVec2::new(2, 1);
+
+        assert_eq!(regions.sizes, vec![6, 4, 2]);
+        assert_eq!(regions.at_edge, vec![false, true, false]);
+        assert_eq!(
+            regions.stats(&grid, spawn),
+            RegionStats {
+                regions: 3,
+                walkable: 8,
+                at_edge: 4,
+                largest: 6,
+                spawn_region: 6,
+            }
+        );
+        assert_eq!(regions.largest_except(regions.labels[grid.index(spawn).unwrap()]), Some(2));
+    }
+
+    #[test]
+    fn unloaded_cells_are_an_edge() {
+        let grid = grid(&["#####", "#.#. ", "#####"]);
+        let regions = Regions::label(&grid);
+
+        assert_eq!(regions.sizes, vec![1, 1]);
+        assert_eq!(regions.at_edge, vec![false, true]);
+    }
+
+    #[test]
+    fn carve_connects_closed_regions_through_the_thinnest_wall() {
+        let mut grid = rooms();
+        let carved = grid.carve_until(IVec2::new(2, 1), 1.0);
+
+        // Only the pocket is joined: the corridor may already connect beyond the loaded area
+        assert_eq!(carved, vec![IVec2::new(1, 3)]);
+        let regions = Regions::label(&grid);
+        assert_eq!(regions.stats(&grid, IVec2::new(2, 1)).spawn_share(), 1.0);
+        assert_eq!(grid.cells[grid.index(IVec2::new(4, 1)).unwrap()], Cell::Blocked);
+    }
+
+    #[test]
+    fn carve_clears_a_blocked_spawn() {
+        let mut grid = grid(&["#####", "#.#.#", "#####"]);
+        let carved = grid.carve_until(IVec2::new(2, 1), 1.0);
+
+        assert_eq!(carved, vec![IVec2::new(2, 1)]);
+        assert_eq!(Regions::label(&grid).sizes, vec![3]);
+    }
+
+    /// Set the node of `cell` on layer `z` in already generated chunks.
+    fn set_node(chunks: &mut HashMap<IVec2, ChunkNodes>, cell: IVec2, z: u32, node: Option<ModelInstance>) {
+        let coord = cell.div_euclid(IVec2::splat(CHUNK_SIZE as i32));
+        let local = (cell - chunk_min_cell(coord)).as_uvec2();
+        chunks.get_mut(&coord).unwrap().set(local.x, local.y, z, node);
+    }
+
+    /// Every chunk around the spawn point, with the spawn boxed in by rocks on bare dirt (the
+    /// connectivity pass carves through them), saved to a map file named after `name`.
+    fn boxed_in_spawn_map(name: &str) -> (Vec<Vec<SpawnableAsset>>, ChunkGenerator, HashMap<IVec2, ChunkNodes>, PathBuf) {
+        let (assets, generator) = game_chunk_generator();
+
+        let mut chunks = HashMap::new();
+        for y in -2..=1 {
+            for x in -2..=1 {
+                let failed = generator.generate_with_dependencies(IVec2::new(x, y), WorldSeed(42), &mut chunks);
+                assert!(failed.is_empty());
+            }
+        }
+        let (layer, rock) = generator.find_model("props.rock_1").unwrap();
+        let rock = ModelInstance {
+            model_index: rock,
+            rotation: ModelRotation::Rot0,
+        };
+        // Bare dirt under the rocks, so carving them has to lay the grass back
+        let (grass, no_grass) = generator.find_model("grass.void").unwrap();
+        let no_grass = ModelInstance {
+            model_index: no_grass,
+            rotation: ModelRotation::Rot0,
+        };
+        for cell in box_cells() {
+            set_node(&mut chunks, cell, layer, Some(rock));
+            set_node(&mut chunks, cell, grass, Some(no_grass));
+        }
+
+        let path = std::env::temp_dir().join(format!("chapter7-{}-{}.ron", name, std::process::id()));
+        MapFile::capture(WorldSeed(42), &chunks, &generator, &assets, Vec::new())
+            .write(&path)
+            .unwrap();
+        (assets, generator, chunks, path)
+    }
+
+    /// Cells of the rock box around the spawn point.
+    fn box_cells() -> impl Iterator<Item = IVec2> {
+        (-3..=3).flat_map(|i| [IVec2::new(i, -3), IVec2::new(i, 3), IVec2::new(-3, i), IVec2::new(3, i)])
+    }
+
+    /// Load the map file at `path` and run the connectivity pass on it until the collision
+    /// map is built. With `check_loaded`, the loaded world is checked like a generated one.
+    fn load_and_build(
+        assets: Vec<Vec<SpawnableAsset>>,
+        generator: ChunkGenerator,
+        path: &Path,
+        check_loaded: bool,
+    ) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_state(GameState::Playing)
+            .insert_resource(TerrainGenerator {
+                chunks: generator,
+                models_assets: Arc::new(ModelsAssets::new()),
+                assets_definitions: Arc::new(assets),
+            })
+            .insert_resource(WorldSeed(7))
+            .insert_resource(CollisionMap::new(TILE_SIZE))
+            .init_resource::<WorldChunks>()
+            .init_resource::<CollisionMapBuilt>()
+            .init_resource::<SkipConnectivity>()
+            .init_resource::<PlayerSpawned>()
+            .init_resource::<EnemiesSpawned>()
+            .add_message::<LoadMap>()
+            .add_message::<ChunkLoaded>()
+            .add_message::<ChunkUnloaded>()
+            .add_systems(
+                Update,
+                (
+                    load_map,
+                    move |mut skip: ResMut<SkipConnectivity>| {
+                        if check_loaded {
+                            skip.0 = false;
+                        }
+                    },
+                    stream_chunks,
+                    sync_collision_map,
+                    check_connectivity.run_if(resource_equals(CollisionMapBuilt(false))),
+                )
+                    .chain(),
+            );
+        app.world_mut().write_message(LoadMap { path: path.to_path_buf() });
+        for _ in 0..10 {
+            app.update();
+            if app.world().resource::<CollisionMapBuilt>().0 {
+                break;
+            }
+        }
+        std::fs::remove_file(path).unwrap();
+        app
+    }
+
+    #[test]
+    fn loaded_map_keeps_its_nodes() {
+        let (assets, generator, chunks, path) = boxed_in_spawn_map("loaded-map");
+        let app = load_and_build(assets, generator, &path, false);
+
+        assert!(app.world().resource::<CollisionMapBuilt>().0);
+        assert_eq!(app.world().resource::<WorldSeed>().0, 42);
+        let loaded = app.world().resource::<WorldChunks>().generated();
+        assert_eq!(loaded.len(), chunks.len());
+        for (coord, nodes) in &chunks {
+            assert!(loaded[coord].nodes() == nodes.nodes(), "chunk {coord} was edited");
+        }
+    }
+
+    #[test]
+    fn carving_lays_ground_over_obstacles() {
+        let (assets, generator, _, path) = boxed_in_spawn_map("carved-map");
+        let (props, _) = generator.find_model("props.rock_1").unwrap();
+        let (grass, grass_fill) = generator.find_model("grass.fill").unwrap();
+        let app = load_and_build(assets, generator, &path, true);
+        assert!(app.world().resource::<CollisionMapBuilt>().0);
+
+        let chunks = app.world().resource::<WorldChunks>().generated();
+        let node = |cell: IVec2, z: u32| {
+            let coord = cell.div_euclid(IVec2::splat(CHUNK_SIZE as i32));
+            let local = (cell - chunk_min_cell(coord)).as_uvec2();
+            chunks[&coord].get(local.x, local.y, z)
+        };
+        let map = app.world().resource::<CollisionMap>();
+        let carved: Vec<IVec2> = box_cells().filter(|cell| node(*cell, props).is_none()).collect();
+        assert!(!carved.is_empty(), "the spawn was not carved out");
+        for cell in carved {
+            assert_eq!(node(cell, grass).map(|node| node.model_index), Some(grass_fill), "no grass at {cell}");
+            assert!(map.is_walkable(cell.x, cell.y), "{cell} is still blocked");
+        }
+    }
+}
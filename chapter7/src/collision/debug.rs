@@ -33,15 +33,15 @@ pub fn debug_draw_collision(
     let Some(map) = map else { return };
 
     let tile_size = map.tile_size();
-    let origin = map.origin();
+    let min = map.min_cell();
 
-    // Draw each tile
-    for y in 0..map.height() {
-        for x in 0..map.width() {
-            let world_pos = Vec2::new(
-                origin.x + (x as f32 + 0.5) * tile_size,
-                origin.y + (y as f32 + 0.5) * tile_size,
-            );
+    // Draw each loaded tile
+    for y in min.y..min.y + map.height() {
+        for x in min.x..min.x + map.width() {
+            if map.get_tile(x, y).is_none() {
+                continue;
+            }
+            let world_pos = map.grid_to_world(x, y);
 
             let color = if map.is_walkable(x, y) {
                 Color::srgba(0.0, 1.0, 0.0, 0.25)  // Green, 25% opacity
@@ -0,0 +1,159 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use bevy::prelude::*;
+
+use super::CollisionMap;
+
+/// Distance map toward one goal cell (Dijkstra over the collision map, with the step
+/// costs of `CollisionMap::find_cell_path`). Built once, then any number of agents read their
+/// next step from it in constant time, instead of each running its own A*.
+/// Only covers the cells within `radius` tiles of the goal, and only leads through the
+/// cells where an agent of `agent_radius` fits.
+pub struct FlowField {
+    goal: IVec2,
+    agent_radius: f32,
+    /// Bottom-left cell of the covered square
+    min: IVec2,
+    size: i32,
+    /// Cost to reach the goal from each cell, `u32::MAX` where it can't be reached
+    costs: Vec<u32>,
+}
+
+impl FlowField {
+    /// Distances toward the cell of `goal` (or the nearest cell the agent fits in if it
+    /// doesn't fit there). `None` if there is no such cell around the goal.
+    pub fn build(map: &CollisionMap, goal: Vec2, radius: i32, agent_radius: f32) -> Option<Self> {
+        let goal_cell = map.world_to_grid(goal);
+        let goal_cell = if map.fits(goal_cell.x, goal_cell.y, agent_radius) {
+            goal_cell
+        } else {
+            map.find_nearest_clear(goal_cell, agent_radius)?
+        };
+
+        let size = radius * 2 + 1;
+        let mut field = Self {
+            goal: goal_cell,
+            agent_radius,
+            min: goal_cell - IVec2::splat(radius),
+            size,
+            costs: vec![u32::MAX; (size * size) as usize],
+        };
+
+        // Expand from the goal: the cost of a cell is the cheapest way to step from it
+        // toward the goal, so steps are weighed like `find_cell_path` weighs them
+        let mut open = BinaryHeap::new();
+        let goal_index = field.index(goal_cell)?;
+        field.costs[goal_index] = 0;
+        open.push(Reverse((0, goal_cell.x, goal_cell.y)));
+        while let Some(Reverse((cost, x, y))) = open.pop() {
+            let cell = IVec2::new(x, y);
+            let cell_index = field.index(cell)?;
+            if cost > field.costs[cell_index] {
+                continue; // Already reached for less
+            }
+            for neighbor in map.get_neighbors(cell, field.agent_radius) {
+                let Some(index) = field.index(neighbor) else {
+                    continue;
+                };
+                let neighbor_cost = cost + map.step_cost(neighbor, cell);
+                if neighbor_cost < field.costs[index] {
+                    field.costs[index] = neighbor_cost;
+                    open.push(Reverse((neighbor_cost, neighbor.x, neighbor.y)));
+                }
+            }
+        }
+
+        Some(field)
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        let local = cell - self.min;
+        (local.x >= 0 && local.y >= 0 && local.x < self.size && local.y < self.size)
+            .then(|| (local.y * self.size + local.x) as usize)
+    }
+
+    /// Cell the field leads to.
+    pub fn goal(&self) -> IVec2 {
+        self.goal
+    }
+
+    /// Radius of the agents the field was built for: larger ones may not fit its paths.
+    pub fn agent_radius(&self) -> f32 {
+        self.agent_radius
+    }
+
+    /// Cost to reach the goal from a cell, `None` outside the field or where the goal
+    /// can't be reached.
+    pub fn cost(&self, cell: IVec2) -> Option<u32> {
+        self.index(cell)
+            .map(|index| self.costs[index])
+            .filter(|cost| *cost != u32::MAX)
+    }
+
+    /// Next cell to step to from `cell` on the way to the goal (the neighbour the cheapest
+    /// path goes through). `None` at the goal, outside the field, or where the goal can't
+    /// be reached.
+    pub fn next_cell(&self, map: &CollisionMap, cell: IVec2) -> Option<IVec2> {
+        if cell == self.goal {
+            return None;
+        }
+        self.cost(cell)?;
+        map.get_neighbors(cell, self.agent_radius)
+            .into_iter()
+            .filter_map(|neighbor| Some((neighbor, self.cost(neighbor)? + map.step_cost(cell, neighbor))))
+            .min_by_key(|(_, cost)| *cost)
+            .map(|(neighbor, _)| neighbor)
+    }
+
+    /// Direction to walk from a world position to follow the field: toward the center of
+    /// the next cell. `None` in the goal cell, outside the field, or where the goal
+    /// can't be reached.
+    pub fn direction(&self, map: &CollisionMap, world_pos: Vec2) -> Option<Vec2> {
+        let next = self.next_cell(map, map.world_to_grid(world_pos))?;
+        Some((map.grid_to_world(next.x, next.y) - world_pos).normalize_or_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::TileType;
+
+    const TILE: f32 = 16.0;
+
+    /// 10 x 10 loaded tiles, split by a water wall at x = 5 with a gap at the top (y >= 8).
+    fn map_with_gap() -> CollisionMap {
+        let mut map = CollisionMap::new(TILE);
+        map.include_region(IVec2::ZERO, IVec2::splat(10));
+        for y in 0..8 {
+            map.set_tile(5, y, TileType::Water);
+        }
+        map
+    }
+
+    #[test]
+    fn leads_around_the_wall_to_the_goal() {
+        let map = map_with_gap();
+        let goal = IVec2::new(8, 2);
+        let field = FlowField::build(&map, map.grid_to_world(goal.x, goal.y), 10, 4.0).unwrap();
+        assert_eq!(field.goal(), goal);
+        assert_eq!(field.cost(IVec2::new(5, 2)), None);
+
+        // Straight at the goal is the wall: the first step heads up toward the gap
+        let start = IVec2::new(2, 2);
+        let direction = field.direction(&map, map.grid_to_world(start.x, start.y)).unwrap();
+        assert!(direction.y > 0.5, "direction {direction}");
+
+        let mut cell = start;
+        let mut steps = 0;
+        while let Some(next) = field.next_cell(&map, cell) {
+            assert!(map.is_walkable(next.x, next.y), "stepped into {next}");
+            assert!(field.cost(next) < field.cost(cell), "no progress at {cell}");
+            cell = next;
+            steps += 1;
+            assert!(steps < 30, "lost at {cell}");
+        }
+        assert_eq!(cell, goal);
+    }
+}
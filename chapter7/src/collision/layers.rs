@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+/// What an entity is, for deciding what it collides with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CollisionLayer {
+    Player,
+    Enemy,
+    Projectile,
+    Pickup,
+    /// Flies over tiles marked `fly_over` (e.g. water)
+    Flying,
+    Ghost,
+}
+
+impl CollisionLayer {
+    const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Set of [`CollisionLayer`]s. Written as a list of layers in RON files, e.g. `[Enemy, Ghost]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<CollisionLayer>", into = "Vec<CollisionLayer>")]
+pub struct LayerMask(u32);
+
+impl LayerMask {
+    pub const NONE: Self = Self(0);
+    pub const ALL: Self = Self(u32::MAX);
+
+    pub const fn from_layers(layers: &[CollisionLayer]) -> Self {
+        let mut bits = 0;
+        let mut index = 0;
+        while index < layers.len() {
+            bits |= layers[index].bit();
+            index += 1;
+        }
+        Self(bits)
+    }
+
+    pub const fn contains(self, layer: CollisionLayer) -> bool {
+        self.0 & layer.bit() != 0
+    }
+
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl From<Vec<CollisionLayer>> for LayerMask {
+    fn from(layers: Vec<CollisionLayer>) -> Self {
+        Self::from_layers(&layers)
+    }
+}
+
+impl From<LayerMask> for Vec<CollisionLayer> {
+    fn from(mask: LayerMask) -> Self {
+        use CollisionLayer::*;
+        [Player, Enemy, Projectile, Pickup, Flying, Ghost]
+            .into_iter()
+            .filter(|layer| mask.contains(*layer))
+            .collect()
+    }
+}
+
+/// Layers an entity is on, and the layers it collides with. Two entities only collide
+/// when each one's `mask` has a layer of the other, so either side can opt out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollisionLayers {
+    pub layers: LayerMask,
+    pub mask: LayerMask,
+}
+
+impl Default for CollisionLayers {
+    /// On every layer and colliding with everything
+    fn default() -> Self {
+        Self::new(LayerMask::ALL, LayerMask::ALL)
+    }
+}
+
+impl CollisionLayers {
+    pub const PLAYER: Self = Self::from_layers(
+        &[CollisionLayer::Player],
+        &[CollisionLayer::Enemy, CollisionLayer::Projectile, CollisionLayer::Pickup],
+    );
+    pub const ENEMY: Self = Self::from_layers(
+        &[CollisionLayer::Enemy],
+        &[CollisionLayer::Player, CollisionLayer::Enemy, CollisionLayer::Projectile],
+    );
+    pub const PICKUP: Self = Self::from_layers(&[CollisionLayer::Pickup], &[CollisionLayer::Player]);
+    /// Projectiles shot by the player, hitting enemies only
+    pub const PLAYER_PROJECTILE: Self =
+        Self::from_layers(&[CollisionLayer::Projectile], &[CollisionLayer::Enemy]);
+    /// Projectiles shot by enemies, hitting the player only
+    pub const ENEMY_PROJECTILE: Self =
+        Self::from_layers(&[CollisionLayer::Projectile], &[CollisionLayer::Player]);
+
+    pub const fn new(layers: LayerMask, mask: LayerMask) -> Self {
+        Self { layers, mask }
+    }
+
+    pub const fn from_layers(layers: &[CollisionLayer], mask: &[CollisionLayer]) -> Self {
+        Self::new(LayerMask::from_layers(layers), LayerMask::from_layers(mask))
+    }
+
+    /// Check if the entity is on `layer`.
+    pub const fn has(&self, layer: CollisionLayer) -> bool {
+        self.layers.contains(layer)
+    }
+
+    /// Check if two entities collide.
+    pub const fn interacts_with(&self, other: &Self) -> bool {
+        self.mask.intersects(other.layers) && other.mask.intersects(self.layers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CollisionLayer::*;
+
+    /// The reaper of `characters.ron`: an enemy passing through other enemies
+    const GHOST: CollisionLayers = CollisionLayers::from_layers(&[Enemy, Ghost], &[Player, Projectile]);
+    /// The player of `characters.ron`, flying over water
+    const FLYING_PLAYER: CollisionLayers =
+        CollisionLayers::from_layers(&[Player, Flying], &[Enemy, Projectile, Pickup]);
+
+    #[test]
+    fn ghosts_only_pass_through_enemies() {
+        assert!(!GHOST.interacts_with(&CollisionLayers::ENEMY));
+        assert!(!CollisionLayers::ENEMY.interacts_with(&GHOST));
+        assert!(!GHOST.interacts_with(&GHOST));
+        assert!(GHOST.interacts_with(&CollisionLayers::PLAYER));
+        assert!(GHOST.interacts_with(&CollisionLayers::PLAYER_PROJECTILE));
+        assert!(!GHOST.interacts_with(&CollisionLayers::ENEMY_PROJECTILE));
+    }
+
+    #[test]
+    fn flying_player_collides_like_the_player() {
+        for other in [
+            CollisionLayers::ENEMY,
+            CollisionLayers::PICKUP,
+            CollisionLayers::ENEMY_PROJECTILE,
+            GHOST,
+        ] {
+            assert_eq!(FLYING_PLAYER.interacts_with(&other), CollisionLayers::PLAYER.interacts_with(&other));
+        }
+        assert!(!FLYING_PLAYER.interacts_with(&CollisionLayers::PLAYER_PROJECTILE));
+        assert!(FLYING_PLAYER.has(Flying) && !CollisionLayers::PLAYER.has(Flying));
+    }
+
+    #[test]
+    fn layer_mask_round_trips_as_a_list() {
+        let mask = LayerMask::from_layers(&[Ghost, Enemy]);
+        let text = ron::to_string(&mask).unwrap();
+        assert_eq!(text, "[Enemy,Ghost]");
+        assert_eq!(ron::from_str::<LayerMask>(&text).unwrap(), mask);
+
+        let layers: CollisionLayers = ron::from_str("(layers: [Player, Flying], mask: [])").unwrap();
+        assert_eq!(layers, CollisionLayers::new(FLYING_PLAYER.layers, LayerMask::NONE));
+        // Only the known layers are written out
+        let all: LayerMask = ron::from_str(&ron::to_string(&LayerMask::ALL).unwrap()).unwrap();
+        assert_eq!(all, LayerMask::from_layers(&[Player, Enemy, Projectile, Pickup, Flying, Ghost]));
+    }
+}
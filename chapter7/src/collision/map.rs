@@ -1,66 +1,74 @@
 use bevy::prelude::*;
-use super::TileType;
+use super::{LayerMask, TerrainProperties, TileType};
 use pathfinding::prelude::astar;
 
 /// Collision map resource that stores walkability information.
 /// Provides efficient spatial queries for movement validation.
-#[derive(Resource)]
+///
+/// Grid coordinates are anchored to the world (tile (0, 0) starts at the world origin),
+/// so they stay valid while the map grows and shrinks with the loaded chunks.
+#[derive(Resource, Clone)]
 pub struct CollisionMap {
-    /// Flat array of tile types (row-major order)
-    tiles: Vec<TileType>,
+    /// Flat array of tile types (row-major order), `None` where no chunk is loaded
+    tiles: Vec<Option<TileType>>,
+    /// Distance from each tile center to the nearest obstacle (same layout as `tiles`):
+    /// the largest radius of a circle that fits at the center. 0 on blocked tiles.
+    clearance: Vec<f32>,
+    /// Grid coordinates of the bottom-left tile
+    min_x: i32,
+    min_y: i32,
     /// Grid dimensions
     width: i32,
     height: i32,
     /// Size of each tile in world units
     tile_size: f32,
-    /// World position of grid origin (bottom-left corner)
-    origin_x: f32,
-    origin_y: f32,
 }
 
 impl CollisionMap {
-    /// Create a new collision map with specified dimensions and origin.
-    pub fn new(width: i32, height: i32, tile_size: f32, origin_x: f32, origin_y: f32) -> Self {
-        let size = (width * height) as usize;
+    /// Create an empty collision map; regions are added with [`CollisionMap::include_region`].
+    pub fn new(tile_size: f32) -> Self {
         Self {
-            tiles: vec![TileType::Empty; size],
-            width,
-            height,
+            tiles: Vec::new(),
+            clearance: Vec::new(),
+            min_x: 0,
+            min_y: 0,
+            width: 0,
+            height: 0,
             tile_size,
-            origin_x,
-            origin_y,
         }
     }
 
     /// Convert 2D grid coordinates to 1D array index.
     #[inline]
     fn xy_to_idx(&self, x: i32, y: i32) -> usize {
-        (y * self.width + x) as usize
+        ((y - self.min_y) * self.width + (x - self.min_x)) as usize
     }
 
     /// Check if grid coordinates are within bounds.
     #[inline]
     pub fn in_bounds(&self, x: i32, y: i32) -> bool {
-        x >= 0 && x < self.width && y >= 0 && y < self.height
+        x >= self.min_x
+            && x < self.min_x + self.width
+            && y >= self.min_y
+            && y < self.min_y + self.height
     }
 
     pub fn world_to_grid(&self, world_pos: Vec2) -> IVec2 {
-        let grid_x = ((world_pos.x - self.origin_x) / self.tile_size).floor() as i32;
-        let grid_y = ((world_pos.y - self.origin_y) / self.tile_size).floor() as i32;
-        IVec2::new(grid_x, grid_y)
+        (world_pos / self.tile_size).floor().as_ivec2()
     }
 
     /// Convert grid coordinates to world position (tile center).
     pub fn grid_to_world(&self, grid_x: i32, grid_y: i32) -> Vec2 {
         Vec2::new(
-            self.origin_x + (grid_x as f32 + 0.5) * self.tile_size,
-            self.origin_y + (grid_y as f32 + 0.5) * self.tile_size,
+            (grid_x as f32 + 0.5) * self.tile_size,
+            (grid_y as f32 + 0.5) * self.tile_size,
         )
     }
 
+    /// Tile at grid coordinates, `None` outside the loaded area.
     pub fn get_tile(&self, x: i32, y: i32) -> Option<TileType> {
         if self.in_bounds(x, y) {
-            Some(self.tiles[self.xy_to_idx(x, y)])
+            self.tiles[self.xy_to_idx(x, y)]
         } else {
             None
         }
@@ -70,8 +78,84 @@ impl CollisionMap {
     pub fn set_tile(&mut self, x: i32, y: i32, tile_type: TileType) {
         if self.in_bounds(x, y) {
             let idx = self.xy_to_idx(x, y);
-            self.tiles[idx] = tile_type;
+            let previous = self.tiles[idx].replace(tile_type);
+            // Only obstacles move the clearance around
+            if previous.and_then(Self::obstacle_adjustment) != Self::obstacle_adjustment(tile_type) {
+                let cell = IVec2::new(x, y);
+                self.refresh_clearance(cell, cell);
+            }
+        }
+    }
+
+    /// Grow the map so it covers `size` tiles starting at grid coordinates `min`.
+    /// New tiles are `Empty`; tiles already in the map are kept.
+    pub fn include_region(&mut self, min: IVec2, size: IVec2) {
+        let max = min + size;
+        let (new_min, new_max) = if self.width == 0 || self.height == 0 {
+            (min, max)
+        } else {
+            let current_max = IVec2::new(self.min_x + self.width, self.min_y + self.height);
+            (min.min(IVec2::new(self.min_x, self.min_y)), max.max(current_max))
+        };
+        self.resize(new_min, new_max);
+
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let idx = self.xy_to_idx(x, y);
+                self.tiles[idx].get_or_insert(TileType::Empty);
+            }
+        }
+        self.refresh_clearance(min, max - IVec2::ONE);
+    }
+
+    /// Unload `size` tiles starting at grid coordinates `min`, then shrink the map to what is left.
+    pub fn clear_region(&mut self, min: IVec2, size: IVec2) {
+        for y in min.y..min.y + size.y {
+            for x in min.x..min.x + size.x {
+                if self.in_bounds(x, y) {
+                    let idx = self.xy_to_idx(x, y);
+                    self.tiles[idx] = None;
+                }
+            }
+        }
+
+        // Shrink to the bounding box of the remaining tiles
+        let (mut new_min, mut new_max) = (IVec2::MAX, IVec2::MIN);
+        for y in self.min_y..self.min_y + self.height {
+            for x in self.min_x..self.min_x + self.width {
+                if self.get_tile(x, y).is_some() {
+                    new_min = new_min.min(IVec2::new(x, y));
+                    new_max = new_max.max(IVec2::new(x + 1, y + 1));
+                }
+            }
+        }
+        if new_min.x > new_max.x {
+            *self = Self::new(self.tile_size);
+        } else {
+            self.resize(new_min, new_max);
+            self.refresh_clearance(min, min + size - IVec2::ONE);
+        }
+    }
+
+    /// Reallocate the tile array for the bounds `min..max`, copying the overlapping tiles.
+    fn resize(&mut self, min: IVec2, max: IVec2) {
+        let size = max - min;
+        let mut tiles = vec![None; (size.x * size.y) as usize];
+        let mut clearance = vec![0.0; tiles.len()];
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let idx = ((y - min.y) * size.x + (x - min.x)) as usize;
+                tiles[idx] = self.get_tile(x, y);
+                clearance[idx] = self.clearance(x, y);
+            }
         }
+
+        self.tiles = tiles;
+        self.clearance = clearance;
+        self.min_x = min.x;
+        self.min_y = min.y;
+        self.width = size.x;
+        self.height = size.y;
     }
 
     /// Check if a grid position is walkable.
@@ -79,18 +163,85 @@ impl CollisionMap {
         self.get_tile(x, y).map_or(false, |t| t.is_walkable())
     }
 
+    /// Distance from the center of a tile to the nearest obstacle (0 on blocked or
+    /// unloaded tiles). Capped at a couple of tiles: open ground reports the cap.
+    pub fn clearance(&self, x: i32, y: i32) -> f32 {
+        if self.in_bounds(x, y) {
+            self.clearance[self.xy_to_idx(x, y)]
+        } else {
+            0.0
+        }
+    }
+
+    /// Check if a circle of `radius` centered on a tile is walkable and clear of obstacles.
+    pub fn fits(&self, x: i32, y: i32, radius: f32) -> bool {
+        self.is_walkable(x, y) && self.clearance(x, y) >= radius
+    }
+
+    /// How far an obstacle tile widens the circles it collides with (in tiles),
+    /// `None` for walkable tiles.
+    fn obstacle_adjustment(tile: TileType) -> Option<f32> {
+        (!tile.is_walkable()).then(|| tile.collision_adjustment())
+    }
+
+    /// Obstacles considered around each tile by the clearance map, in tiles.
+    const CLEARANCE_REACH: i32 = 2;
+
+    /// Recompute the clearance of every tile whose value can depend on the tiles
+    /// from `min` to `max` (inclusive).
+    fn refresh_clearance(&mut self, min: IVec2, max: IVec2) {
+        let reach = IVec2::splat(Self::CLEARANCE_REACH);
+        let min = (min - reach).max(self.min_cell());
+        let max = (max + reach).min(self.min_cell() + IVec2::new(self.width, self.height) - IVec2::ONE);
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let idx = self.xy_to_idx(x, y);
+                self.clearance[idx] = self.compute_clearance(x, y);
+            }
+        }
+    }
+
+    /// Distance from the center of a tile to the nearest obstacle within reach, taking
+    /// collision adjustments into account. Unloaded tiles count as obstacles.
+    fn compute_clearance(&self, x: i32, y: i32) -> f32 {
+        if !self.is_walkable(x, y) {
+            return 0.0;
+        }
+        let center = self.grid_to_world(x, y);
+        let mut clearance = (Self::CLEARANCE_REACH as f32 + 0.5) * self.tile_size;
+        for gy in y - Self::CLEARANCE_REACH..=y + Self::CLEARANCE_REACH {
+            for gx in x - Self::CLEARANCE_REACH..=x + Self::CLEARANCE_REACH {
+                let adjustment = match self.get_tile(gx, gy) {
+                    Some(tile) if tile.is_walkable() => continue,
+                    Some(tile) => tile.collision_adjustment(),
+                    None => 0.0,
+                };
+                let tile_min = Vec2::new(gx as f32, gy as f32) * self.tile_size;
+                let tile_max = tile_min + Vec2::splat(self.tile_size);
+                let distance = center.distance(center.clamp(tile_min, tile_max));
+                clearance = clearance.min(distance - adjustment * self.tile_size);
+            }
+        }
+        clearance.max(0.0)
+    }
+
     /// Check if a world position is walkable.
     pub fn is_world_pos_walkable(&self, world_pos: Vec2) -> bool {
         let grid_pos = self.world_to_grid(world_pos);
         self.is_walkable(grid_pos.x, grid_pos.y)
     }
 
+    /// Movement speed multiplier at a world position (1.0 outside the map).
+    pub fn speed_multiplier_at(&self, world_pos: Vec2) -> f32 {
+        let grid_pos = self.world_to_grid(world_pos);
+        self.get_tile(grid_pos.x, grid_pos.y)
+            .filter(|tile| tile.is_walkable())
+            .map_or(1.0, |tile| tile.speed_multiplier())
+    }
+
     fn circle_intersects_tile(&self, center: Vec2, radius: f32, gx: i32, gy: i32) -> bool {
         // Tile bounding box
-        let tile_min = Vec2::new(
-            self.origin_x + gx as f32 * self.tile_size,
-            self.origin_y + gy as f32 * self.tile_size,
-        );
+        let tile_min = Vec2::new(gx as f32 * self.tile_size, gy as f32 * self.tile_size);
         let tile_max = tile_min + Vec2::splat(self.tile_size);
 
         // Find closest point on tile to circle center
@@ -104,10 +255,10 @@ impl CollisionMap {
     }
 
     fn is_within_bounds(&self, center: Vec2, radius: f32) -> bool {
-        let left = self.origin_x;
-        let right = self.origin_x + self.width as f32 * self.tile_size;
-        let bottom = self.origin_y;
-        let top = self.origin_y + self.height as f32 * self.tile_size;
+        let left = self.min_x as f32 * self.tile_size;
+        let right = (self.min_x + self.width) as f32 * self.tile_size;
+        let bottom = self.min_y as f32 * self.tile_size;
+        let top = (self.min_y + self.height) as f32 * self.tile_size;
 
         center.x - radius >= left
             && center.x + radius <= right
@@ -127,10 +278,10 @@ impl CollisionMap {
         }
 
         // Find grid cells that could overlap the circle
-        let min_gx = ((center.x - radius - self.origin_x) / self.tile_size).floor() as i32;
-        let max_gx = ((center.x + radius - self.origin_x) / self.tile_size).floor() as i32;
-        let min_gy = ((center.y - radius - self.origin_y) / self.tile_size).floor() as i32;
-        let max_gy = ((center.y + radius - self.origin_y) / self.tile_size).floor() as i32;
+        let min_gx = ((center.x - radius) / self.tile_size).floor() as i32;
+        let max_gx = ((center.x + radius) / self.tile_size).floor() as i32;
+        let min_gy = ((center.y - radius) / self.tile_size).floor() as i32;
+        let max_gy = ((center.y + radius) / self.tile_size).floor() as i32;
 
         for gy in min_gy..=max_gy {
             for gx in min_gx..=max_gx {
@@ -138,85 +289,198 @@ impl CollisionMap {
                     return false;  // Out of bounds = blocked
                 }
 
-                if let Some(tile) = self.get_tile(gx, gy) {
-                    if !tile.is_walkable() {
-                        // Apply tile-specific collision adjustment
-                        let effective_radius = radius + tile.collision_adjustment() * self.tile_size;
-                        
-                        if self.circle_intersects_tile(center, effective_radius, gx, gy) {
-                            return false;
-                        }
-                    }
+                // Unloaded tiles (None) block like out-of-bounds ones
+                let effective_radius = match self.get_tile(gx, gy) {
+                    Some(tile) if tile.is_walkable() => continue,
+                    // Apply tile-specific collision adjustment
+                    Some(tile) => radius + tile.collision_adjustment() * self.tile_size,
+                    None => radius,
+                };
+
+                if self.circle_intersects_tile(center, effective_radius, gx, gy) {
+                    return false;
                 }
             }
         }
         true
     }
 
-    pub fn sweep_circle(&self, start: Vec2, end: Vec2, radius: f32) -> Vec2 {
-        let delta = end - start;
-        
-        // No movement needed
-        if delta.length() < 0.001 {
-            return start;
-        }
-
-        // Step size (quarter tile for smooth collision)
-        let max_step = self.tile_size * 0.25;
-        let steps = (delta.length() / max_step).ceil().max(1.0) as i32;
-        let step_vec = delta / steps as f32;
-
-        let mut pos = start;
-        for _ in 0..steps {
-            let candidate = pos + step_vec;
-
-            if self.is_circle_clear(candidate, radius) {
-                pos = candidate;
-            } else {
-                // Try sliding along X axis only
-                let try_x = Vec2::new(candidate.x, pos.y);
-                if self.is_circle_clear(try_x, radius) {
-                    pos = try_x;
-                    continue;
-                }
+    /// Move a circle from `start` toward `end`, stopping at the first blocking tile and
+    /// sliding along it for the rest of the motion (up to [`Self::SWEEP_ITERATIONS`] contacts).
+    /// Continuous: tiles are never skipped, however long the motion. Tiles the circle already
+    /// overlaps only block motion going further into them, so overlapping circles can get out.
+    pub fn sweep_circle(&self, start: Vec2, end: Vec2, radius: f32) -> Sweep {
+        self.sweep_circle_as(start, end, radius, LayerMask::NONE)
+    }
+
+    /// Same as [`CollisionMap::sweep_circle`], for a circle on collision `layers`
+    /// (flying circles cross the tiles they can fly over).
+    pub fn sweep_circle_as(&self, start: Vec2, end: Vec2, radius: f32, layers: LayerMask) -> Sweep {
+        let mut sweep = Sweep {
+            position: start,
+            hit: None,
+        };
+        let mut remaining = end - start;
+
+        for _ in 0..Self::SWEEP_ITERATIONS {
+            let length = remaining.length();
+            if length < 0.001 {
+                break;
+            }
+
+            let Some(hit) = self.first_contact(sweep.position, remaining, radius, layers) else {
+                sweep.position += remaining;
+                break;
+            };
+
+            // Stop at the contact, just off the surface, then keep the motion along it
+            sweep.position += remaining * hit.time + hit.normal * Self::CONTACT_SKIN;
+            remaining *= 1.0 - hit.time;
+            remaining -= hit.normal * remaining.dot(hit.normal);
+            sweep.hit.get_or_insert(SweepHit {
+                cell: hit.cell,
+                tile: self.get_tile(hit.cell.x, hit.cell.y),
+                normal: hit.normal,
+                position: sweep.position,
+            });
+        }
+        sweep
+    }
+
+    /// Maximum number of slides of one sweep (a corner takes two)
+    const SWEEP_ITERATIONS: usize = 4;
+
+    /// Gap left between a swept circle and the tile it hits (world units)
+    const CONTACT_SKIN: f32 = 0.01;
 
-                // Try sliding along Y axis only
-                let try_y = Vec2::new(pos.x, candidate.y);
-                if self.is_circle_clear(try_y, radius) {
-                    pos = try_y;
+    /// Earliest blocking tile hit by the circle at `position` moving by `motion`.
+    /// Unloaded cells block like obstacles.
+    fn first_contact(&self, position: Vec2, motion: Vec2, radius: f32, layers: LayerMask) -> Option<Contact> {
+        // Every cell the circle's bounding box crosses (obstacles may widen it)
+        let reach = radius + TerrainProperties::max_collision_adjustment().max(0.0) * self.tile_size;
+        let min = ((position.min(position + motion) - reach) / self.tile_size).floor().as_ivec2();
+        let max = ((position.max(position + motion) + reach) / self.tile_size).floor().as_ivec2();
+
+        let mut first: Option<Contact> = None;
+        for gy in min.y..=max.y {
+            for gx in min.x..=max.x {
+                let adjustment = match self.get_tile(gx, gy) {
+                    Some(tile) if !tile.blocks(layers) => continue,
+                    Some(tile) => tile.collision_adjustment(),
+                    None => 0.0,
+                };
+                let tile_min = Vec2::new(gx as f32, gy as f32) * self.tile_size;
+                let tile_max = tile_min + Vec2::splat(self.tile_size);
+                let effective_radius = (radius + adjustment * self.tile_size).max(0.0);
+
+                let Some((time, normal)) =
+                    circle_box_time_of_impact(position, motion, effective_radius, tile_min, tile_max)
+                else {
+                    continue;
+                };
+                // Moving away from (or along) a tile we touch doesn't hit it
+                if motion.dot(normal) >= 0.0 {
                     continue;
                 }
+                if first.as_ref().is_none_or(|first| time < first.time) {
+                    first = Some(Contact {
+                        time,
+                        normal,
+                        cell: IVec2::new(gx, gy),
+                    });
+                }
+            }
+        }
+        first
+    }
 
-                // Completely blocked
-                break;
+    /// Walk the tiles crossed by the segment from `from` to `to` (DDA over the grid) and
+    /// return the first one blocking `blocker`. Unloaded cells block everything.
+    pub fn raycast(&self, from: Vec2, to: Vec2, blocker: RayBlocker) -> Option<RayHit> {
+        let blocks = |cell: IVec2| match self.get_tile(cell.x, cell.y) {
+            None => true,
+            Some(tile) => match blocker {
+                RayBlocker::Movement => !tile.is_walkable(),
+                RayBlocker::Sight => tile.blocks_sight(),
+            },
+        };
+        let hit = |cell: IVec2, point: Vec2, normal: Vec2| RayHit {
+            cell,
+            tile: self.get_tile(cell.x, cell.y),
+            point,
+            normal,
+        };
+
+        let mut cell = self.world_to_grid(from);
+        if blocks(cell) {
+            return Some(hit(cell, from, Vec2::ZERO));
+        }
+
+        let delta = to - from;
+        let end_cell = self.world_to_grid(to);
+        let step = IVec2::new(
+            if delta.x > 0.0 { 1 } else if delta.x < 0.0 { -1 } else { 0 },
+            if delta.y > 0.0 { 1 } else if delta.y < 0.0 { -1 } else { 0 },
+        );
+        // Fraction of the segment where it crosses the next column / row boundary,
+        // and how much more it takes to cross a whole tile
+        let boundary = |axis: usize| {
+            if step[axis] == 0 {
+                return f32::INFINITY;
+            }
+            let edge = (cell[axis] + step[axis].max(0)) as f32 * self.tile_size;
+            (edge - from[axis]) / delta[axis]
+        };
+        let mut next = Vec2::new(boundary(0), boundary(1));
+        let across = self.tile_size / delta.abs();
+
+        while cell != end_cell {
+            let axis = if next.x < next.y { 0 } else { 1 };
+            let time = next[axis];
+            if time > 1.0 {
+                break; // Rounding kept us from landing on the end cell
+            }
+            cell[axis] += step[axis];
+            next[axis] += across[axis];
+
+            if blocks(cell) {
+                let mut normal = Vec2::ZERO;
+                normal[axis] = -step[axis] as f32;
+                return Some(hit(cell, from + delta * time, normal));
             }
         }
-        pos
+        None
+    }
+
+    /// Check if nothing blocks sight between two world positions (see `TileType::blocks_sight`).
+    /// Water blocks walking but not sight, so characters see across lakes.
+    pub fn has_line_of_sight(&self, a: Vec2, b: Vec2) -> bool {
+        self.raycast(a, b, RayBlocker::Sight).is_none()
     }
 
-    #[cfg(debug_assertions)]
     pub fn width(&self) -> i32 { self.width }
     
-    #[cfg(debug_assertions)]
     pub fn height(&self) -> i32 { self.height }
     
     #[cfg(debug_assertions)]
     pub fn tile_size(&self) -> f32 { self.tile_size }
     
-    #[cfg(debug_assertions)]
-    pub fn origin(&self) -> Vec2 { Vec2::new(self.origin_x, self.origin_y) }
+    /// Grid coordinates of the bottom-left tile
+    pub fn min_cell(&self) -> IVec2 { IVec2::new(self.min_x, self.min_y) }
 
-    pub fn get_neighbors(&self, pos: IVec2) -> Vec<IVec2> {
+    /// Cells an agent of `radius` can step to from `pos` (a radius of 0 only needs the
+    /// cells to be walkable).
+    pub fn get_neighbors(&self, pos: IVec2, radius: f32) -> Vec<IVec2> {
         let mut neighbors = Vec::new();
         
-        // Cardinal directions (always allowed if walkable)
+        // Cardinal directions (always allowed if the agent fits)
         let cardinals = [
             IVec2::new(0, 1), IVec2::new(0, -1), IVec2::new(-1, 0), IVec2::new(1, 0),
         ];
         
         for dir in cardinals {
             let neighbor = pos + dir;
-            if self.is_walkable(neighbor.x, neighbor.y) {
+            if self.fits(neighbor.x, neighbor.y, radius) {
                 neighbors.push(neighbor);
             }
         }
@@ -235,10 +499,10 @@ impl CollisionMap {
             let adj1_pos = pos + adj1;
             let adj2_pos = pos + adj2;
             
-            // Only allow diagonal if destination AND both adjacent cells are walkable
-            if self.is_walkable(diag_pos.x, diag_pos.y)
-                && self.is_walkable(adj1_pos.x, adj1_pos.y)
-                && self.is_walkable(adj2_pos.x, adj2_pos.y)
+            // Only allow diagonal if the agent fits in the destination AND both adjacent cells
+            if self.fits(diag_pos.x, diag_pos.y, radius)
+                && self.fits(adj1_pos.x, adj1_pos.y, radius)
+                && self.fits(adj2_pos.x, adj2_pos.y, radius)
             {
                 neighbors.push(diag_pos);
             }
@@ -247,57 +511,131 @@ impl CollisionMap {
         neighbors
     }
     
-    /// Find path using A* algorithm
-    pub fn find_path(&self, start: Vec2, goal: Vec2) -> Option<Vec<Vec2>> {
+    /// Pathfinding cost of stepping between two neighbouring cells. Costs scale with the
+    /// terrain of the cell stepped onto (slow ground costs more).
+    pub fn step_cost(&self, from: IVec2, to: IVec2) -> u32 {
+        let base = if (to.x - from.x).abs() + (to.y - from.y).abs() == 2 {
+            141.0 // Diagonal
+        } else {
+            100.0 // Cardinal
+        };
+        let terrain_cost = self.get_tile(to.x, to.y).map_or(1.0, |tile| tile.properties().path_cost);
+        (base * terrain_cost).round() as u32
+    }
+
+    /// A* over the grid between two cells, for an agent of `radius`: every cell of the
+    /// path, start and goal included. The path only goes through cells where the agent
+    /// fits (the start cell excepted, so an agent pressed against a wall can still leave
+    /// it). A goal the agent doesn't fit in is moved to the nearest cell it does.
+    ///
+    /// Turn the cells into waypoints with [`CollisionMap::smooth_path`].
+    pub fn find_cell_path(&self, start: IVec2, goal: IVec2, radius: f32) -> Option<Vec<IVec2>> {
         use pathfinding::prelude::astar;
         
-        let start_grid = self.world_to_grid(start);
-        let goal_grid = self.world_to_grid(goal);
-        
-        if !self.is_walkable(start_grid.x, start_grid.y) {
+        if !self.is_walkable(start.x, start.y) {
             return None;
         }
         
-        let actual_goal = if self.is_walkable(goal_grid.x, goal_grid.y) {
-            goal_grid
+        let actual_goal = if self.fits(goal.x, goal.y, radius) {
+            goal
         } else {
-            self.find_nearest_walkable(goal_grid)?
+            self.find_nearest_clear(goal, radius)?
         };
         
+        let cheapest = TerrainProperties::cheapest_path_cost();
+
         let result = astar(
-            &start_grid,
+            &start,
             |pos| {
                 let pos = *pos;
-                self.get_neighbors(pos).into_iter().map(move |n| {
-                    let cost = if (n.x - pos.x).abs() + (n.y - pos.y).abs() == 2 {
-                        14u32 // Diagonal
-                    } else {
-                        10u32 // Cardinal
-                    };
-                    (n, cost)
-                })
+                self.get_neighbors(pos, radius)
+                    .into_iter()
+                    .map(move |n| (n, self.step_cost(pos, n)))
             },
             |pos| {
+                // Octile distance: diagonal steps for the shorter axis, straight ones for
+                // the rest, on the cheapest terrain - never more than the real cost
                 let dx = (pos.x - actual_goal.x).abs();
                 let dy = (pos.y - actual_goal.y).abs();
-                ((dx + dy) * 10) as u32
+                let steps = 100 * dx.max(dy) + 41 * dx.min(dy);
+                (steps as f32 * cheapest).floor() as u32
             },
             |pos| *pos == actual_goal,
         );
         
-        result.map(|(path, _cost)| {
-            path.into_iter().map(|p| self.grid_to_world(p.x, p.y)).collect()
+        result.map(|(path, _cost)| path)
+    }
+
+    /// Turn a path of cells into waypoints, starting at `start` (in the first cell), and
+    /// drop the waypoints an agent of `radius` can cut straight past (string pulling).
+    /// A shortcut is only taken when the agent can sweep along it without touching an
+    /// obstacle, and without crossing terrain slower than the cells it skips.
+    pub fn smooth_path(&self, start: Vec2, cells: &[IVec2], radius: f32) -> Vec<Vec2> {
+        let mut waypoints = vec![start];
+        let Some(&last) = cells.last() else {
+            return waypoints;
+        };
+        if cells.len() == 1 {
+            waypoints.push(self.grid_to_world(last.x, last.y));
+            return waypoints;
+        }
+
+        let center = |index: usize| self.grid_to_world(cells[index].x, cells[index].y);
+        let path_cost = |cell: IVec2| {
+            self.get_tile(cell.x, cell.y)
+                .map_or(1.0, |tile| tile.properties().path_cost)
+        };
+
+        // Walk from the last kept waypoint as far down the path as a straight line allows
+        let mut anchor = start;
+        let mut slowest = path_cost(cells[0]);
+        for index in 1..cells.len() {
+            slowest = slowest.max(path_cost(cells[index]));
+            let target = center(index);
+            if !self.is_shortcut(anchor, target, radius, slowest) {
+                // Keep the previous cell, the last one reachable in a straight line
+                anchor = center(index - 1);
+                waypoints.push(anchor);
+                slowest = path_cost(cells[index - 1]).max(path_cost(cells[index]));
+            }
+        }
+        waypoints.push(center(cells.len() - 1));
+        waypoints
+    }
+
+    /// Check if an agent of `radius` can walk straight from `from` to `to`: nothing blocks
+    /// it and no cell along the way is slower to cross than `max_path_cost`.
+    fn is_shortcut(&self, from: Vec2, to: Vec2, radius: f32, max_path_cost: f32) -> bool {
+        if self.sweep_circle(from, to, radius).hit.is_some() {
+            return false;
+        }
+
+        self.cells_along(from, to).all(|cell| {
+            self.get_tile(cell.x, cell.y)
+                .is_some_and(|tile| tile.properties().path_cost <= max_path_cost)
         })
     }
+
+    /// Cells under the segment from `from` to `to`, sampled a few times per tile (a cell
+    /// may come up more than once).
+    pub fn cells_along(&self, from: Vec2, to: Vec2) -> impl Iterator<Item = IVec2> + '_ {
+        let samples = ((from.distance(to) / (self.tile_size * 0.25)).ceil() as i32).max(1);
+        (0..=samples).map(move |sample| self.world_to_grid(from.lerp(to, sample as f32 / samples as f32)))
+    }
     
     /// Find nearest walkable cell
     pub fn find_nearest_walkable(&self, pos: IVec2) -> Option<IVec2> {
-        for radius in 1i32..10 {
-            for dx in -radius..=radius {
-                for dy in -radius..=radius {
-                    if dx.abs() == radius || dy.abs() == radius {
+        self.find_nearest_clear(pos, 0.0)
+    }
+
+    /// Find nearest cell (other than `pos`) where an agent of `radius` fits
+    pub fn find_nearest_clear(&self, pos: IVec2, radius: f32) -> Option<IVec2> {
+        for ring in 1i32..10 {
+            for dx in -ring..=ring {
+                for dy in -ring..=ring {
+                    if dx.abs() == ring || dy.abs() == ring {
                         let check = IVec2::new(pos.x + dx, pos.y + dy);
-                        if self.is_walkable(check.x, check.y) {
+                        if self.fits(check.x, check.y, radius) {
                             return Some(check);
                         }
                     }
@@ -308,4 +646,252 @@ impl CollisionMap {
     }
 
 
-}
\ No newline at end of file
+}
+
+/// What stops a [`CollisionMap::raycast`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RayBlocker {
+    /// Tiles characters can't walk through (projectiles, movement checks)
+    Movement,
+    /// Tiles characters can't see through (line of sight)
+    Sight,
+}
+
+/// First blocking tile found by [`CollisionMap::raycast`].
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    /// Grid coordinates of the tile
+    pub cell: IVec2,
+    /// Type of the tile, `None` for the edge of the loaded area
+    pub tile: Option<TileType>,
+    /// Where the ray enters the tile (the start of the ray if it starts inside)
+    pub point: Vec2,
+    /// Unit normal of the side the ray enters through, zero if it starts inside
+    pub normal: Vec2,
+}
+
+/// Result of [`CollisionMap::sweep_circle`].
+#[derive(Debug, Clone, Copy)]
+pub struct Sweep {
+    /// Where the circle ended up
+    pub position: Vec2,
+    /// First tile the circle ran into, if any
+    pub hit: Option<SweepHit>,
+}
+
+/// Contact between a swept circle and a blocking tile, for gameplay to react to
+/// (bump sounds, projectile impacts...).
+#[derive(Debug, Clone, Copy)]
+pub struct SweepHit {
+    /// Grid coordinates of the tile
+    pub cell: IVec2,
+    /// Type of the tile, `None` for the edge of the loaded area
+    pub tile: Option<TileType>,
+    /// Unit normal of the tile's surface at the contact, pointing toward the circle
+    pub normal: Vec2,
+    /// Circle center at the contact
+    pub position: Vec2,
+}
+
+struct Contact {
+    /// Fraction of the motion done when the circle touches the tile
+    time: f32,
+    normal: Vec2,
+    cell: IVec2,
+}
+
+/// When a circle at `position` moving by `motion` first touches the box `min` - `max`:
+/// the fraction of the motion (0 to 1) and the box's surface normal there. A circle
+/// already overlapping the box touches it at 0, with the normal pushing it out.
+fn circle_box_time_of_impact(
+    position: Vec2,
+    motion: Vec2,
+    radius: f32,
+    min: Vec2,
+    max: Vec2,
+) -> Option<(f32, Vec2)> {
+    let closest = position.clamp(min, max);
+    let offset = position - closest;
+    if offset.length_squared() <= radius * radius {
+        return Some((0.0, overlap_normal(position, offset, min, max)));
+    }
+
+    // Enter the box grown by the radius (slab test)...
+    let (grown_min, grown_max) = (min - radius, max + radius);
+    let mut enter = 0.0_f32;
+    let mut exit = 1.0_f32;
+    let mut normal = Vec2::ZERO;
+    for axis in 0..2 {
+        if motion[axis] == 0.0 {
+            if position[axis] < grown_min[axis] || position[axis] > grown_max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let (mut near, mut far) = (
+            (grown_min[axis] - position[axis]) / motion[axis],
+            (grown_max[axis] - position[axis]) / motion[axis],
+        );
+        if near > far {
+            std::mem::swap(&mut near, &mut far);
+        }
+        if near > enter {
+            enter = near;
+            normal = Vec2::ZERO;
+            normal[axis] = -motion[axis].signum();
+        }
+        exit = exit.min(far);
+        if enter > exit {
+            return None;
+        }
+    }
+
+    // ...where its corners are rounded: past a corner, hit the corner's circle instead
+    let point = position + motion * enter;
+    let outside = point.cmplt(min) | point.cmpgt(max);
+    if !outside.all() {
+        return Some((enter, normal));
+    }
+    let corner = Vec2::select(point.cmplt(min), min, max);
+    let time = ray_circle_time(position - corner, motion, radius)?;
+    (time <= 1.0).then(|| (time, (position + motion * time - corner).normalize_or(normal)))
+}
+
+/// First time (from 0) a point at `offset` from a circle's center, moving by `motion`
+/// per unit of time, reaches the circle.
+fn ray_circle_time(offset: Vec2, motion: Vec2, radius: f32) -> Option<f32> {
+    let a = motion.length_squared();
+    let b = offset.dot(motion);
+    let c = offset.length_squared() - radius * radius;
+    let discriminant = b * b - a * c;
+    if a == 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let time = (-b - discriminant.sqrt()) / a;
+    (time >= 0.0).then_some(time)
+}
+
+/// Normal pushing a circle at `position` out of a box it overlaps. `offset` goes from the
+/// closest point of the box to the center (zero when the center is inside the box).
+fn overlap_normal(position: Vec2, offset: Vec2, min: Vec2, max: Vec2) -> Vec2 {
+    if offset != Vec2::ZERO {
+        return offset.normalize();
+    }
+    // Center inside: out through the nearest side
+    let sides = [
+        (position.x - min.x, Vec2::NEG_X),
+        (max.x - position.x, Vec2::X),
+        (position.y - min.y, Vec2::NEG_Y),
+        (max.y - position.y, Vec2::Y),
+    ];
+    sides
+        .into_iter()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map_or(Vec2::Y, |(_, normal)| normal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::CollisionLayer;
+
+    const TILE: f32 = 16.0;
+
+    /// 10 x 10 loaded tiles with a column of water at x = 5.
+    fn map_with_water_wall() -> CollisionMap {
+        let mut map = CollisionMap::new(TILE);
+        map.include_region(IVec2::ZERO, IVec2::splat(10));
+        for y in 0..10 {
+            map.set_tile(5, y, TileType::Water);
+        }
+        map
+    }
+
+    #[test]
+    fn time_of_impact_hits_rounded_corner() {
+        // The grown box is entered at 0.5, but its corner is rounded: the circle only
+        // touches the box's corner once it is `radius` away from it
+        let (time, normal) =
+            circle_box_time_of_impact(Vec2::splat(-1.0), Vec2::ONE, 0.5, Vec2::ZERO, Vec2::ONE).unwrap();
+        assert!((time - (1.0 - 0.5 / 2.0_f32.sqrt())).abs() < 1e-4, "time {time}");
+        assert!(normal.abs_diff_eq(Vec2::NEG_ONE.normalize(), 1e-4), "normal {normal}");
+
+        // Passing diagonally by the corner, inside the grown box but outside the rounded corner
+        let miss =
+            circle_box_time_of_impact(Vec2::new(-1.28, 0.72), Vec2::new(2.0, -2.0), 0.3, Vec2::ZERO, Vec2::ONE);
+        assert!(miss.is_none(), "{miss:?}");
+    }
+
+    #[test]
+    fn time_of_impact_starting_overlap_pushes_out() {
+        // Center outside the box, overlapping its top side
+        let (time, normal) =
+            circle_box_time_of_impact(Vec2::new(0.5, 1.2), Vec2::X, 0.5, Vec2::ZERO, Vec2::ONE).unwrap();
+        assert_eq!(time, 0.0);
+        assert_eq!(normal, Vec2::Y);
+
+        // Center inside the box, nearest the right side
+        let (time, normal) =
+            circle_box_time_of_impact(Vec2::new(0.9, 0.5), Vec2::Y, 0.5, Vec2::ZERO, Vec2::ONE).unwrap();
+        assert_eq!(time, 0.0);
+        assert_eq!(normal, Vec2::X);
+    }
+
+    #[test]
+    fn sweep_does_not_tunnel_at_high_speed() {
+        let map = map_with_water_wall();
+        let radius = 4.0;
+        let start = map.grid_to_world(2, 5);
+
+        // Motion far longer than a tile, ending past the wall
+        for end_x in [150.0, 1.0e5] {
+            let sweep = map.sweep_circle(start, Vec2::new(end_x, start.y), radius);
+            let hit = sweep.hit.expect("the wall stops the circle");
+            assert_eq!(hit.cell.x, 5);
+            assert_eq!(hit.tile, Some(TileType::Water));
+            assert_eq!(hit.normal, Vec2::NEG_X);
+            assert!(sweep.position.x <= 5.0 * TILE - radius, "went through: {}", sweep.position);
+            assert!(sweep.position.x > 5.0 * TILE - radius - 0.1, "stopped early: {}", sweep.position);
+        }
+    }
+
+    #[test]
+    fn sweep_slides_along_wall() {
+        let map = map_with_water_wall();
+        let start = map.grid_to_world(2, 2);
+        let sweep = map.sweep_circle(start, start + Vec2::new(100.0, 50.0), 4.0);
+
+        assert_eq!(sweep.hit.map(|hit| hit.normal), Some(Vec2::NEG_X));
+        assert!((sweep.position.y - (start.y + 50.0)).abs() < 1e-3, "lost the slide: {}", sweep.position);
+    }
+
+    #[test]
+    fn sweep_as_flying_crosses_water() {
+        let map = map_with_water_wall();
+        let start = map.grid_to_world(2, 5);
+        let end = map.grid_to_world(8, 5);
+        let flying = LayerMask::from_layers(&[CollisionLayer::Flying]);
+
+        let sweep = map.sweep_circle_as(start, end, 4.0, flying);
+        assert!(sweep.hit.is_none());
+        assert_eq!(sweep.position, end);
+
+        // The edge of the loaded area still stops it
+        let sweep = map.sweep_circle_as(start, Vec2::new(1.0e4, start.y), 4.0, flying);
+        assert_eq!(sweep.hit.map(|hit| hit.tile), Some(None));
+    }
+
+    #[test]
+    fn circle_over_unloaded_gap_is_blocked() {
+        // Two loaded blocks with an unloaded column (x = 4) between them
+        let mut map = CollisionMap::new(TILE);
+        map.include_region(IVec2::ZERO, IVec2::new(4, 10));
+        map.include_region(IVec2::new(5, 0), IVec2::new(5, 10));
+        assert_eq!(map.get_tile(4, 3), None);
+
+        assert!(map.is_circle_clear(map.grid_to_world(2, 3), 4.0));
+        assert!(!map.is_circle_clear(map.grid_to_world(4, 3), 4.0));
+        // Overlapping the gap from the loaded side is blocked as well
+        assert!(!map.is_circle_clear(map.grid_to_world(3, 3) + Vec2::new(6.0, 0.0), 4.0));
+    }
+}
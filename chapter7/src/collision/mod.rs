@@ -1,17 +1,33 @@
 mod tile_type;
 mod map;
 mod systems;
+mod connectivity;
+mod flow_field;
+mod layers;
+mod path_cache;
+mod path_tasks;
+mod zones;
+pub mod spatial;
 
 #[cfg(debug_assertions)]
 mod debug;
 
 use bevy::prelude::*;
+use crate::config::map::TILE_SIZE;
+use crate::map::chunks::TerrainGenerator;
 use crate::state::GameState;
 
 // Re-export commonly used types
-pub use tile_type::{TileType, TileMarker};
-pub use map::CollisionMap;
-pub use systems::CollisionMapBuilt;
+pub use tile_type::{TerrainProperties, TileType, TileMarker};
+pub use map::{CollisionMap, RayBlocker, RayHit, Sweep, SweepHit};
+pub use systems::{water_edge_tile, CollisionMapBuilt, CollisionMapChanged};
+pub use connectivity::{ConnectivityPolicy, RegionStats, SkipConnectivity};
+pub use spatial::{SpatialIndex, SpatialKind};
+pub use flow_field::FlowField;
+pub use layers::{CollisionLayer, CollisionLayers, LayerMask};
+pub use path_cache::{PathCache, PathKey};
+pub use path_tasks::{PathFound, PathRequest, PathTask};
+pub use zones::{TriggerZone, ZoneEntered, ZoneExited, ZoneShape};
 
 #[cfg(debug_assertions)]
 pub use debug::DebugCollisionEnabled;
@@ -21,10 +37,45 @@ pub struct CollisionPlugin;
 impl Plugin for CollisionPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CollisionMapBuilt>()
+            .init_resource::<SkipConnectivity>()
+            .add_message::<CollisionMapChanged>()
+            // Starts empty and follows the loaded chunks
+            .insert_resource(CollisionMap::new(TILE_SIZE))
+            // Filled by the plugins owning the indexed components (see `spatial::index_shapes`)
+            .insert_resource(SpatialIndex::new(TILE_SIZE))
+            .init_resource::<PathCache>()
+            .init_resource::<path_tasks::PathfindingSnapshot>()
+            .add_message::<PathFound>()
+            .add_message::<ZoneEntered>()
+            .add_message::<ZoneExited>()
+            // Zones are placed by their global transform (tile entities are children of chunks)
+            .add_systems(PostUpdate, zones::update_trigger_zones.after(TransformSystems::Propagate))
             .add_systems(
                 Update,
-                systems::build_collision_map
-                    .run_if(resource_equals(CollisionMapBuilt(false)))
+                (
+                    (
+                        // Chunks loaded this frame are read right away
+                        systems::sync_collision_map.after(crate::map::chunks::stream_chunks),
+                        systems::patch_edited_tiles
+                            .after(crate::map::edit::apply_terrain_edits)
+                            .after(systems::sync_collision_map),
+                    )
+                        .run_if(resource_exists::<TerrainGenerator>),
+                    // Check the spawn area before anything spawns on it
+                    connectivity::check_connectivity
+                        .after(systems::sync_collision_map)
+                        .run_if(resource_equals(CollisionMapBuilt(false)))
+                        .run_if(resource_exists::<TerrainGenerator>),
+                    // Paths are planned on the map as it is after this frame's changes
+                    (
+                        path_cache::clear_stale_paths,
+                        path_tasks::finish_path_tasks,
+                        path_tasks::start_path_tasks,
+                    )
+                        .chain()
+                        .after(systems::sync_collision_map)
+                        .after(systems::patch_edited_tiles),
+                )
                     .run_if(in_state(GameState::Playing)),
             );
 
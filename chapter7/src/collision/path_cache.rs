@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use super::CollisionMap;
+
+/// Cells and agent radius a path is planned for, as stored in the [`PathCache`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PathKey {
+    pub start: IVec2,
+    pub goal: IVec2,
+    /// Bits of the `f32` radius, so the key can be hashed
+    radius: u32,
+}
+
+impl PathKey {
+    pub fn new(map: &CollisionMap, start: Vec2, goal: Vec2, radius: f32) -> Self {
+        Self {
+            start: map.world_to_grid(start),
+            goal: map.world_to_grid(goal),
+            radius: radius.to_bits(),
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        f32::from_bits(self.radius)
+    }
+}
+
+/// Paths found by [`CollisionMap::find_cell_path`], keyed by start cell, goal cell and
+/// agent radius, so agents planning from the same area toward the same goal share one A*.
+/// Unreachable goals are cached too. Cleared whenever the collision map changes.
+#[derive(Resource, Default)]
+pub struct PathCache {
+    paths: HashMap<PathKey, Option<Vec<IVec2>>>,
+    /// Bumped on every clear, so searches started before can tell their result is outdated
+    generation: u32,
+}
+
+impl PathCache {
+    /// Paths kept at most; the cache starts over once it is full.
+    const CAPACITY: usize = 256;
+
+    /// Cached cell path: `None` if it isn't cached, `Some(None)` if the goal is known to
+    /// be unreachable.
+    pub fn get(&self, key: &PathKey) -> Option<Option<&[IVec2]>> {
+        self.paths.get(key).map(Option::as_deref)
+    }
+
+    /// Cache the result of [`CollisionMap::find_cell_path`] for `key`.
+    pub fn insert(&mut self, key: PathKey, cells: Option<Vec<IVec2>>) {
+        if !self.paths.contains_key(&key) && self.paths.len() >= Self::CAPACITY {
+            self.paths.clear();
+        }
+        self.paths.insert(key, cells);
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Number of times the cache was cleared.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Forget every path.
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.generation = self.generation.wrapping_add(1);
+    }
+}
+
+/// Empty the [`PathCache`] when the collision map changed (chunks loaded or unloaded,
+/// terrain edits), as any cached path may now be blocked or beaten by a shorter one.
+pub fn clear_stale_paths(map: Res<CollisionMap>, mut cache: ResMut<PathCache>) {
+    if map.is_changed() {
+        cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE: f32 = 16.0;
+
+    #[test]
+    fn map_changes_clear_the_cache_and_bump_the_generation() {
+        let mut app = App::new();
+        app.insert_resource(CollisionMap::new(TILE))
+            .init_resource::<PathCache>()
+            .add_systems(Update, clear_stale_paths);
+        // The map is new on the first run
+        app.update();
+        let generation = app.world().resource::<PathCache>().generation();
+
+        let map = app.world().resource::<CollisionMap>();
+        let found = PathKey::new(map, Vec2::ZERO, Vec2::splat(40.0), 4.0);
+        let unreachable = PathKey::new(map, Vec2::ZERO, Vec2::splat(-40.0), 4.0);
+        assert_eq!(found.radius(), 4.0);
+        let mut cache = app.world_mut().resource_mut::<PathCache>();
+        cache.insert(found, Some(vec![IVec2::ZERO, IVec2::ONE, IVec2::splat(2)]));
+        cache.insert(unreachable, None);
+
+        // Nothing changed: the paths are kept
+        app.update();
+        let cache = app.world().resource::<PathCache>();
+        assert_eq!(cache.generation(), generation);
+        assert_eq!(cache.get(&found), Some(Some(&[IVec2::ZERO, IVec2::ONE, IVec2::splat(2)][..])));
+        assert_eq!(cache.get(&unreachable), Some(None));
+
+        app.world_mut()
+            .resource_mut::<CollisionMap>()
+            .include_region(IVec2::ZERO, IVec2::splat(4));
+        app.update();
+        let cache = app.world().resource::<PathCache>();
+        assert_eq!(cache.generation(), generation + 1);
+        assert!(cache.is_empty());
+        assert_eq!(cache.get(&found), None);
+    }
+}
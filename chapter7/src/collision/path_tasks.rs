@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+
+use super::path_cache::{PathCache, PathKey};
+use super::CollisionMap;
+use crate::config::pathfinding::PATH_TASKS_PER_FRAME;
+
+/// Ask for a path to be planned for this entity, off the main thread (see
+/// [`CollisionMap::find_cell_path`]). The request is taken by [`start_path_tasks`] and the
+/// result comes back as a [`PathFound`] message. A new request replaces the one in
+/// progress.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PathRequest {
+    pub start: Vec2,
+    pub goal: Vec2,
+    /// Radius of the agent (see [`CollisionMap::find_cell_path`])
+    pub radius: f32,
+}
+
+/// Sent when the path asked for with a [`PathRequest`] is ready: the waypoints, or
+/// `None` if the goal can't be reached.
+#[derive(Message, Debug, Clone)]
+pub struct PathFound {
+    pub entity: Entity,
+    pub path: Option<Vec<Vec2>>,
+}
+
+/// Search started for an entity, on the [`AsyncComputeTaskPool`].
+#[derive(Component)]
+pub struct PathTask {
+    key: PathKey,
+    task: Task<PlannedPath>,
+}
+
+/// What a [`PathTask`] sends back.
+struct PlannedPath {
+    key: PathKey,
+    /// [`PathCache`] generation the search started in
+    generation: u32,
+    cells: Option<Vec<IVec2>>,
+    path: Option<Vec<Vec2>>,
+}
+
+/// Copy of the collision map shared with the running searches, taken again when the
+/// map changes.
+#[derive(Resource, Default)]
+pub struct PathfindingSnapshot(Option<Arc<CollisionMap>>);
+
+/// Answer the [`PathRequest`]s: from the [`PathCache`] right away when it has the path,
+/// otherwise by starting a search on a snapshot of the collision map. Requests between
+/// the same cells as a running search wait for its result in the cache instead of
+/// starting another one. At most `PATH_TASKS_PER_FRAME` searches start per frame; the
+/// other requests wait.
+pub fn start_path_tasks(
+    mut commands: Commands,
+    map: Res<CollisionMap>,
+    mut snapshot: ResMut<PathfindingSnapshot>,
+    cache: Res<PathCache>,
+    requests: Query<(Entity, &PathRequest)>,
+    running: Query<&PathTask>,
+    mut found: MessageWriter<PathFound>,
+) {
+    if map.is_changed() || snapshot.0.is_none() {
+        snapshot.0 = Some(Arc::new(map.clone()));
+    }
+    let Some(snapshot) = &snapshot.0 else {
+        return;
+    };
+
+    let pool = AsyncComputeTaskPool::get();
+    let mut searching: HashSet<PathKey> = running.iter().map(|task| task.key).collect();
+    let mut started = 0;
+    for (entity, request) in &requests {
+        let key = PathKey::new(&map, request.start, request.goal, request.radius);
+
+        if let Some(cells) = cache.get(&key) {
+            let path = cells.map(|cells| map.smooth_path(request.start, cells, request.radius));
+            found.write(PathFound { entity, path });
+            commands.entity(entity).remove::<(PathRequest, PathTask)>();
+            continue;
+        }
+
+        if searching.contains(&key) || started == PATH_TASKS_PER_FRAME {
+            continue; // Keep the request for a later frame
+        }
+        searching.insert(key);
+        started += 1;
+
+        let (snapshot, request, generation) = (Arc::clone(snapshot), *request, cache.generation());
+        let task = pool.spawn(async move {
+            let cells = snapshot.find_cell_path(key.start, key.goal, key.radius());
+            let path = cells
+                .as_deref()
+                .map(|cells| snapshot.smooth_path(request.start, cells, request.radius));
+            PlannedPath {
+                key,
+                generation,
+                cells,
+                path,
+            }
+        });
+        commands
+            .entity(entity)
+            .remove::<PathRequest>()
+            .insert(PathTask { key, task });
+    }
+}
+
+/// Collect the finished searches: send their [`PathFound`] and cache their path, unless
+/// the collision map changed since they started.
+pub fn finish_path_tasks(
+    mut commands: Commands,
+    mut cache: ResMut<PathCache>,
+    mut tasks: Query<(Entity, &mut PathTask)>,
+    mut found: MessageWriter<PathFound>,
+) {
+    for (entity, mut task) in &mut tasks {
+        let Some(planned) = block_on(poll_once(&mut task.task)) else {
+            continue;
+        };
+        commands.entity(entity).remove::<PathTask>();
+
+        if planned.generation == cache.generation() {
+            cache.insert(planned.key, planned.cells);
+        }
+        found.write(PathFound {
+            entity,
+            path: planned.path,
+        });
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use super::CollisionLayers;
+
+/// What an entity of the [`SpatialIndex`] is, so queries can keep the ones they want.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpatialKind {
+    /// Characters with a `Collider` (player and enemies)
+    Character,
+    /// Items the player can pick up
+    Item,
+}
+
+/// Component kept in the [`SpatialIndex`] by [`index_shapes`], as a circle.
+pub trait SpatialShape: Component {
+    const KIND: SpatialKind;
+
+    /// Center and radius of the circle covered by the entity.
+    fn circle(&self, transform: &GlobalTransform) -> (Vec2, f32);
+
+    /// Collision layers of the entity, for queries to skip what they don't collide with.
+    fn layers(&self) -> CollisionLayers;
+}
+
+/// One entity of the [`SpatialIndex`], as of the last transform propagation.
+#[derive(Debug, Clone, Copy)]
+pub struct SpatialEntry {
+    pub entity: Entity,
+    pub kind: SpatialKind,
+    pub position: Vec2,
+    pub radius: f32,
+    pub layers: CollisionLayers,
+    /// Cells covered by the circle's bounding box (inclusive)
+    min_cell: IVec2,
+    max_cell: IVec2,
+}
+
+/// Broad-phase index of the entities in the world: a uniform grid with the cells of
+/// `CollisionMap` (cell (0, 0) starts at the world origin). Each entity is stored in every
+/// cell its circle overlaps, so queries only look at the cells around them.
+#[derive(Resource)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<Entity>>,
+    entries: HashMap<Entity, SpatialEntry>,
+}
+
+impl SpatialIndex {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            entries: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    /// Add an entity, or move it if it is already indexed.
+    pub fn insert(
+        &mut self,
+        entity: Entity,
+        kind: SpatialKind,
+        position: Vec2,
+        radius: f32,
+        layers: CollisionLayers,
+    ) {
+        let min_cell = self.cell(position - Vec2::splat(radius));
+        let max_cell = self.cell(position + Vec2::splat(radius));
+        let entry = SpatialEntry {
+            entity,
+            kind,
+            position,
+            radius,
+            layers,
+            min_cell,
+            max_cell,
+        };
+
+        if let Some(previous) = self.entries.insert(entity, entry) {
+            if previous.min_cell == min_cell && previous.max_cell == max_cell {
+                return; // Still in the same cells
+            }
+            self.unlink(&previous);
+        }
+        for y in min_cell.y..=max_cell.y {
+            for x in min_cell.x..=max_cell.x {
+                self.cells.entry(IVec2::new(x, y)).or_default().push(entity);
+            }
+        }
+    }
+
+    /// Remove an entity (no-op if it isn't indexed).
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(entry) = self.entries.remove(&entity) {
+            self.unlink(&entry);
+        }
+    }
+
+    /// Remove an entry from the cells it covers, dropping cells left empty.
+    fn unlink(&mut self, entry: &SpatialEntry) {
+        for y in entry.min_cell.y..=entry.max_cell.y {
+            for x in entry.min_cell.x..=entry.max_cell.x {
+                let cell = IVec2::new(x, y);
+                let Some(entities) = self.cells.get_mut(&cell) else {
+                    continue;
+                };
+                if let Some(index) = entities.iter().position(|other| *other == entry.entity) {
+                    entities.swap_remove(index);
+                }
+                if entities.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&SpatialEntry> {
+        self.entries.get(&entity)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries whose circle overlaps the circle at `center` (a radius of 0 gives the
+    /// entries containing `center`).
+    pub fn query_radius(&self, center: Vec2, radius: f32) -> impl Iterator<Item = &SpatialEntry> + '_ {
+        self.query_cells(center - Vec2::splat(radius), center + Vec2::splat(radius))
+            .filter(move |entry| {
+                let reach = radius + entry.radius;
+                entry.position.distance_squared(center) <= reach * reach
+            })
+    }
+
+    /// Entries whose circle overlaps the box between `min` and `max`.
+    pub fn query_aabb(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = &SpatialEntry> + '_ {
+        self.query_cells(min, max).filter(move |entry| {
+            let closest = entry.position.clamp(min, max);
+            entry.position.distance_squared(closest) <= entry.radius * entry.radius
+        })
+    }
+
+    /// Entries in the cells overlapping the box between `min` and `max`, each one once.
+    fn query_cells(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = &SpatialEntry> + '_ {
+        let (min_cell, max_cell) = (self.cell(min), self.cell(max));
+        (min_cell.y..=max_cell.y)
+            .flat_map(move |y| (min_cell.x..=max_cell.x).map(move |x| IVec2::new(x, y)))
+            .filter_map(|cell| Some((cell, self.cells.get(&cell)?)))
+            .flat_map(move |(cell, entities)| {
+                entities.iter().filter_map(move |entity| {
+                    let entry = self.entries.get(entity)?;
+                    // An entry spanning several of the cells is only reported from the first
+                    (entry.min_cell.max(min_cell) == cell).then_some(entry)
+                })
+            })
+    }
+}
+
+/// Entities holding `T` that moved, or whose `T` changed, since the last update.
+type MovedShapes<'w, 's, T> =
+    Query<'w, 's, (Entity, &'static T, &'static GlobalTransform), Or<(Changed<T>, Changed<GlobalTransform>)>>;
+
+/// Keep the [`SpatialIndex`] up to date with the entities holding `T`: moved or
+/// changed ones are re-indexed, removed or despawned ones dropped.
+/// Runs after transform propagation, so the index matches the transforms of the frame.
+pub fn index_shapes<T: SpatialShape>(
+    mut index: ResMut<SpatialIndex>,
+    shapes: MovedShapes<T>,
+    mut removed: RemovedComponents<T>,
+) {
+    for entity in removed.read() {
+        index.remove(entity);
+    }
+    for (entity, shape, transform) in &shapes {
+        let (position, radius) = shape.circle(transform);
+        index.insert(entity, T::KIND, position, radius, shape.layers());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CELL: f32 = 10.0;
+
+    fn entities<'a>(entries: impl Iterator<Item = &'a SpatialEntry>) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = entries.map(|entry| entry.entity).collect();
+        entities.sort_by_key(|entity| entity.index());
+        entities
+    }
+
+    #[test]
+    fn entry_spanning_cells_is_reported_once() {
+        let mut index = SpatialIndex::new(CELL);
+        let big = Entity::from_raw_u32(1).unwrap();
+        let small = Entity::from_raw_u32(2).unwrap();
+        // Covers cells (-2, -2) to (1, 1)
+        index.insert(big, SpatialKind::Character, Vec2::ZERO, 12.0, CollisionLayers::ENEMY);
+        index.insert(small, SpatialKind::Item, Vec2::new(5.0, 5.0), 1.0, CollisionLayers::PICKUP);
+
+        assert_eq!(entities(index.query_aabb(Vec2::splat(-15.0), Vec2::splat(15.0))), vec![big, small]);
+        assert_eq!(entities(index.query_radius(Vec2::new(-11.0, 0.0), 0.0)), vec![big]);
+        // The query box starts past the big entry's first cell
+        assert_eq!(entities(index.query_aabb(Vec2::splat(1.0), Vec2::splat(9.0))), vec![big, small]);
+    }
+
+    #[test]
+    fn moving_entry_leaves_its_old_cells() {
+        let mut index = SpatialIndex::new(CELL);
+        let entity = Entity::from_raw_u32(1).unwrap();
+        index.insert(entity, SpatialKind::Character, Vec2::splat(5.0), 2.0, CollisionLayers::PLAYER);
+        // Moving within the same cell keeps it linked once
+        index.insert(entity, SpatialKind::Character, Vec2::splat(6.0), 2.0, CollisionLayers::PLAYER);
+        assert_eq!(index.cells[&IVec2::ZERO], vec![entity]);
+
+        index.insert(entity, SpatialKind::Character, Vec2::new(35.0, 5.0), 2.0, CollisionLayers::PLAYER);
+        assert_eq!(index.len(), 1);
+        assert!(!index.cells.contains_key(&IVec2::ZERO), "old cell left behind");
+        assert_eq!(index.query_radius(Vec2::splat(5.0), 4.0).count(), 0);
+        assert_eq!(index.get(entity).map(|entry| entry.position), Some(Vec2::new(35.0, 5.0)));
+        assert_eq!(index.query_radius(Vec2::new(33.0, 5.0), 1.0).count(), 1);
+
+        index.remove(entity);
+        assert!(index.is_empty());
+        assert!(index.cells.is_empty());
+    }
+}
@@ -1,127 +1,169 @@
 use bevy::prelude::*;
-use std::collections::{HashMap, hash_map::Entry};
 
-use super::{CollisionMap, TileMarker, TileType};
-use crate::config::map::{TILE_SIZE, GRID_X, GRID_Y};
+use super::{CollisionMap, TileType};
+use crate::config::map::CHUNK_SIZE;
+use crate::map::chunks::{chunk_min_cell, ChunkLoaded, ChunkUnloaded, TerrainGenerator, WorldChunks};
+use crate::map::edit::TerrainChanged;
+use crate::map::tiles::{tile_reach, tile_types};
 
 /// Resource to track if collision map has been built.
+/// Becomes true once every chunk around the spawn point is in the map
+/// and the connectivity pass accepted it (see `check_connectivity`).
 #[derive(Resource, Default, PartialEq, Eq)]
 pub struct CollisionMapBuilt(pub bool);
 
-pub fn build_collision_map(
-    mut commands: Commands,
-    mut built: ResMut<CollisionMapBuilt>,
-    tile_query: Query<(&TileMarker, &Transform)>,
+/// Keep the collision map in sync with the spawned chunks: loaded chunks are added
+/// (growing the map if needed) and unloaded chunks are removed (shrinking it).
+/// Tiles come from the generated nodes, once per chunk load, so they don't depend on
+/// the spawned sprites.
+pub fn sync_collision_map(
+    mut map: ResMut<CollisionMap>,
+    chunks: Res<WorldChunks>,
+    generator: Res<TerrainGenerator>,
+    mut chunk_loaded: MessageReader<ChunkLoaded>,
+    mut chunk_unloaded: MessageReader<ChunkUnloaded>,
 ) {
-    // Need at least one tile to proceed
-    let mut tile_iter = tile_query.iter();
-    let Some((first_marker, first_transform)) = tile_iter.next() else {
-        return; // WFC hasn't generated tiles yet
-    };
-
-    // Calculate grid origin (centered map)
-    let grid_origin_x = -TILE_SIZE * GRID_X as f32 / 2.0;
-    let grid_origin_y = -TILE_SIZE * GRID_Y as f32 / 2.0;
-
-    // Track bounds and layer info
-    let (mut min_x, mut max_x) = (i32::MAX, i32::MIN);
-    let (mut min_y, mut max_y) = (i32::MAX, i32::MIN);
-    let mut layer_tracker: HashMap<(i32, i32), (TileType, f32)> = HashMap::new();
-    let mut tile_count: usize = 0;
-
-    // Process all tiles, keeping only the topmost at each position
-    let mut process_tile = |marker: &TileMarker, transform: &Transform| {
-        tile_count += 1;
-
-        let world_x = transform.translation.x;
-        let world_y = transform.translation.y;
-        let world_z = transform.translation.z;
-        
-        let grid_x = ((world_x - grid_origin_x) / TILE_SIZE).floor() as i32;
-        let grid_y = ((world_y - grid_origin_y) / TILE_SIZE).floor() as i32;
-
-        min_x = min_x.min(grid_x);
-        max_x = max_x.max(grid_x);
-        min_y = min_y.min(grid_y);
-        max_y = max_y.max(grid_y);
-
-        // Keep only the topmost layer (highest Z)
-        match layer_tracker.entry((grid_x, grid_y)) {
-            Entry::Occupied(mut entry) => {
-                if world_z > entry.get().1 {
-                    *entry.get_mut() = (marker.tile_type, world_z);
-                }
-            }
-            Entry::Vacant(entry) => {
-                entry.insert((marker.tile_type, world_z));
-            }
+    let chunk_size = IVec2::splat(CHUNK_SIZE as i32);
+
+    for ChunkUnloaded { coord } in chunk_unloaded.read() {
+        let min = chunk_min_cell(*coord);
+        map.clear_region(min, chunk_size);
+        // Water next to the removed chunk is no longer next to land
+        convert_water_edges_to_shore(&mut map, min - IVec2::ONE, min + chunk_size);
+    }
+
+    for ChunkLoaded { coord } in chunk_loaded.read() {
+        if chunks.loaded(*coord).is_none() {
+            continue; // Already unloaded again
         }
-    };
 
-    // Process first tile and remaining
-    process_tile(first_marker, first_transform);
-    for (marker, transform) in tile_iter {
-        process_tile(marker, transform);
+        let min = chunk_min_cell(*coord);
+        map.include_region(min, chunk_size);
+
+        // Assets overhanging from this chunk may cover the edges of the loaded neighbours
+        let reach = IVec2::splat(tile_reach(&generator.assets_definitions));
+        refresh_tiles(&mut map, &chunks, &generator, min - reach, max_cell(min, chunk_size) + reach);
+
+        // Post-processing: Convert water edges to shore, including the neighbours' edges
+        convert_water_edges_to_shore(&mut map, min - IVec2::ONE, min + chunk_size);
     }
+}
 
-    // Calculate actual dimensions
-    let actual_width = (max_x - min_x + 1) as i32;
-    let actual_height = (max_y - min_y + 1) as i32;
-
-    // Create the collision map
-    let mut map = CollisionMap::new(
-        actual_width,
-        actual_height,
-        TILE_SIZE,
-        grid_origin_x,
-        grid_origin_y,
+/// Last cell of the rectangle of `size` cells starting at `min`.
+fn max_cell(min: IVec2, size: IVec2) -> IVec2 {
+    min + size - IVec2::ONE
+}
+
+/// Set the tiles between `min` and `max` (inclusive) from the generated nodes.
+/// Cells of chunks that aren't loaded are left alone.
+fn refresh_tiles(
+    map: &mut CollisionMap,
+    chunks: &WorldChunks,
+    generator: &TerrainGenerator,
+    min: IVec2,
+    max: IVec2,
+) {
+    let size = (max - min + IVec2::ONE).as_uvec2();
+    let tiles = tile_types(
+        chunks.generated(),
+        generator.chunks.layers,
+        &generator.assets_definitions,
+        min,
+        size,
     );
 
-    // Populate the map from layer tracker
-    for ((grid_x, grid_y), (tile_type, _z)) in layer_tracker.iter() {
-        // Convert world grid to local array coordinates
-        let local_x = grid_x - min_x;
-        let local_y = grid_y - min_y;
-        map.set_tile(local_x, local_y, *tile_type);
+    let chunk_size = IVec2::splat(CHUNK_SIZE as i32);
+    for (index, tile_type) in tiles.into_iter().enumerate() {
+        let cell = min + IVec2::new(index as i32 % size.x as i32, index as i32 / size.x as i32);
+        if chunks.loaded(cell.div_euclid(chunk_size)).is_some() {
+            map.set_tile(cell.x, cell.y, tile_type.unwrap_or_default());
+        }
+    }
+}
+
+/// Sent when tiles of the collision map change after it was built (terrain edits).
+/// Paths going through these cells are stale.
+#[derive(Message, Debug, Clone)]
+pub struct CollisionMapChanged {
+    pub cells: Vec<IVec2>,
+}
+
+/// Patch the collision map where the terrain was edited, from the edited nodes,
+/// then let pathfinding users know.
+pub fn patch_edited_tiles(
+    mut map: ResMut<CollisionMap>,
+    chunks: Res<WorldChunks>,
+    generator: Res<TerrainGenerator>,
+    mut terrain_changed: MessageReader<TerrainChanged>,
+    mut collision_changed: MessageWriter<CollisionMapChanged>,
+) {
+    for TerrainChanged { cells } in terrain_changed.read() {
+        let Some(first) = cells.first() else {
+            continue;
+        };
+        let (min, max) = cells
+            .iter()
+            .fold((*first, *first), |(min, max), cell| (min.min(*cell), max.max(*cell)));
+
+        refresh_tiles(&mut map, &chunks, &generator, min, max);
+        // Water around the edit may have gained or lost its shore
+        convert_water_edges_to_shore(&mut map, min - IVec2::ONE, max + IVec2::ONE);
+
+        let mut changed = cells.clone();
+        changed.extend(shore_neighbours(&map, min, max));
+        collision_changed.write(CollisionMapChanged { cells: changed });
     }
+}
 
-    // Post-processing: Convert water edges to shore
-    convert_water_edges_to_shore(&mut map);
-    // Insert as resource and mark built
-    commands.insert_resource(map);
-    built.0 = true;
+/// Water and shore cells in the ring around `min` - `max` (their type may have flipped).
+fn shore_neighbours(map: &CollisionMap, min: IVec2, max: IVec2) -> Vec<IVec2> {
+    let (outer_min, outer_max) = (min - IVec2::ONE, max + IVec2::ONE);
+    (outer_min.y..=outer_max.y)
+        .flat_map(|y| (outer_min.x..=outer_max.x).map(move |x| IVec2::new(x, y)))
+        .filter(|cell| cell.cmplt(min).any() || cell.cmpgt(max).any())
+        .filter(|cell| matches!(map.get_tile(cell.x, cell.y), Some(TileType::Water | TileType::Shore)))
+        .collect()
 }
 
-fn convert_water_edges_to_shore(map: &mut CollisionMap) {
-    let mut shores = Vec::new();
+/// Tile a water or shore tile at `cell` becomes: shore if it touches walkable land, water
+/// otherwise. `None` for any other tile. Shore counts as water here, so the result
+/// doesn't depend on what was converted before.
+pub fn water_edge_tile(tile_at: impl Fn(IVec2) -> Option<TileType>, cell: IVec2) -> Option<TileType> {
+    if !matches!(tile_at(cell), Some(TileType::Water | TileType::Shore)) {
+        return None;
+    }
 
-    // Find water tiles that touch walkable tiles
-    for y in 0..map.height() {
-        for x in 0..map.width() {
-            if map.get_tile(x, y) != Some(TileType::Water) {
-                continue;
-            }
+    let is_land = |cell: IVec2| tile_at(cell).is_some_and(|tile| tile.is_walkable() && tile != TileType::Shore);
+
+    // Check 8 neighbors
+    let (x, y) = (cell.x, cell.y);
+    let neighbors = [
+        (x - 1, y),     (x + 1, y),     // left, right
+        (x, y - 1),     (x, y + 1),     // down, up
+        (x - 1, y - 1), (x + 1, y - 1), // bottom corners
+        (x - 1, y + 1), (x + 1, y + 1), // top corners
+    ];
 
-            // Check 8 neighbors
-            let neighbors = [
-                (x - 1, y),     (x + 1, y),     // left, right
-                (x, y - 1),     (x, y + 1),     // down, up
-                (x - 1, y - 1), (x + 1, y - 1), // bottom corners
-                (x - 1, y + 1), (x + 1, y + 1), // top corners
-            ];
-
-            for (nx, ny) in neighbors {
-                if map.is_walkable(nx, ny) {
-                    shores.push((x, y));
-                    break;
-                }
+    let touches_land = neighbors.iter().any(|&(nx, ny)| is_land(IVec2::new(nx, ny)));
+    Some(if touches_land { TileType::Shore } else { TileType::Water })
+}
+
+/// Turn water tiles touching land into shore, for tiles between `min` and `max` (inclusive).
+/// Existing shore tiles are checked again, so this can be re-run when the land around changes.
+fn convert_water_edges_to_shore(map: &mut CollisionMap, min: IVec2, max: IVec2) {
+    let tile_at = |cell: IVec2| map.get_tile(cell.x, cell.y);
+    let mut changes = Vec::new();
+
+    // Find water tiles that touch walkable tiles
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            if let Some(tile_type) = water_edge_tile(tile_at, IVec2::new(x, y)) {
+                changes.push((x, y, tile_type));
             }
         }
     }
 
-    for (x, y) in shores {
-        map.set_tile(x, y, TileType::Shore);
+    for (x, y, tile_type) in changes {
+        map.set_tile(x, y, tile_type);
     }
-
-
-}
\ No newline at end of file
+}
@@ -1,8 +1,12 @@
 use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::{CollisionLayer, LayerMask};
+use crate::config::terrain::TERRAIN;
 
 /// Tile types for collision detection.
-/// Each type has different walkability and collision behavior.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+/// Each type has different walkability and collision behavior (see `config::terrain`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
 pub enum TileType {
     // Walkable terrain
     #[default]
@@ -11,24 +15,143 @@ pub enum TileType {
     Grass,
     YellowGrass,
     Shore,  // Water edges (walkable)
+    /// Placeholder: no rule or asset produces it yet (there is no mud tile in the atlas)
+    Mud,
     // Non-walkable obstacles
     Water,
     Tree,
     Rock,
 }
 
+/// Movement and sight properties of a tile type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainProperties {
+    pub tile_type: TileType,
+    /// Characters can walk on it
+    pub walkable: bool,
+    /// Characters can't see through it (see `CollisionMap::has_line_of_sight`)
+    pub blocks_sight: bool,
+    /// Characters on the `Flying` layer can cross it even if it isn't walkable
+    pub fly_over: bool,
+    /// Movement speed multiplier while walking on it (1.0 = normal)
+    pub speed: f32,
+    /// Pathfinding cost of crossing the tile, relative to a road (1.0). Slower ground
+    /// costs more, so paths stick to roads.
+    pub path_cost: f32,
+    /// Positive = push player away, negative = allow corner cutting
+    pub collision_adjustment: f32,
+}
+
+impl TerrainProperties {
+    /// Ground characters walk on at `speed` times their normal speed. Its path cost
+    /// follows the speed (`1.0 / speed`) unless set with [`TerrainProperties::path_cost`].
+    pub const fn walkable(tile_type: TileType, speed: f32) -> Self {
+        Self {
+            tile_type,
+            walkable: true,
+            blocks_sight: false,
+            fly_over: false,
+            speed,
+            path_cost: 1.0 / speed,
+            collision_adjustment: 0.0,
+        }
+    }
+
+    /// Obstacle characters can't walk or see through.
+    pub const fn obstacle(tile_type: TileType, collision_adjustment: f32) -> Self {
+        Self {
+            tile_type,
+            walkable: false,
+            blocks_sight: true,
+            fly_over: false,
+            speed: 0.0,
+            path_cost: f32::INFINITY,
+            collision_adjustment,
+        }
+    }
+
+    /// Same properties, with another pathfinding cost (e.g. ground as fast as a road,
+    /// but that paths should still avoid).
+    pub const fn path_cost(self, path_cost: f32) -> Self {
+        Self { path_cost, ..self }
+    }
+
+    /// Same properties, but characters can see across the tile (e.g. water).
+    pub const fn see_through(self) -> Self {
+        Self { blocks_sight: false, ..self }
+    }
+
+    /// Same properties, but flying characters can cross the tile (e.g. water).
+    pub const fn fly_over(self) -> Self {
+        Self { fly_over: true, ..self }
+    }
+
+    /// Lowest path cost of any walkable tile (keeps the A* heuristic from overestimating).
+    pub fn cheapest_path_cost() -> f32 {
+        TERRAIN
+            .iter()
+            .filter(|properties| properties.walkable)
+            .map(|properties| properties.path_cost)
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// Largest collision adjustment of any obstacle (how far obstacles can reach past their tile).
+    pub fn max_collision_adjustment() -> f32 {
+        TERRAIN
+            .iter()
+            .filter(|properties| !properties.walkable)
+            .map(|properties| properties.collision_adjustment)
+            .fold(f32::NEG_INFINITY, f32::max)
+    }
+}
+
 impl TileType {
+    /// Movement properties of this tile type.
+    pub fn properties(&self) -> &'static TerrainProperties {
+        &TERRAIN[*self as usize]
+    }
+
     /// Check if this tile type allows movement through it.
     pub fn is_walkable(&self) -> bool {
-        !matches!(self, TileType::Water | TileType::Tree | TileType::Rock)
+        self.properties().walkable
+    }
+
+    /// Check if this tile type stops a character on `layers` (flying characters cross
+    /// the tiles marked `fly_over`).
+    pub fn blocks(&self, layers: LayerMask) -> bool {
+        let properties = self.properties();
+        let flies_over = properties.fly_over && layers.contains(CollisionLayer::Flying);
+        !(properties.walkable || flies_over)
+    }
+
+    /// Check if this tile type hides what is behind it.
+    pub fn blocks_sight(&self) -> bool {
+        self.properties().blocks_sight
+    }
+
+    /// Movement speed multiplier on this tile type.
+    pub fn speed_multiplier(&self) -> f32 {
+        self.properties().speed
     }
 
     /// Get the collision adjustment for this tile type.
     /// Positive = push player away, negative = allow corner cutting.
     pub fn collision_adjustment(&self) -> f32 {
+        self.properties().collision_adjustment
+    }
+
+    /// Character used for this tile type in ASCII map exports.
+    pub fn ascii(&self) -> char {
         match self {
-            TileType::Tree | TileType::Rock => -0.2,  // Allow cutting corners
-            _ => 0.0,
+            TileType::Empty => '.',
+            TileType::Dirt => ':',
+            TileType::Grass => ',',
+            TileType::YellowGrass => ';',
+            TileType::Shore => '-',
+            TileType::Mud => '%',
+            TileType::Water => '~',
+            TileType::Tree => 'T',
+            TileType::Rock => '#',
         }
     }
 }
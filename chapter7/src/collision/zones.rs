@@ -0,0 +1,220 @@
+use bevy::ecs::lifecycle::HookContext;
+use bevy::ecs::world::DeferredWorld;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::LayerMask;
+use crate::characters::collider::Collider;
+use crate::config::map::TILE_SIZE;
+
+/// Area covered by a [`TriggerZone`], around the zone entity's position.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ZoneShape {
+    Circle { radius: f32 },
+    Rect { half_size: Vec2 },
+    /// Grid cells (see `CollisionMap`), as offsets from the cell the zone is in
+    Cells(Vec<IVec2>),
+}
+
+impl ZoneShape {
+    /// Check if `point` is inside the shape placed at `center`.
+    pub fn contains(&self, center: Vec2, point: Vec2) -> bool {
+        match self {
+            ZoneShape::Circle { radius } => center.distance_squared(point) <= radius * radius,
+            ZoneShape::Rect { half_size } => {
+                let offset = (point - center).abs();
+                offset.x <= half_size.x && offset.y <= half_size.y
+            }
+            ZoneShape::Cells(cells) => {
+                let cell = (point / TILE_SIZE).floor().as_ivec2() - (center / TILE_SIZE).floor().as_ivec2();
+                cells.contains(&cell)
+            }
+        }
+    }
+}
+
+/// Area sending [`ZoneEntered`] and [`ZoneExited`] when a character's collider
+/// (`Collider::world_position`) moves in or out of it.
+///
+/// Placed from code (spawned with a `Transform`), with the assets of a model (see
+/// `AssetRule::zone`), or from a saved map (see `MapZone`). Characters still inside when
+/// the zone goes away (e.g. unloaded with its chunk) get a [`ZoneExited`].
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[component(on_remove = exit_removed_zone)]
+pub struct TriggerZone {
+    /// What the zone is for (e.g. "shore", "graveyard", "exit"), for the systems reacting to it
+    pub name: String,
+    pub shape: ZoneShape,
+    /// Only colliders on one of these layers trigger the zone
+    #[serde(default = "all_layers")]
+    pub layers: LayerMask,
+    /// Characters inside the zone as of the last update
+    #[serde(skip)]
+    occupants: Vec<Entity>,
+}
+
+fn all_layers() -> LayerMask {
+    LayerMask::ALL
+}
+
+impl TriggerZone {
+    pub fn new(name: impl Into<String>, shape: ZoneShape) -> Self {
+        Self {
+            name: name.into(),
+            shape,
+            layers: LayerMask::ALL,
+            occupants: Vec::new(),
+        }
+    }
+
+    pub fn with_layers(mut self, layers: LayerMask) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Characters inside the zone.
+    pub fn occupants(&self) -> &[Entity] {
+        &self.occupants
+    }
+}
+
+/// Sent when a character walks into a [`TriggerZone`].
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ZoneEntered {
+    pub entity: Entity,
+    pub zone: Entity,
+}
+
+/// Sent when a character leaves a [`TriggerZone`], or is despawned inside it.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ZoneExited {
+    pub entity: Entity,
+    pub zone: Entity,
+}
+
+/// Send a [`ZoneExited`] for every character left inside a zone being removed or despawned.
+fn exit_removed_zone(mut world: DeferredWorld, context: HookContext) {
+    let Some(zone) = world.get::<TriggerZone>(context.entity) else {
+        return;
+    };
+    if zone.occupants.is_empty() {
+        return;
+    }
+    let exits: Vec<ZoneExited> = zone
+        .occupants
+        .iter()
+        .map(|&entity| ZoneExited {
+            entity,
+            zone: context.entity,
+        })
+        .collect();
+    world.write_message_batch(exits);
+}
+
+/// Check which characters are inside each zone, and report the ones that came or left.
+pub fn update_trigger_zones(
+    mut zones: Query<(Entity, &GlobalTransform, &mut TriggerZone)>,
+    colliders: Query<(Entity, &Transform, &Collider)>,
+    mut entered: MessageWriter<ZoneEntered>,
+    mut exited: MessageWriter<ZoneExited>,
+) {
+    for (zone_entity, zone_transform, mut zone) in &mut zones {
+        let center = zone_transform.translation().truncate();
+        let occupants: Vec<Entity> = colliders
+            .iter()
+            .filter(|(_, _, collider)| zone.layers.intersects(collider.layers.layers))
+            .filter(|(_, transform, collider)| zone.shape.contains(center, collider.world_position(transform)))
+            .map(|(entity, _, _)| entity)
+            .collect();
+        if occupants == zone.occupants {
+            continue;
+        }
+
+        for &entity in occupants.iter().filter(|entity| !zone.occupants.contains(entity)) {
+            entered.write(ZoneEntered { entity, zone: zone_entity });
+        }
+        for &entity in zone.occupants.iter().filter(|entity| !occupants.contains(entity)) {
+            exited.write(ZoneExited { entity, zone: zone_entity });
+        }
+        zone.occupants = occupants;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::{CollisionLayer, CollisionLayers};
+
+    #[test]
+    fn cells_are_offsets_from_the_zone_cell() {
+        let shape = ZoneShape::Cells(vec![IVec2::ZERO, IVec2::new(1, 0)]);
+        let center = Vec2::splat(TILE_SIZE * 0.5);
+
+        assert!(shape.contains(center, Vec2::new(TILE_SIZE * 0.1, TILE_SIZE * 0.9)));
+        assert!(shape.contains(center, Vec2::new(TILE_SIZE * 1.9, TILE_SIZE * 0.5)));
+        assert!(!shape.contains(center, Vec2::new(TILE_SIZE * 0.5, TILE_SIZE * 1.5)));
+        assert!(!shape.contains(center, Vec2::new(TILE_SIZE * 2.0, 0.0)));
+        // Left of and below the origin, cells round down
+        let center = Vec2::splat(-TILE_SIZE * 0.5);
+        assert!(shape.contains(center, Vec2::new(-1.0, -1.0)));
+        assert!(!shape.contains(center, Vec2::new(-TILE_SIZE * 1.5, -1.0)));
+    }
+
+    /// Character and zone of the entered messages sent since the last call.
+    fn entered(app: &mut App) -> Vec<(Entity, Entity)> {
+        let mut messages = app.world_mut().resource_mut::<Messages<ZoneEntered>>();
+        messages.drain().map(|message| (message.entity, message.zone)).collect()
+    }
+
+    /// Character and zone of the exited messages sent since the last call.
+    fn exited(app: &mut App) -> Vec<(Entity, Entity)> {
+        let mut messages = app.world_mut().resource_mut::<Messages<ZoneExited>>();
+        messages.drain().map(|message| (message.entity, message.zone)).collect()
+    }
+
+    #[test]
+    fn reports_characters_coming_and_leaving() {
+        let mut app = App::new();
+        app.add_message::<ZoneEntered>()
+            .add_message::<ZoneExited>()
+            .add_systems(Update, update_trigger_zones);
+
+        let zone = TriggerZone::new("exit", ZoneShape::Circle { radius: 50.0 })
+            .with_layers(LayerMask::from_layers(&[CollisionLayer::Player]));
+        let zone = app.world_mut().spawn((zone, GlobalTransform::default())).id();
+        let collider = |layers| Collider {
+            radius: 10.0,
+            offset: Vec2::new(0.0, -20.0),
+            layers,
+        };
+        let player = app
+            .world_mut()
+            .spawn((Transform::from_xyz(0.0, 60.0, 0.0), collider(CollisionLayers::PLAYER)))
+            .id();
+        app.world_mut()
+            .spawn((Transform::from_xyz(0.0, 0.0, 0.0), collider(CollisionLayers::ENEMY)));
+
+        // The collider's feet are inside, and enemies don't count
+        app.update();
+        assert_eq!(entered(&mut app), vec![(player, zone)]);
+        assert_eq!(app.world().get::<TriggerZone>(zone).unwrap().occupants(), &[player]);
+
+        // Staying in sends nothing
+        app.world_mut().get_mut::<Transform>(player).unwrap().translation.x = 10.0;
+        app.update();
+        assert!(entered(&mut app).is_empty());
+        assert!(exited(&mut app).is_empty());
+
+        app.world_mut().get_mut::<Transform>(player).unwrap().translation.x = 100.0;
+        app.update();
+        assert!(entered(&mut app).is_empty());
+        assert_eq!(exited(&mut app), vec![(player, zone)]);
+
+        // Back in, then the zone goes away with the player inside
+        app.world_mut().get_mut::<Transform>(player).unwrap().translation.x = 0.0;
+        app.update();
+        assert_eq!(entered(&mut app), vec![(player, zone)]);
+        app.world_mut().despawn(zone);
+        assert_eq!(exited(&mut app), vec![(player, zone)]);
+    }
+}
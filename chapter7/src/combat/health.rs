@@ -0,0 +1,218 @@
+use super::power_type::PowerType;
+use super::projectile::ProjectileHit;
+use crate::characters::input::Player;
+use crate::config::combat::INVULNERABILITY_SECONDS;
+use crate::state::GameState;
+use bevy::prelude::*;
+
+/// Hit points of a character, starting at `CharacterEntry::max_health`.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    /// Seconds left during which hits are ignored, after taking one
+    invulnerable: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            invulnerable: 0.0,
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable > 0.0
+    }
+
+    /// Share of the maximum left (0.0 - 1.0)
+    pub fn fraction(&self) -> f32 {
+        if self.max > 0.0 {
+            (self.current / self.max).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Take a hit and become invulnerable for `INVULNERABILITY_SECONDS`.
+    /// Returns the damage actually taken: none while invulnerable or dead.
+    pub fn damage(&mut self, amount: f32) -> f32 {
+        if self.is_dead() || self.is_invulnerable() || amount <= 0.0 {
+            return 0.0;
+        }
+        let taken = amount.min(self.current);
+        self.current -= taken;
+        self.invulnerable = INVULNERABILITY_SECONDS;
+        taken
+    }
+
+    pub fn heal(&mut self, amount: f32) {
+        if !self.is_dead() {
+            self.current = (self.current + amount).min(self.max);
+        }
+    }
+
+    /// Change the maximum (e.g. when switching character), keeping the same share of it.
+    pub fn set_max(&mut self, max: f32) {
+        self.current = self.fraction() * max;
+        self.max = max;
+    }
+}
+
+/// Damage dealt to a character with [`Health`].
+#[derive(Message, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    /// Who dealt it, if anyone (e.g. the character that shot the projectile)
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: f32,
+    pub power_type: PowerType,
+}
+
+/// Turn projectile hits into damage, depending on the power of the projectile.
+pub fn damage_from_projectiles(
+    mut hits: MessageReader<ProjectileHit>,
+    mut damage: MessageWriter<DamageEvent>,
+) {
+    for hit in hits.read() {
+        damage.write(DamageEvent {
+            source: Some(hit.source),
+            target: hit.target,
+            amount: hit.power_type.damage(),
+            power_type: hit.power_type,
+        });
+    }
+}
+
+/// Take the damage off the targets' health. Hits landing while a target is invulnerable
+/// are ignored.
+pub fn apply_damage(mut damage: MessageReader<DamageEvent>, mut targets: Query<&mut Health>) {
+    for event in damage.read() {
+        let Ok(mut health) = targets.get_mut(event.target) else {
+            continue;
+        };
+        let taken = health.damage(event.amount);
+        if taken > 0.0 {
+            info!(
+                "{:?} took {} {:?} damage ({}/{})",
+                event.target, taken, event.power_type, health.current, health.max
+            );
+        }
+    }
+}
+
+/// Count down the invulnerability after a hit, blinking the sprite meanwhile. The countdown
+/// doesn't mark `Health` as changed, so `Changed<Health>` only sees hits and heals.
+/// The dead stop blinking right away.
+pub fn tick_invulnerability(time: Res<Time>, mut query: Query<(&mut Health, Option<&mut Sprite>)>) {
+    for (mut health, sprite) in query.iter_mut() {
+        if !health.is_invulnerable() {
+            continue;
+        }
+        let health = health.bypass_change_detection();
+        health.invulnerable = if health.is_dead() {
+            0.0
+        } else {
+            (health.invulnerable - time.delta_secs()).max(0.0)
+        };
+
+        if let Some(mut sprite) = sprite {
+            let visible = !health.is_invulnerable() || (health.invulnerable * 10.0).fract() < 0.5;
+            sprite.color.set_alpha(if visible { 1.0 } else { 0.35 });
+        }
+    }
+}
+
+/// Despawn dead enemies; the player dying ends the game.
+pub fn handle_deaths(
+    mut commands: Commands,
+    query: Query<(Entity, &Health, Has<Player>), Changed<Health>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (entity, health, is_player) in query.iter() {
+        if !health.is_dead() {
+            continue;
+        }
+        if is_player {
+            info!("Player died");
+            next_state.set(GameState::GameOver);
+        } else {
+            info!("{:?} died", entity);
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn damage_is_clamped_and_ignored_while_invulnerable() {
+        let mut health = Health::new(50.0);
+        assert_eq!(health.damage(20.0), 20.0);
+        assert!(health.is_invulnerable());
+        assert_eq!(health.damage(20.0), 0.0);
+        assert_eq!(health.current, 30.0);
+
+        // Only what is left can be taken, and nothing once dead
+        health.invulnerable = 0.0;
+        assert_eq!(health.damage(100.0), 30.0);
+        assert_eq!(health.current, 0.0);
+        assert!(health.is_dead());
+        health.invulnerable = 0.0;
+        assert_eq!(health.damage(10.0), 0.0);
+        health.heal(10.0);
+        assert_eq!(health.fraction(), 0.0);
+
+        let mut health = Health::new(50.0);
+        assert_eq!(health.damage(-5.0), 0.0);
+        assert!(!health.is_invulnerable());
+        health.damage(10.0);
+        health.heal(100.0);
+        assert_eq!(health.current, 50.0);
+    }
+
+    /// Times `Health` was seen changed.
+    #[derive(Resource, Default)]
+    struct Changes(u32);
+
+    #[test]
+    fn countdown_does_not_mark_health_changed() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<Changes>()
+            .add_systems(
+                Update,
+                (tick_invulnerability, |query: Query<(), Changed<Health>>, mut changes: ResMut<Changes>| {
+                    changes.0 += query.iter().count() as u32;
+                })
+                    .chain(),
+            );
+
+        let mut blinking = Health::new(50.0);
+        blinking.damage(10.0);
+        let mut dead = Health::new(50.0);
+        dead.damage(100.0);
+        let dimmed = || Sprite::from_color(Color::WHITE.with_alpha(0.35), Vec2::ONE);
+        let blinking = app.world_mut().spawn((blinking, dimmed())).id();
+        let dead = app.world_mut().spawn((dead, dimmed())).id();
+
+        // Both are new on the first update, then only the timers move
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<Changes>().0, 2);
+        assert!(app.world().get::<Health>(blinking).unwrap().is_invulnerable());
+
+        let dead_health = app.world().get::<Health>(dead).unwrap();
+        assert!(dead_health.is_dead() && !dead_health.is_invulnerable());
+        assert_eq!(app.world().get::<Sprite>(dead).unwrap().color.alpha(), 1.0);
+    }
+}
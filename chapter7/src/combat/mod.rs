@@ -1,18 +1,42 @@
 // src/combat/mod.rs
+mod health;
 mod player_combat;
 mod power_type;
+mod projectile;
 pub mod systems;
 
+pub use health::{DamageEvent, Health};
 pub use player_combat::PlayerCombat;
 pub use power_type::{PowerType, PowerVisuals};
+pub use projectile::{Projectile, ProjectileHit};
 pub use systems::{debug_switch_power, handle_power_input, spawn_projectile};
 
+use crate::state::GameState;
 use bevy::prelude::*;
 
 pub struct CombatPlugin;
 
 impl Plugin for CombatPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (handle_power_input, debug_switch_power));
+        app.add_message::<ProjectileHit>()
+            .add_message::<DamageEvent>()
+            .add_systems(
+                Update,
+                (handle_power_input, debug_switch_power, projectile::move_projectiles)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            // Damage, invulnerability and death, once the projectiles have hit
+            .add_systems(
+                Update,
+                (
+                    health::damage_from_projectiles,
+                    health::apply_damage,
+                    health::tick_invulnerability,
+                    health::handle_deaths,
+                )
+                    .chain()
+                    .after(projectile::move_projectiles)
+                    .run_if(in_state(GameState::Playing)),
+            );
     }
 }
\ No newline at end of file
@@ -30,6 +30,16 @@ impl PowerType {
         }
     }
 
+    /// Health taken by a projectile of this power
+    pub fn damage(&self) -> f32 {
+        match self {
+            PowerType::Fire => 20.0,
+            PowerType::Arcane => 15.0,
+            PowerType::Shadow => 12.0,
+            PowerType::Poison => 10.0,
+        }
+    }
+
     fn fire_visuals(direction: Vec3) -> PowerVisuals {
         PowerVisuals {
             primary: ParticleConfig {
@@ -0,0 +1,106 @@
+use super::power_type::PowerType;
+use crate::collision::{CollisionLayers, CollisionMap, RayBlocker, SpatialIndex, SpatialKind};
+use bevy::prelude::*;
+
+/// Hitbox of a projectile, flying straight along with its particles. It stops at the
+/// first character it collides with (see `CollisionLayers`), at tiles blocking sight,
+/// or when its lifetime runs out.
+#[derive(Component, Debug, Clone)]
+pub struct Projectile {
+    /// Character that shot it
+    pub source: Entity,
+    pub power_type: PowerType,
+    /// World units per second
+    pub velocity: Vec2,
+    pub radius: f32,
+    /// Who shot it decides who it hits (e.g. `CollisionLayers::ENEMY_PROJECTILE`)
+    pub layers: CollisionLayers,
+    /// Seconds left before it fades out
+    pub lifetime: f32,
+}
+
+/// Sent when a projectile hits a character.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ProjectileHit {
+    pub projectile: Entity,
+    pub source: Entity,
+    pub target: Entity,
+    pub power_type: PowerType,
+}
+
+/// Move the projectiles and check what they run into on the way.
+pub fn move_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    collision_map: Option<Res<CollisionMap>>,
+    index: Res<SpatialIndex>,
+    mut projectiles: Query<(Entity, &mut Transform, &mut Projectile)>,
+    mut hits: MessageWriter<ProjectileHit>,
+) {
+    let delta = time.delta_secs();
+
+    for (entity, mut transform, mut projectile) in projectiles.iter_mut() {
+        projectile.lifetime -= delta;
+        let from = transform.translation.truncate();
+        let mut to = from + projectile.velocity * delta;
+
+        // Cut the flight short at the first tile in the way
+        let wall = collision_map
+            .as_ref()
+            .and_then(|map| map.raycast(from, to, RayBlocker::Sight));
+        if let Some(wall) = &wall {
+            to = wall.point;
+        }
+
+        // Nearest character along the segment it can hit
+        let reach = from.distance(to) * 0.5 + projectile.radius;
+        let target = index
+            .query_radius(from.midpoint(to), reach)
+            .filter(|entry| entry.kind == SpatialKind::Character)
+            .filter(|entry| projectile.layers.interacts_with(&entry.layers))
+            .filter_map(|entry| {
+                let time = segment_circle_time(from, to, entry.position, entry.radius + projectile.radius)?;
+                Some((entry.entity, time))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        if let Some((target, _)) = target {
+            info!("{:?} projectile hit {:?}", projectile.power_type, target);
+            hits.write(ProjectileHit {
+                projectile: entity,
+                source: projectile.source,
+                target,
+                power_type: projectile.power_type,
+            });
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        if wall.is_some() || projectile.lifetime <= 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation = to.extend(transform.translation.z);
+    }
+}
+
+/// Fraction (0.0 - 1.0) of the segment from `from` to `to` at which it enters the circle,
+/// 0.0 if it starts inside; `None` if it misses.
+fn segment_circle_time(from: Vec2, to: Vec2, center: Vec2, radius: f32) -> Option<f32> {
+    let offset = from - center;
+    if offset.length_squared() <= radius * radius {
+        return Some(0.0);
+    }
+    let motion = to - from;
+    let a = motion.length_squared();
+    if a == 0.0 {
+        return None;
+    }
+    let b = offset.dot(motion);
+    let discriminant = b * b - a * (offset.length_squared() - radius * radius);
+    if discriminant < 0.0 {
+        return None;
+    }
+    let time = (-b - discriminant.sqrt()) / a;
+    (0.0..=1.0).contains(&time).then_some(time)
+}
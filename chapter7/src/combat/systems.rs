@@ -1,8 +1,11 @@
 // src/combat/systems.rs
 use super::player_combat::PlayerCombat;
 use super::power_type::{PowerType, PowerVisuals};
+use super::projectile::Projectile;
 use crate::characters::facing::Facing;
 use crate::characters::input::Player;
+use crate::collision::CollisionLayers;
+use crate::config::combat::PROJECTILE_RADIUS;
 use crate::particles::components::ParticleEmitter;
 use bevy::prelude::*;
 
@@ -16,9 +19,9 @@ pub fn handle_power_input(
     mut commands: Commands,
     input: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
-    mut player_query: Query<(&GlobalTransform, &Facing, &mut PlayerCombat), With<Player>>,
+    mut player_query: Query<(Entity, &GlobalTransform, &Facing, &mut PlayerCombat), With<Player>>,
 ) {
-    let Ok((global_transform, facing, mut combat)) = player_query.single_mut() else {
+    let Ok((player, global_transform, facing, mut combat)) = player_query.single_mut() else {
         return;
     };
 
@@ -45,19 +48,43 @@ pub fn handle_power_input(
     // Get visuals from power type
     let visuals = combat.power_type.visuals(direction);
 
-    spawn_projectile(&mut commands, spawn_position, combat.power_type, &visuals);
+    spawn_projectile(
+        &mut commands,
+        player,
+        spawn_position,
+        combat.power_type,
+        &visuals,
+        CollisionLayers::PLAYER_PROJECTILE,
+    );
 
     info!("{:?} projectile fired!", combat.power_type);
 }
 
 
 
+/// Spawn the particles of a projectile shot by `source`, and its hitbox flying along
+/// with them. `layers` decide what it hits.
 pub fn spawn_projectile(
     commands: &mut Commands,
+    source: Entity,
     position: Vec3,
     power_type: PowerType,
     visuals: &PowerVisuals,
+    layers: CollisionLayers,
 ) {
+    // Hitbox, moving like the primary particles
+    commands.spawn((
+        Projectile {
+            source,
+            power_type,
+            velocity: visuals.primary.direction.truncate().normalize_or_zero() * visuals.primary.speed,
+            radius: PROJECTILE_RADIUS,
+            layers,
+            lifetime: visuals.primary.lifetime,
+        },
+        Transform::from_translation(position),
+    ));
+
     // Primary particles
     let primary_emitter =
         ParticleEmitter::new(0.016, visuals.particles_per_spawn, visuals.primary.clone())
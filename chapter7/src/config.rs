@@ -20,6 +20,16 @@ pub mod enemy {
 
     /// Visual scale of enemy sprites (same as player for consistency)
     pub const ENEMY_SCALE: f32 = 1.2;
+
+    /// Enemies closer than this to each other steer apart instead of queuing on one path
+    pub const SEPARATION_RADIUS: f32 = 72.0;
+
+    /// Strength of that steering, relative to following the path (1.0 = as strong)
+    pub const SEPARATION_WEIGHT: f32 = 0.6;
+
+    /// Reach of the flow field toward the player (in tiles). Enemies further away, or
+    /// cut off from the player inside it, plan their own A* path.
+    pub const FLOW_FIELD_RADIUS: i32 = 24;
 } 
 
 /// Map/terrain configuration
@@ -28,14 +38,105 @@ pub mod map {
     /// NOTE: This must match TILE_SIZE in generate.rs!
     pub const TILE_SIZE: f32 = 64.0; // Line update alert (was 32.0)
     
-    /// Grid dimensions
-    pub const GRID_X: u32 = 25;
-    pub const GRID_Y: u32 = 18;
+    /// Width and height of a chunk, in tiles
+    pub const CHUNK_SIZE: u32 = 16;
+
+    /// Chunks closer than this to the player (in world units) are generated and spawned
+    pub const CHUNK_LOAD_DISTANCE: f32 = 1280.0;
+
+    /// Spawned chunks farther than this are despawned (their generated tiles stay cached)
+    pub const CHUNK_UNLOAD_DISTANCE: f32 = 2048.0;
+
+    /// Maximum number of new chunks generated per frame
+    pub const CHUNKS_PER_FRAME: usize = 1;
+
+    /// Model covering a chunk that can't be generated (plain ground, so it is walkable
+    /// and drawn like the rest of the map)
+    pub const FALLBACK_CHUNK_MODEL: &str = "dirt.fill";
+
+    /// World height (centered on the origin) over which Y is mapped to a Z offset for depth sorting
+    pub const DEPTH_SORT_HEIGHT: f32 = TILE_SIZE * 4096.0;
     
     /// Z-height of each layer (used for Y-based depth sorting)
     pub const NODE_SIZE_Z: f32 = 1.0; // Add this line
 }
 
+/// Movement and sight properties of each tile type
+pub mod terrain {
+    use crate::collision::{TerrainProperties, TileType};
+
+    /// One entry per `TileType`, in declaration order. Speeds also set the pathfinding
+    /// costs, so enemies avoid slow ground; grass costs more than dirt roads even though
+    /// it is as fast, so enemies keep to roads (see `TerrainProperties::path_cost`).
+    /// Obstacles block sight too, unless marked `see_through`, and flying characters unless
+    /// marked `fly_over`.
+    pub const TERRAIN: [TerrainProperties; 9] = [
+        TerrainProperties::walkable(TileType::Empty, 1.0),
+        TerrainProperties::walkable(TileType::Dirt, 1.0),
+        TerrainProperties::walkable(TileType::Grass, 1.0).path_cost(1.25),
+        TerrainProperties::walkable(TileType::YellowGrass, 0.85).path_cost(1.5),
+        TerrainProperties::walkable(TileType::Shore, 0.7),
+        TerrainProperties::walkable(TileType::Mud, 0.5),
+        TerrainProperties::obstacle(TileType::Water, 0.0).see_through().fly_over(),
+        TerrainProperties::obstacle(TileType::Tree, -0.2), // Allow cutting corners
+        TerrainProperties::obstacle(TileType::Rock, -0.2),
+    ];
+
+    // `TileType::properties` indexes the table by tile type
+    const _: () = {
+        let mut index = 0;
+        while index < TERRAIN.len() {
+            assert!(TERRAIN[index].tile_type as usize == index, "TERRAIN must follow the TileType order");
+            index += 1;
+        }
+    };
+}
+
+/// Connectivity pass run on the generated map before spawning
+pub mod connectivity {
+    use crate::collision::{ConnectivityPolicy, TileType};
+
+    /// What to do when the spawn region is too small
+    pub const POLICY: ConnectivityPolicy = ConnectivityPolicy::Carve;
+
+    /// Share (0.0 - 1.0) of the counted walkable tiles the spawn region must cover. Counted
+    /// are the spawn region and the closed regions inside the loaded area: regions cut by its
+    /// edge may connect beyond it, so they are left out. A few closed pockets (a clearing
+    /// ringed by trees, an island) are fine: carving only starts once they add up.
+    pub const MIN_SPAWN_REGION_SHARE: f32 = 0.7;
+
+    /// With `Regenerate`, seeds tried before falling back to carving
+    pub const MAX_REGENERATIONS: u32 = 5;
+
+    /// Ground model (`layer.name`) laid where an obstacle of each tile type is carved away
+    pub const CARVED_GROUND: [(TileType, &str); 3] = [
+        (TileType::Water, "dirt.fill"),
+        (TileType::Tree, "grass.fill"),
+        (TileType::Rock, "grass.fill"),
+    ];
+}
+
+/// Projectiles shot by the player and enemies
+pub mod combat {
+    /// Radius of a projectile's hitbox
+    pub const PROJECTILE_RADIUS: f32 = 12.0;
+
+    /// Seconds a character ignores hits after taking one
+    pub const INVULNERABILITY_SECONDS: f32 = 0.6;
+}
+
+/// Paths planned in the background (see `collision::PathRequest`)
+pub mod pathfinding {
+    /// Maximum number of path searches started per frame
+    pub const PATH_TASKS_PER_FRAME: usize = 4;
+}
+
+/// Saved maps
+pub mod save {
+    /// File written by F6 and read back by F9 (relative to the working directory)
+    pub const MAP_FILE: &str = "saves/world.map.ron";
+}
+
 pub mod camera {
     /// How fast the camera interpolates toward the player (higher = snappier)
     pub const CAMERA_LERP_SPEED: f32 = 6.0;
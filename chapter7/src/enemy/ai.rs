@@ -1,34 +1,131 @@
 // src/enemy/ai.rs
 use super::components::{AIBehavior, Enemy, EnemyPath};
 use crate::characters::{
+    collider::Collider,
     config::CharacterEntry,
     facing::Facing,
     input::Player,
     physics::{Velocity, calculate_velocity},
     state::CharacterState,
 };
-use crate::collision::CollisionMap;
+use crate::collision::{
+    CollisionMap, CollisionMapChanged, FlowField, PathFound, PathRequest, SpatialIndex, SpatialKind,
+};
+use crate::config::enemy::{FLOW_FIELD_RADIUS, SEPARATION_RADIUS, SEPARATION_WEIGHT};
+use crate::config::player::COLLIDER_RADIUS;
 use bevy::prelude::*;
+use std::collections::HashSet;
+
+/// Drop the paths going through tiles that changed (terrain edits), so they are planned again
+pub fn invalidate_stale_paths(
+    mut changes: MessageReader<CollisionMapChanged>,
+    collision_map: Option<Res<CollisionMap>>,
+    mut paths: Query<(&Transform, &mut EnemyPath), With<Enemy>>,
+) {
+    // Steps also depend on the cells they cut past
+    let cells: HashSet<IVec2> = changes
+        .read()
+        .flat_map(|change| change.cells.iter().copied())
+        .flat_map(|cell| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| cell + IVec2::new(dx, dy))))
+        .collect();
+    let Some(collision_map) = collision_map else {
+        return;
+    };
+    if cells.is_empty() {
+        return;
+    }
+
+    for (transform, mut path) in &mut paths {
+        // Smoothed paths run straight across several cells between waypoints, so check
+        // the cells along each leg, from where the enemy is now
+        let mut from = transform.translation.truncate();
+        let stale = path.remaining().iter().any(|&to| {
+            let leg_start = std::mem::replace(&mut from, to);
+            collision_map
+                .cells_along(leg_start, to)
+                .any(|cell| cells.contains(&cell))
+        });
+        if stale {
+            path.clear();
+        }
+    }
+}
+
+/// Take the paths planned in the background for the enemies (see [`PathRequest`]).
+pub fn receive_paths(
+    mut found: MessageReader<PathFound>,
+    mut paths: Query<&mut EnemyPath, With<Enemy>>,
+) {
+    for PathFound { entity, path: waypoints } in found.read() {
+        let Ok(mut path) = paths.get_mut(*entity) else {
+            continue; // Despawned while its path was planned
+        };
+        path.waiting = false;
+        if let Some(waypoints) = waypoints {
+            path.set_path(waypoints.clone());
+        }
+    }
+}
+
+/// Flow field toward the player, shared by every enemy chasing them (built for colliders
+/// of the default size; bigger enemies plan their own paths).
+/// Rebuilt when the player moves to another tile or the collision map changes.
+#[derive(Resource, Default)]
+pub struct PlayerFlowField {
+    pub field: Option<FlowField>,
+    /// Player tile the field was built for
+    player_cell: Option<IVec2>,
+}
+
+/// Rebuild the [`PlayerFlowField`] when it is out of date.
+pub fn update_player_flow_field(
+    collision_map: Option<Res<CollisionMap>>,
+    mut flow_field: ResMut<PlayerFlowField>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    let (Some(collision_map), Ok(player_transform)) = (collision_map, player_query.single()) else {
+        if flow_field.player_cell.is_some() {
+            *flow_field = PlayerFlowField::default();
+        }
+        return;
+    };
+
+    let player_pos = player_transform.translation.truncate();
+    let player_cell = collision_map.world_to_grid(player_pos);
+    if flow_field.player_cell == Some(player_cell) && !collision_map.is_changed() {
+        return;
+    }
 
-/// AI system that makes enemies follow the player using A* pathfinding
+    flow_field.field = FlowField::build(&collision_map, player_pos, FLOW_FIELD_RADIUS, COLLIDER_RADIUS);
+    flow_field.player_cell = Some(player_cell);
+}
+
+/// AI system that makes enemies follow the player: along the [`PlayerFlowField`] near the
+/// player, with their own A* path further away (planned in the background, see
+/// [`PathRequest`])
 pub fn enemy_follow_player(
+    mut commands: Commands,
     time: Res<Time>,
     collision_map: Option<Res<CollisionMap>>,
+    index: Res<SpatialIndex>,
+    flow_field: Res<PlayerFlowField>,
     mut enemy_query: Query<
         (
+            Entity,
             &Transform,
             &mut CharacterState,
             &mut Velocity,
             &mut Facing,
             &CharacterEntry,
-            &AIBehavior,
+            &Collider,
+            &mut AIBehavior,
             &mut EnemyPath,
         ),
         With<Enemy>,
     >,
-    player_query: Query<&Transform, With<Player>>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
 ) {
-    let Ok(player_transform) = player_query.single() else {
+    let Ok((player, player_transform)) = player_query.single() else {
         return;
     };
     
@@ -39,15 +136,26 @@ pub fn enemy_follow_player(
     let player_pos = player_transform.translation.truncate();
     let delta = time.delta_secs();
 
-    for (enemy_transform, mut state, mut velocity, mut facing, character, ai, mut path) in
+    for (enemy, enemy_transform, mut state, mut velocity, mut facing, character, collider, mut ai, mut path) in
         enemy_query.iter_mut()
     {
         let enemy_pos = enemy_transform.translation.truncate();
+        let terrain_speed = collision_map.speed_multiplier_at(enemy_pos);
         let to_player = player_pos - enemy_pos;
         let distance = to_player.length();
 
-        // Outside detection range - go idle
-        if distance > ai.detection_range {
+        // Enemies notice the player when they can see them (not through trees or rocks,
+        // but across water). Once alerted, they keep chasing around obstacles.
+        let sees_player = distance <= ai.detection_range
+            && collision_map.has_line_of_sight(enemy_pos, player_pos);
+        if sees_player {
+            ai.alerted = true;
+        } else if distance > ai.detection_range {
+            ai.alerted = false;
+        }
+
+        // Outside detection range, or never saw the player - go idle
+        if !ai.alerted {
             if *state != CharacterState::Idle {
                 *state = CharacterState::Idle;
             }
@@ -64,7 +172,7 @@ pub fn enemy_follow_player(
             ai.attack_range // Enter attack mode at normal range
         };
         
-        if distance <= attack_threshold {
+        if distance <= attack_threshold && sees_player {
             if *state != CharacterState::Idle {
                 *state = CharacterState::Idle;
             }
@@ -81,22 +189,51 @@ pub fn enemy_follow_player(
             continue;
         }
 
-        // Need to move toward player - use pathfinding
+        // Need to move toward player - follow the shared flow field while inside it,
+        // unless we are too big for the paths it takes
+        let shared_field = flow_field
+            .field
+            .as_ref()
+            .filter(|field| collider.radius <= field.agent_radius());
+        let flow_direction = shared_field.and_then(|field| {
+            if collision_map.world_to_grid(enemy_pos) == field.goal() {
+                Some(to_player.normalize_or_zero()) // Same cell: straight at the player
+            } else {
+                field.direction(&collision_map, enemy_pos)
+            }
+        });
+        if let Some(direction) = flow_direction {
+            path.clear();
+            let direction = steer_apart(direction, separation(&index, enemy, player, enemy_pos));
+
+            if *state != CharacterState::Walking {
+                *state = CharacterState::Walking;
+            }
+
+            if direction != Vec2::ZERO {
+                let new_facing = Facing::from_velocity(direction);
+                if *facing != new_facing {
+                    *facing = new_facing;
+                }
+            }
+
+            *velocity = calculate_velocity(*state, direction, character, terrain_speed);
+            continue;
+        }
+
+        // Outside the flow field - use our own A* path
         path.recalc_timer -= delta;
         
-        // Recalculate path if we don't have one
-        if !path.has_path() {
-            if let Some(waypoints) = collision_map.find_path(enemy_pos, player_pos) {
-                path.set_path(waypoints);
-                path.recalc_timer = EnemyPath::RECALC_INTERVAL;
-            }
-        } else if path.recalc_timer <= 0.0 {
-            // Periodically update existing path  
+        // Ask for a path if we don't have one, and periodically for a fresher one.
+        // The current path is followed until the new one arrives.
+        if !path.waiting && (!path.has_path() || path.recalc_timer <= 0.0) {
+            commands.entity(enemy).insert(PathRequest {
+                start: enemy_pos,
+                goal: player_pos,
+                radius: collider.radius,
+            });
+            path.waiting = true;
             path.recalc_timer = EnemyPath::RECALC_INTERVAL;
-            
-            if let Some(waypoints) = collision_map.find_path(enemy_pos, player_pos) {
-                path.set_path(waypoints);
-            }
         }
 
         // Follow current waypoint
@@ -112,7 +249,10 @@ pub fn enemy_follow_player(
             // Recalculate direction for current waypoint (might have advanced)
             if let Some(current_wp) = path.current_waypoint() {
                 let to_waypoint = current_wp - enemy_pos;
-                let direction = to_waypoint.normalize_or_zero();
+                let direction = steer_apart(
+                    to_waypoint.normalize_or_zero(),
+                    separation(&index, enemy, player, enemy_pos),
+                );
             
             // Update state
             if *state != CharacterState::Walking {
@@ -128,11 +268,14 @@ pub fn enemy_follow_player(
             }
                 
                 // Calculate velocity toward waypoint
-                *velocity = calculate_velocity(*state, direction, character);
+                *velocity = calculate_velocity(*state, direction, character, terrain_speed);
             }
         } else {
             // No path available - fallback to direct movement
-            let direction = to_player.normalize_or_zero();
+            let direction = steer_apart(
+                to_player.normalize_or_zero(),
+                separation(&index, enemy, player, enemy_pos),
+            );
             
             if *state != CharacterState::Walking {
                 *state = CharacterState::Walking;
@@ -145,7 +288,34 @@ pub fn enemy_follow_player(
                 }
             }
             
-            *velocity = calculate_velocity(*state, direction, character);
+            *velocity = calculate_velocity(*state, direction, character, terrain_speed);
         }
     }
-}
\ No newline at end of file
+}
+
+/// Push away from the other enemies within `SEPARATION_RADIUS` (stronger when closer),
+/// found through the spatial index. Zero when no enemy is around.
+fn separation(index: &SpatialIndex, enemy: Entity, player: Entity, enemy_pos: Vec2) -> Vec2 {
+    index
+        .query_radius(enemy_pos, SEPARATION_RADIUS)
+        .filter(|other| other.kind == SpatialKind::Character)
+        .filter(|other| other.entity != enemy && other.entity != player)
+        .map(|other| {
+            let away = enemy_pos - other.position;
+            let distance = away.length();
+            if distance < 0.01 {
+                return Vec2::ZERO;
+            }
+            let closeness = (1.0 - distance / (SEPARATION_RADIUS + other.radius)).max(0.0);
+            away / distance * closeness
+        })
+        .sum()
+}
+
+/// Walking direction once `separation` is added to the desired direction.
+fn steer_apart(direction: Vec2, separation: Vec2) -> Vec2 {
+    if direction == Vec2::ZERO {
+        return direction;
+    }
+    (direction + separation * SEPARATION_WEIGHT).normalize_or(direction)
+}
@@ -1,6 +1,7 @@
 // src/enemy/combat.rs
 use super::components::{AIBehavior, Enemy, EnemyCombat};
 use crate::characters::input::Player;
+use crate::collision::{CollisionLayers, CollisionMap};
 use crate::combat::systems::spawn_projectile;
 use bevy::prelude::*;
 
@@ -8,14 +9,15 @@ use bevy::prelude::*;
 pub fn enemy_attack(
     mut commands: Commands,
     time: Res<Time>,
-    mut enemy_query: Query<(&GlobalTransform, &mut EnemyCombat, &AIBehavior), With<Enemy>>,
+    collision_map: Option<Res<CollisionMap>>,
+    mut enemy_query: Query<(Entity, &GlobalTransform, &mut EnemyCombat, &AIBehavior), With<Enemy>>,
     player_query: Query<&Transform, With<Player>>,
 ) {
     let Ok(player_transform) = player_query.single() else {
         return;
     };
 
-    for (enemy_transform, mut combat, ai) in enemy_query.iter_mut() {
+    for (enemy, enemy_transform, mut combat, ai) in enemy_query.iter_mut() {
         // Tick the cooldown timer
         combat.cooldown.tick(time.delta());
 
@@ -25,8 +27,16 @@ pub fn enemy_attack(
         // Calculate distance to player
         let distance = enemy_pos.distance(player_pos);
 
+        // Only shoot at a player the enemy can see
+        let sees_player = collision_map
+            .as_ref()
+            .is_none_or(|map| map.has_line_of_sight(enemy_pos.truncate(), player_pos.truncate()));
+
         // Attack if in range and cooldown is ready
-        if distance <= ai.attack_range && combat.cooldown.elapsed() >= combat.cooldown.duration() {
+        if distance <= ai.attack_range
+            && sees_player
+            && combat.cooldown.elapsed() >= combat.cooldown.duration()
+        {
             // Calculate direction to player
             let to_player = (player_pos - enemy_pos).normalize();
             let spawn_position = enemy_pos + to_player * 5.0;
@@ -35,7 +45,14 @@ pub fn enemy_attack(
             let visuals = combat.power_type.visuals(to_player);
 
             // Spawn projectile (reuse existing function!)
-            spawn_projectile(&mut commands, spawn_position, combat.power_type, &visuals);
+            spawn_projectile(
+                &mut commands,
+                enemy,
+                spawn_position,
+                combat.power_type,
+                &visuals,
+                CollisionLayers::ENEMY_PROJECTILE,
+            );
 
             // Reset cooldown for next attack
             combat.cooldown.reset();
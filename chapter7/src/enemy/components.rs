@@ -36,6 +36,8 @@ impl EnemyCombat {
 pub struct AIBehavior {
     pub attack_range: f32,
     pub detection_range: f32,
+    /// Saw the player and is after them, until they leave the detection range
+    pub alerted: bool,
 }
 
 impl Default for AIBehavior {
@@ -43,6 +45,7 @@ impl Default for AIBehavior {
         Self {
             attack_range: 150.0,    // Stop and attack within this range
             detection_range: 500.0, // Start following player within this range
+            alerted: false,
         }
     }
 }
@@ -52,6 +55,7 @@ impl AIBehavior {
         Self {
             attack_range,
             detection_range,
+            alerted: false,
         }
     }
 }
@@ -64,6 +68,8 @@ pub struct EnemyPath {
     pub current_index: usize,
     /// Timer for path recalculation
     pub recalc_timer: f32,
+    /// A new path was requested and hasn't arrived yet (the current one is followed meanwhile)
+    pub waiting: bool,
 }
 
 impl EnemyPath {
@@ -113,4 +119,15 @@ impl EnemyPath {
     pub fn has_path(&self) -> bool {
         !self.waypoints.is_empty() && self.current_index < self.waypoints.len()
     }
+
+    /// Waypoints not reached yet
+    pub fn remaining(&self) -> &[Vec2] {
+        self.waypoints.get(self.current_index..).unwrap_or_default()
+    }
+
+    /// Forget the path, so a new one is computed right away
+    pub fn clear(&mut self) {
+        self.waypoints.clear();
+        self.current_index = 0;
+    }
 }
\ No newline at end of file
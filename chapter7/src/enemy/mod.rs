@@ -18,6 +18,7 @@ impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app
             .init_resource::<EnemiesSpawned>()
+            .init_resource::<ai::PlayerFlowField>()
             // Spawn enemies AFTER collision map is ready (prevents spawning on obstacles)
             .add_systems(
                 Update,
@@ -29,7 +30,13 @@ impl Plugin for EnemyPlugin {
             // Enemy AI and combat systems
             .add_systems(
                 Update,
-                (ai::enemy_follow_player, combat::enemy_attack)
+                (
+                    ai::invalidate_stale_paths,
+                    ai::receive_paths,
+                    ai::update_player_flow_field,
+                    ai::enemy_follow_player,
+                    combat::enemy_attack,
+                )
                     .chain()
                     .run_if(in_state(GameState::Playing)),
             );
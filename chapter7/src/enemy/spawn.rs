@@ -9,8 +9,10 @@ use crate::characters::{
     spawn::CharactersListResource, // Add this line
     state::CharacterState,
 };
-use crate::collision::CollisionMap;
+use crate::collision::{CollisionLayers, CollisionMap};
+use crate::combat::Health;
 use crate::config::enemy::{ENEMY_SCALE, ENEMY_Z_POSITION};
+use crate::config::player::COLLIDER_RADIUS;
 use bevy::prelude::*;
 
 /// Spawn an enemy at the given position
@@ -55,7 +57,12 @@ pub fn spawn_enemy(
             CharacterState::default(),
             Velocity::default(),
             Facing::default(),
-            Collider::default(),
+            Collider {
+                radius: character_entry.collider_radius,
+                layers: character_entry.collision.unwrap_or(CollisionLayers::ENEMY),
+                ..default()
+            },
+            Health::new(character_entry.max_health),
             EnemyCombat::default(),
             AIBehavior::default(),
             EnemyPath::default(),  // Add this line
@@ -77,18 +84,16 @@ pub fn spawn_enemy(
 pub struct EnemiesSpawned(pub bool);
 
 /// Validate and adjust spawn position to ensure it's on a walkable tile
-fn get_valid_spawn_position(collision_map: &CollisionMap, desired_pos: Vec2) -> Vec2 {
+fn get_valid_spawn_position(collision_map: &CollisionMap, desired_pos: Vec2, enemy_radius: f32) -> Vec2 {
     // Use circle check with enemy collision radius for robust detection
-    let enemy_radius = 12.0; // Approximate enemy collision radius
-    
     // Check if the desired position is clear (considering radius)
     if collision_map.is_circle_clear(desired_pos, enemy_radius) {
         return desired_pos;
     }
 
-    // Find nearest walkable tile
+    // Find nearest tile the enemy fits in
     let grid_pos = collision_map.world_to_grid(desired_pos);
-    if let Some(walkable) = collision_map.find_nearest_walkable(grid_pos) {
+    if let Some(walkable) = collision_map.find_nearest_clear(grid_pos, enemy_radius) {
         let world_pos = collision_map.grid_to_world(walkable.x, walkable.y);
         info!(
             "Adjusted spawn from {:?} to {:?} (was on obstacle)",
@@ -129,10 +134,16 @@ pub fn spawn_test_enemies(
 
     // Define desired spawn positions
     let spawn_positions = [Vec2::new(200.0, 0.0), Vec2::new(-200.0, 100.0)];
+    let enemy_name = "graveyard_reaper";
+    let enemy_radius = characters_list
+        .characters
+        .iter()
+        .find(|c| c.name == enemy_name)
+        .map_or(COLLIDER_RADIUS, |c| c.collider_radius);
 
     for desired_pos in spawn_positions {
         // Validate position against collision map
-        let valid_pos = get_valid_spawn_position(&collision_map, desired_pos);
+        let valid_pos = get_valid_spawn_position(&collision_map, desired_pos, enemy_radius);
 
         spawn_enemy(
             &mut commands,
@@ -140,7 +151,7 @@ pub fn spawn_test_enemies(
             &mut atlas_layouts,
             characters_list,
             Vec3::new(valid_pos.x, valid_pos.y, ENEMY_Z_POSITION),
-            "graveyard_reaper",
+            enemy_name,
         );
     }
 
@@ -1,12 +1,15 @@
 // src/inventory/inventory.rs
 use bevy::prelude::*;
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::fmt;
 
+use crate::collision::spatial::{SpatialKind, SpatialShape};
+use crate::collision::CollisionLayers;
 use crate::config::pickup::DEFAULT_RADIUS;
 
 /// Types of items that can be collected.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 pub enum ItemKind {
     Plant1,
     Plant2,
@@ -31,7 +34,7 @@ impl fmt::Display for ItemKind {
     }
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct Pickable {
     pub kind: ItemKind,
     pub radius: f32,
@@ -46,6 +49,18 @@ impl Pickable {
     }
 }
 
+impl SpatialShape for Pickable {
+    const KIND: SpatialKind = SpatialKind::Item;
+
+    fn circle(&self, transform: &GlobalTransform) -> (Vec2, f32) {
+        (transform.translation().truncate(), self.radius)
+    }
+
+    fn layers(&self) -> CollisionLayers {
+        CollisionLayers::PICKUP
+    }
+}
+
 #[derive(Resource, Default, Debug)]
 pub struct Inventory {
     items: HashMap<ItemKind, u32>,
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::collision::spatial::index_shapes;
 use crate::state::GameState;
 
 mod inventory;
@@ -17,6 +18,10 @@ impl Plugin for InventoryPlugin {
             .add_systems(
                 Update,
                 handle_pickups.run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                PostUpdate,
+                index_shapes::<Pickable>.after(TransformSystems::Propagate),
             );
     }
 }
\ No newline at end of file
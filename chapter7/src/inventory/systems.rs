@@ -1,29 +1,32 @@
 use bevy::prelude::*;
 
+use crate::characters::collider::Collider;
 use crate::characters::input::Player;
+use crate::collision::{SpatialIndex, SpatialKind};
 use super::inventory::{Pickable, Inventory};
 
 /// System that checks for and processes item pickups.
 pub fn handle_pickups(
     mut commands: Commands,
     mut inventory: ResMut<Inventory>,
-    player_query: Query<&Transform, With<Player>>,
-    pickables: Query<(Entity, &GlobalTransform, &Pickable)>,
+    index: Res<SpatialIndex>,
+    player_query: Query<(&Transform, &Collider), With<Player>>,
+    pickables: Query<&Pickable>,
 ) {
-    let Ok(player_transform) = player_query.single() else {
+    let Ok((player_transform, collider)) = player_query.single() else {
         return;
     };
 
     let player_pos = player_transform.translation.truncate();
     let mut collected = Vec::new();
 
-    // Check distance to each pickable
-    for (entity, global_transform, pickable) in pickables.iter() {
-        let item_pos = global_transform.translation().truncate();
-        let distance_sq = player_pos.distance_squared(item_pos);
-        
-        if distance_sq <= pickable.radius * pickable.radius {
-            collected.push((entity, pickable.kind));
+    // Items whose pickup radius reaches the player, if the player can collect them
+    let in_reach = index
+        .query_radius(player_pos, 0.0)
+        .filter(|entry| entry.kind == SpatialKind::Item && entry.layers.interacts_with(&collider.layers));
+    for entry in in_reach {
+        if let Ok(pickable) = pickables.get(entry.entity) {
+            collected.push((entry.entity, pickable.kind));
         }
     }
 
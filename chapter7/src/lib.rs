@@ -0,0 +1,10 @@
+pub mod map;
+pub mod characters;
+pub mod state;
+pub mod collision;
+pub mod config;
+pub mod inventory;
+pub mod camera;
+pub mod combat;
+pub mod particles;
+pub mod enemy;
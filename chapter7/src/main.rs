@@ -1,26 +1,16 @@
-mod map;
-mod characters;
-mod state; 
-mod collision;
-mod config;
-mod inventory;
-mod camera;
-mod combat;
-mod particles;
-mod enemy;
-
 use bevy::{
     prelude::*,
     window::{MonitorSelection, Window, WindowMode, WindowPlugin}, // Line update alert
 };
 
-use bevy_procedural_tilemaps::prelude::*;
-use crate::camera::CameraPlugin;
-use crate::map::generate::setup_generator;
+use chapter7::camera::CameraPlugin;
+use chapter7::map::seed::WorldSeed;
+use chapter7::{characters, collision, combat, enemy, inventory, map, particles, state};
 
 fn main() {
     App::new()
         .insert_resource(ClearColor(Color::BLACK)) // Line update alert
+        .insert_resource(WorldSeed::from_args_or_env())
         .add_plugins(
             DefaultPlugins
                 .set(AssetPlugin {
@@ -37,7 +27,7 @@ fn main() {
                 })
                 .set(ImagePlugin::default_nearest()),
         )
-        .add_plugins(ProcGenSimplePlugin::<Cartesian3D, Sprite>::default())
+        .add_plugins(map::MapPlugin)
         .add_plugins(state::StatePlugin)
         .add_plugins(CameraPlugin) // Add this line
         .add_plugins(inventory::InventoryPlugin)
@@ -46,6 +36,5 @@ fn main() {
         .add_plugins(combat::CombatPlugin)
         .add_plugins(enemy::EnemyPlugin) 
         .add_plugins(particles::ParticlesPlugin)
-        .add_systems(Startup, setup_generator) // Line update alert - remove setup_camera here
         .run();
 }
\ No newline at end of file
@@ -0,0 +1,94 @@
+// src/map/animation.rs
+use bevy::prelude::*;
+
+use crate::config::map::TILE_SIZE;
+use crate::map::tilemap::{AnimationPhase, TilemapDefinition};
+
+/// Tile animations of the atlas, with their frames as atlas indices.
+/// All tiles of an animation share its clock, so tiles need no timer of their own.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct TileAnimations {
+    animations: Vec<TileAnimation>,
+}
+
+#[derive(Debug, Clone)]
+struct TileAnimation {
+    frames: Vec<usize>,
+    frame_time: f32,
+    phase: AnimationPhase,
+    /// Frame count shown last, `None` before the first update
+    step: Option<u64>,
+}
+
+impl TileAnimations {
+    /// Animations of a validated atlas (see `TilemapDefinition::validate`).
+    pub fn new(tilemap: &TilemapDefinition) -> Self {
+        let animations = tilemap
+            .animations
+            .iter()
+            .map(|animation| TileAnimation {
+                frames: animation
+                    .frames
+                    .iter()
+                    .filter_map(|frame| tilemap.sprite_index(frame))
+                    .collect(),
+                frame_time: animation.frame_time,
+                phase: animation.phase,
+                step: None,
+            })
+            .collect();
+        Self { animations }
+    }
+}
+
+/// Tile playing one of the [`TileAnimations`] (index into the atlas animations).
+#[derive(Component, Debug, Clone, Copy)]
+pub struct AnimatedTile {
+    pub animation: usize,
+}
+
+/// Move every animated tile to the current frame of its animation. Tiles are only
+/// touched when an animation steps to its next frame.
+pub fn animate_tiles(
+    time: Res<Time>,
+    mut animations: ResMut<TileAnimations>,
+    mut tiles: Query<(&AnimatedTile, &GlobalTransform, &mut Sprite)>,
+) {
+    let elapsed = time.elapsed_secs_f64();
+    let mut stepped = Vec::with_capacity(animations.animations.len());
+    for animation in &mut animations.animations {
+        let step = (elapsed / animation.frame_time as f64) as u64;
+        stepped.push(animation.step != Some(step));
+        animation.step = Some(step);
+    }
+    if !stepped.contains(&true) {
+        return;
+    }
+
+    for (tile, transform, mut sprite) in &mut tiles {
+        let Some(animation) = animations.animations.get(tile.animation) else {
+            continue;
+        };
+        if !stepped[tile.animation] || animation.frames.is_empty() {
+            continue;
+        }
+
+        let offset = match animation.phase {
+            AnimationPhase::Synced => 0,
+            AnimationPhase::PerTile => tile_phase(transform.translation().truncate()),
+        };
+        let step = animation.step.unwrap_or_default().wrapping_add(offset);
+        let frame = animation.frames[(step % animation.frames.len() as u64) as usize];
+        if let Some(atlas) = sprite.texture_atlas.as_mut() {
+            atlas.index = frame;
+        }
+    }
+}
+
+/// Frame offset of the tile at `world_pos`, stable for a given cell.
+fn tile_phase(world_pos: Vec2) -> u64 {
+    let cell = (world_pos / TILE_SIZE).floor().as_ivec2();
+    let mut hash = (cell.x as u32 as u64) << 32 | cell.y as u32 as u64;
+    hash = (hash ^ (hash >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^ (hash >> 33)
+}
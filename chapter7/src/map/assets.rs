@@ -1,33 +1,37 @@
+use std::sync::Arc;
+
 use bevy::{prelude::*};
 use bevy_procedural_tilemaps::prelude::*;
-use crate::collision::{TileMarker, TileType};
-use crate::map::tilemap::TILEMAP; 
+use crate::collision::{TileMarker, TileType, TriggerZone};
+use crate::map::animation::AnimatedTile;
+use crate::map::tilemap::TilemapDefinition;
 use crate::inventory::{ItemKind, Pickable};
 
-
+/// Adds components to a spawned tile entity.
+pub type ComponentInserter = Arc<dyn Fn(&mut EntityCommands) + Send + Sync>;
 
 #[derive(Clone)]
 pub struct SpawnableAsset {
     /// Name of the sprite inside our tilemap atlas
-    sprite_name: &'static str,
+    sprite_name: String,
     /// Offset in grid coordinates (for multi-tile objects)
     grid_offset: GridDelta,
     /// Offset in world coordinates (fine positioning)
     offset: Vec3,
-    /// Function to add custom components (like collision, physics, etc.)
+    /// Tile type given to the cell (also inserted as a `TileMarker`)
     tile_type: Option<TileType>,
-
-    pickable: Option<ItemKind>, 
+    /// Extra components (collision, pickable, ...), inserted in order
+    components: Vec<ComponentInserter>,
 }
 
 impl SpawnableAsset {
-    pub fn new(sprite_name: &'static str) -> Self {
+    pub fn new(sprite_name: impl Into<String>) -> Self {
         Self {
-            sprite_name,
+            sprite_name: sprite_name.into(),
             grid_offset: GridDelta::new(0, 0, 0),
             offset: Vec3::ZERO,
-            tile_type: None, // Default: no extra components
-            pickable: None, 
+            tile_type: None,
+            components: Vec::new(), // Default: no extra components
         }
     }
 
@@ -36,15 +40,78 @@ impl SpawnableAsset {
         self
     }
 
-    pub fn with_tile_type(mut self, tile_type: TileType) -> Self { 
+    pub fn with_tile_type(mut self, tile_type: TileType) -> Self {
         self.tile_type = Some(tile_type);
-        self
+        self.with_component(TileMarker::new(tile_type))
+    }
+
+    pub fn with_pickable(self, kind: ItemKind) -> Self {
+        self.with_component(Pickable::new(kind))
+    }
+
+    pub fn with_trigger_zone(self, zone: TriggerZone) -> Self {
+        self.with_component(zone)
     }
 
-    pub fn with_pickable(mut self, kind: ItemKind) -> Self {
-        self.pickable = Some(kind);
+    /// Insert a copy of `bundle` on every entity spawned for this asset.
+    pub fn with_component<B: Bundle + Clone>(self, bundle: B) -> Self {
+        self.with_inserter(move |entity: &mut EntityCommands| {
+            entity.insert(bundle.clone());
+        })
+    }
+
+    /// Run `insert` on every entity spawned for this asset, for components that
+    /// need more than a copy of a bundle.
+    pub fn with_inserter(mut self, insert: impl Fn(&mut EntityCommands) + Send + Sync + 'static) -> Self {
+        self.components.push(Arc::new(insert));
         self
     }
+
+    pub fn sprite_name(&self) -> &str {
+        &self.sprite_name
+    }
+
+    pub fn grid_offset(&self) -> GridDelta {
+        self.grid_offset
+    }
+
+    pub fn tile_type(&self) -> Option<TileType> {
+        self.tile_type
+    }
+}
+
+/// Sprite of a model asset and the components inserted with it.
+#[derive(Clone, Default)]
+pub struct TileAsset {
+    sprite: Sprite,
+    components: Vec<ComponentInserter>,
+}
+
+impl BundleInserter for TileAsset {
+    fn insert_bundle(
+        &self,
+        commands: &mut EntityCommands,
+        translation: Vec3,
+        scale: Vec3,
+        rotation: ModelRotation,
+    ) {
+        self.sprite.insert_bundle(commands, translation, scale, rotation);
+        for insert in &self.components {
+            insert(commands);
+        }
+    }
+}
+
+/// Handle to the tile atlas definition, loaded at startup.
+#[derive(Resource)]
+pub struct TilemapDefinitionResource {
+    pub handle: Handle<TilemapDefinition>,
+}
+
+/// Start loading the tile atlas definition (checked by the loading state before generation).
+pub fn load_tilemap_definition(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle: Handle<TilemapDefinition> = asset_server.load("tile_layers/tilemap.ron");
+    commands.insert_resource(TilemapDefinitionResource { handle });
 }
 
 #[derive(Clone)]
@@ -65,13 +132,14 @@ impl TilemapHandles {
 pub fn prepare_tilemap_handles(
     asset_server: &Res<AssetServer>,
     atlas_layouts: &mut ResMut<Assets<TextureAtlasLayout>>,
+    tilemap: &TilemapDefinition,
     assets_directory: &str,
     tilemap_file: &str,
 ) -> TilemapHandles {
     let image = asset_server.load::<Image>(format!("{assets_directory}/{tilemap_file}"));
-    let mut layout = TextureAtlasLayout::new_empty(TILEMAP.atlas_size());
-    for index in 0..TILEMAP.sprites.len() {
-        layout.add_texture(TILEMAP.sprite_rect(index));
+    let mut layout = TextureAtlasLayout::new_empty(tilemap.atlas_size());
+    for index in 0..tilemap.sprites.len() {
+        layout.add_texture(tilemap.sprite_rect(index));
     }
     let layout = atlas_layouts.add(layout);
 
@@ -79,88 +147,46 @@ pub fn prepare_tilemap_handles(
 }
 
 pub fn load_assets(
+    tilemap: &TilemapDefinition,
     tilemap_handles: &TilemapHandles,
-    assets_definitions: Vec<Vec<SpawnableAsset>>,
-) -> ModelsAssets<Sprite> {
-    let mut models_assets = ModelsAssets::<Sprite>::new();
+    assets_definitions: &[Vec<SpawnableAsset>],
+) -> ModelsAssets<TileAsset> {
+    let mut models_assets = ModelsAssets::<TileAsset>::new();
     
-    for (model_index, assets) in assets_definitions.into_iter().enumerate() {
-        for asset_def in assets {
+    for (model_index, assets) in assets_definitions.iter().enumerate() {
+        for asset_def in assets.iter().cloned() {
             let SpawnableAsset {
                 sprite_name,
                 grid_offset,
                 offset,
-                tile_type,
-                pickable, // Add this line
+                tile_type: _,
+                mut components,
             } = asset_def;
 
-            let Some(atlas_index) = TILEMAP.sprite_index(sprite_name) else {
-                panic!("Unknown atlas sprite '{}'", sprite_name);
+            // Unknown names are reported by `TilemapDefinition::validate` before we get here
+            let Some(atlas_index) = tilemap.sprite_index(&sprite_name) else {
+                continue;
             };
-
-            // Create the spawner function that adds components
-            let spawner = create_spawner(tile_type, pickable); // Line update alert
+            if let Some(animation) = tilemap.animation_index(&sprite_name) {
+                components.push(Arc::new(move |entity: &mut EntityCommands| {
+                    entity.insert(AnimatedTile { animation });
+                }));
+            }
 
             models_assets.add(
                 model_index,
                 ModelAsset {
-                    assets_bundle: tilemap_handles.sprite(atlas_index),
+                    assets_bundle: TileAsset {
+                        sprite: tilemap_handles.sprite(atlas_index),
+                        components,
+                    },
                     grid_offset,
                     world_offset: offset,
-                    spawn_commands: spawner,
+                    // The components are inserted with the `TileAsset`
+                    spawn_commands: |_: &mut EntityCommands| {},
                 },
             );
         }
     }
     models_assets
 }
-
-fn create_spawner(
-    tile_type: Option<TileType>,
-    pickable: Option<ItemKind>,
-) -> fn(&mut EntityCommands) {
-    match (tile_type, pickable) {
-        // Tile types without pickable
-        (Some(TileType::Dirt), None) => |e: &mut EntityCommands| {
-            e.insert(TileMarker::new(TileType::Dirt));
-        },
-        (Some(TileType::Grass), None) => |e: &mut EntityCommands| {
-            e.insert(TileMarker::new(TileType::Grass));
-        },
-        (Some(TileType::YellowGrass), None) => |e: &mut EntityCommands| {
-            e.insert(TileMarker::new(TileType::YellowGrass));
-        },
-        (Some(TileType::Water), None) => |e: &mut EntityCommands| {
-            e.insert(TileMarker::new(TileType::Water));
-        },
-        (Some(TileType::Shore), None) => |e: &mut EntityCommands| {
-            e.insert(TileMarker::new(TileType::Shore));
-        },
-        (Some(TileType::Tree), None) => |e: &mut EntityCommands| {
-            e.insert(TileMarker::new(TileType::Tree));
-        },
-        (Some(TileType::Rock), None) => |e: &mut EntityCommands| {
-            e.insert(TileMarker::new(TileType::Rock));
-        },
-        (Some(TileType::Empty), None) => |e: &mut EntityCommands| {
-            e.insert(TileMarker::new(TileType::Empty));
-        },
-
-        // Pickable plants (with grass tile type)
-        (Some(TileType::Grass), Some(ItemKind::Plant1)) => |e: &mut EntityCommands| {
-            e.insert((TileMarker::new(TileType::Grass), Pickable::new(ItemKind::Plant1)));
-        },
-        (Some(TileType::Grass), Some(ItemKind::Plant2)) => |e: &mut EntityCommands| {
-            e.insert((TileMarker::new(TileType::Grass), Pickable::new(ItemKind::Plant2)));
-        },
-        (Some(TileType::Grass), Some(ItemKind::Plant3)) => |e: &mut EntityCommands| {
-            e.insert((TileMarker::new(TileType::Grass), Pickable::new(ItemKind::Plant3)));
-        },
-        (Some(TileType::Grass), Some(ItemKind::Plant4)) => |e: &mut EntityCommands| {
-            e.insert((TileMarker::new(TileType::Grass), Pickable::new(ItemKind::Plant4)));
-        },
-
-        // Default: no components
-        _ => |_: &mut EntityCommands| {},
-    }
-}
\ No newline at end of file
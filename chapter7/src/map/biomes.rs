@@ -0,0 +1,101 @@
+// src/map/biomes.rs
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_procedural_tilemaps::proc_gen::generator::model::ModelIndex;
+
+use crate::config::map::TILE_SIZE;
+use crate::map::chunks::TerrainGenerator;
+use crate::map::seed::WorldSeed;
+
+/// A kind of region (forest, lake...) where some models are more or less likely.
+#[derive(Debug, Clone)]
+pub struct Biome {
+    pub name: String,
+    /// Weight multiplier of each model, indexed by model index
+    weights: Vec<f32>,
+}
+
+impl Biome {
+    pub fn new(name: String, weights: Vec<f32>) -> Self {
+        Self { name, weights }
+    }
+
+    /// Multiplier applied to the weight of `model` inside this biome.
+    pub fn weight_scale(&self, model: ModelIndex) -> f32 {
+        self.weights.get(model).copied().unwrap_or(1.0)
+    }
+}
+
+/// Biome layout of the world. Every biome has its own noise field, seeded from the
+/// world seed, and each cell belongs to the biome whose field is highest there.
+#[derive(Debug, Clone, Default)]
+pub struct BiomeMap {
+    /// Size of the noise features, in tiles
+    scale: f32,
+    biomes: Vec<Biome>,
+}
+
+impl BiomeMap {
+    pub fn new(scale: f32, biomes: Vec<Biome>) -> Self {
+        Self { scale, biomes }
+    }
+
+    pub fn biomes(&self) -> &[Biome] {
+        &self.biomes
+    }
+
+    /// Biome of a grid cell (see `CollisionMap`), `None` when no biomes are defined.
+    pub fn at(&self, seed: WorldSeed, cell: IVec2) -> Option<&Biome> {
+        let point = (cell.as_vec2() + 0.5) / self.scale;
+        self.biomes
+            .iter()
+            .enumerate()
+            .map(|(index, biome)| (biome, fractal_noise(seed.for_biome(index), point)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(biome, _)| biome)
+    }
+}
+
+/// Two octaves of value noise, in 0.0 - 1.0.
+fn fractal_noise(seed: WorldSeed, point: Vec2) -> f32 {
+    // Offset the second octave so its lattice doesn't line up with the first one
+    value_noise(seed, point) * 0.7 + value_noise(seed, point * 2.0 + Vec2::splat(0.5)) * 0.3
+}
+
+/// Random values on the integer lattice, smoothly interpolated in between.
+fn value_noise(seed: WorldSeed, point: Vec2) -> f32 {
+    let base = point.floor();
+    let t = point - base;
+    let t = t * t * (3.0 - 2.0 * t);
+
+    let base = base.as_ivec2();
+    let corner = |dx: i32, dy: i32| lattice_value(seed, base + IVec2::new(dx, dy));
+    let bottom = corner(0, 0).lerp(corner(1, 0), t.x);
+    let top = corner(0, 1).lerp(corner(1, 1), t.x);
+    bottom.lerp(top, t.y)
+}
+
+/// Value at a lattice point, in 0.0 - 1.0 (same mixing as the chunk seeds).
+fn lattice_value(seed: WorldSeed, point: IVec2) -> f32 {
+    (seed.for_chunk(point) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Biome lookups for gameplay systems (spawning, ambient audio...).
+/// Empty until the terrain generator is set up.
+#[derive(SystemParam)]
+pub struct Biomes<'w> {
+    generator: Option<Res<'w, TerrainGenerator>>,
+    seed: Res<'w, WorldSeed>,
+}
+
+impl Biomes<'_> {
+    /// Biome of a grid cell.
+    pub fn at_cell(&self, cell: IVec2) -> Option<&Biome> {
+        self.generator.as_ref()?.chunks.biomes.at(*self.seed, cell)
+    }
+
+    /// Biome at a world position.
+    pub fn at(&self, world_pos: Vec2) -> Option<&Biome> {
+        self.at_cell((world_pos / TILE_SIZE).floor().as_ivec2())
+    }
+}
@@ -0,0 +1,745 @@
+// src/map/chunks.rs
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_procedural_tilemaps::prelude::*;
+use bevy_procedural_tilemaps::proc_gen::generator::model::{ModelIndex, ModelInstance};
+use bevy_procedural_tilemaps::proc_gen::generator::rules::Rules;
+use bevy_procedural_tilemaps::proc_gen::{GeneratorError, NodeSetError};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::characters::input::Player;
+use crate::config::connectivity::CARVED_GROUND;
+use crate::config::map::{
+    CHUNKS_PER_FRAME, CHUNK_LOAD_DISTANCE, CHUNK_SIZE, CHUNK_UNLOAD_DISTANCE, DEPTH_SORT_HEIGHT,
+    FALLBACK_CHUNK_MODEL, NODE_SIZE_Z, TILE_SIZE,
+};
+use crate::map::assets::{SpawnableAsset, TileAsset};
+use crate::map::biomes::{Biome, BiomeMap};
+use crate::map::generate::{ASSETS_SCALE, NODE_SIZE};
+use crate::map::rules::NodePresets;
+use crate::map::seed::WorldSeed;
+use crate::map::tiles::tile_source;
+
+/// Size of a chunk in world units.
+pub const CHUNK_WORLD_SIZE: f32 = CHUNK_SIZE as f32 * TILE_SIZE;
+
+/// How many times the generator retries a chunk before giving up on its seam constraints.
+const CHUNK_MAX_RETRIES: u32 = 10;
+
+/// A node of the padded chunk grid set before generation: position and model variant.
+type InitialNode = ((u32, u32, u32), (ModelIndex, ModelRotation));
+
+/// Compiled terrain rules, generating the nodes of a chunk.
+/// Needs no assets, so it also runs outside the game (see the `mapgen` binary).
+#[derive(Clone)]
+pub struct ChunkGenerator {
+    pub rules: Arc<Rules<Cartesian3D>>,
+    /// Number of z levels (one per terrain layer)
+    pub layers: u32,
+    /// Hand-placed nodes (spawn clearing, landmarks) the chunks are generated around
+    pub presets: Arc<NodePresets>,
+    /// Biome regions scaling the model weights cell by cell (may be empty)
+    pub biomes: Arc<BiomeMap>,
+    /// Name of each model (indexed by model index), used in saved maps
+    pub model_references: Arc<Vec<String>>,
+    /// Layer (z level) of each model, indexed by model index
+    pub model_layers: Arc<Vec<u32>>,
+}
+
+/// Chunk generator and assets used to generate and spawn chunks.
+/// Inserted once the tile atlas and terrain rules are loaded.
+#[derive(Resource, Clone)]
+pub struct TerrainGenerator {
+    pub chunks: ChunkGenerator,
+    pub models_assets: Arc<ModelsAssets<TileAsset>>,
+    /// Asset definitions of each model (indexed by model index), with their tile types
+    pub assets_definitions: Arc<Vec<Vec<SpawnableAsset>>>,
+}
+
+/// Model instances generated for one chunk.
+#[derive(Clone, Debug)]
+pub struct ChunkNodes {
+    /// `None` where there is no node (see [`ChunkGenerator::fallback_chunk`])
+    nodes: Vec<Option<ModelInstance>>,
+}
+
+impl ChunkNodes {
+    /// Nodes of a chunk in [`ChunkNodes::nodes`] order, `None` if there are not
+    /// `CHUNK_SIZE * CHUNK_SIZE * layers` of them.
+    pub fn from_nodes(nodes: Vec<Option<ModelInstance>>, layers: u32) -> Option<Self> {
+        (nodes.len() == (CHUNK_SIZE * CHUNK_SIZE * layers) as usize).then_some(Self { nodes })
+    }
+
+    /// A chunk without any node.
+    pub fn empty(layers: u32) -> Self {
+        Self {
+            nodes: vec![None; (CHUNK_SIZE * CHUNK_SIZE * layers) as usize],
+        }
+    }
+
+    /// Every node, layer by layer, each layer row by row from the bottom.
+    pub fn nodes(&self) -> &[Option<ModelInstance>] {
+        &self.nodes
+    }
+
+    fn index(x: u32, y: u32, z: u32) -> usize {
+        ((z * CHUNK_SIZE + y) * CHUNK_SIZE + x) as usize
+    }
+
+    /// Node at chunk-local coordinates.
+    pub fn get(&self, x: u32, y: u32, z: u32) -> Option<ModelInstance> {
+        self.nodes[Self::index(x, y, z)]
+    }
+
+    /// Remove a node, so nothing is spawned there.
+    pub fn remove(&mut self, x: u32, y: u32, z: u32) {
+        self.nodes[Self::index(x, y, z)] = None;
+    }
+
+    /// Replace a node (`None` removes it).
+    pub fn set(&mut self, x: u32, y: u32, z: u32, node: Option<ModelInstance>) {
+        self.nodes[Self::index(x, y, z)] = node;
+    }
+}
+
+/// Marker on the parent entity of every spawned chunk.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Chunk;
+
+/// Node a tile entity was spawned for, in chunk-local coordinates.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkNode {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+/// Sent after a chunk and its tiles are spawned.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ChunkLoaded {
+    pub coord: IVec2,
+}
+
+/// Sent after a chunk is despawned.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ChunkUnloaded {
+    pub coord: IVec2,
+}
+
+/// Generated and spawned chunks.
+#[derive(Resource, Default)]
+pub struct WorldChunks {
+    /// Every chunk generated so far. Despawned chunks stay here so they come back
+    /// identical, and so new neighbours can still line up with their edges.
+    generated: HashMap<IVec2, ChunkNodes>,
+    /// Chunk entities currently in the world
+    loaded: HashMap<IVec2, Entity>,
+    /// Chunks within load distance that are not spawned yet
+    pending: usize,
+}
+
+impl WorldChunks {
+    /// Every chunk generated so far, spawned or not.
+    pub fn generated(&self) -> &HashMap<IVec2, ChunkNodes> {
+        &self.generated
+    }
+
+    pub fn loaded(&self, coord: IVec2) -> Option<Entity> {
+        self.loaded.get(&coord).copied()
+    }
+
+    /// True once every chunk around the streaming center is spawned.
+    pub fn is_settled(&self) -> bool {
+        self.pending == 0 && !self.loaded.is_empty()
+    }
+}
+
+/// Access to the spawned chunks for systems that edit or replace them.
+#[derive(SystemParam)]
+pub struct ChunkEditor<'w, 's> {
+    commands: Commands<'w, 's>,
+    chunks: ResMut<'w, WorldChunks>,
+    generator: Res<'w, TerrainGenerator>,
+    chunk_loaded: MessageWriter<'w, ChunkLoaded>,
+    chunk_unloaded: MessageWriter<'w, ChunkUnloaded>,
+    children: Query<'w, 's, &'static Children>,
+    tile_nodes: Query<'w, 's, &'static ChunkNode>,
+}
+
+impl ChunkEditor<'_, '_> {
+    /// See [`WorldChunks::is_settled`].
+    pub fn is_settled(&self) -> bool {
+        self.chunks.is_settled()
+    }
+
+    /// Carve away the generated nodes that make `cell` unwalkable (props, water...),
+    /// topmost first, until walkable ground shows through. Each obstacle node is cleared
+    /// and the ground model of its tile type ([`CARVED_GROUND`]) is set in its cell, so
+    /// the carved path looks like the terrain around it. The node cleared may be a
+    /// neighbour whose asset overhangs into `cell` (an obstacle spanning several tiles),
+    /// even in another chunk. Returns the chunks to respawn, so the collision map picks
+    /// up the change. Carved nodes stay carved when the chunk is streamed in again.
+    pub fn clear_obstacles(&mut self, cell: IVec2) -> Vec<IVec2> {
+        let layers = self.generator.chunks.layers;
+        let mut changed = Vec::new();
+
+        while let Some((node, z, tile_type)) =
+            tile_source(&self.chunks.generated, layers, &self.generator.assets_definitions, cell)
+        {
+            if tile_type.is_walkable() || self.set_node(node, z, None).is_none() {
+                break;
+            }
+            let ground = CARVED_GROUND
+                .iter()
+                .find(|(carved, _)| *carved == tile_type)
+                .and_then(|(_, reference)| self.generator.chunks.find_model(reference));
+            if let Some((ground_z, model_index)) = ground {
+                let instance = ModelInstance { model_index, rotation: ModelRotation::Rot0 };
+                self.set_node(node, ground_z, Some(instance));
+            }
+
+            let coord = node.div_euclid(IVec2::splat(CHUNK_SIZE as i32));
+            if !changed.contains(&coord) {
+                changed.push(coord);
+            }
+        }
+        changed
+    }
+
+    /// Replace the node of `cell` on layer `z` and, if its chunk is spawned, respawn that
+    /// node's tiles. The change is kept when the chunk is streamed in again. Returns the
+    /// previous node, or `None` if the chunk wasn't generated yet.
+    pub fn set_node(&mut self, cell: IVec2, z: u32, node: Option<ModelInstance>) -> Option<Option<ModelInstance>> {
+        let coord = cell.div_euclid(IVec2::splat(CHUNK_SIZE as i32));
+        let local = (cell - chunk_min_cell(coord)).as_uvec2();
+        let nodes = self.chunks.generated.get_mut(&coord)?;
+        let previous = nodes.get(local.x, local.y, z);
+        nodes.set(local.x, local.y, z, node);
+
+        let Some(chunk) = self.chunks.loaded(coord) else {
+            return Some(previous);
+        };
+        let key = ChunkNode { x: local.x, y: local.y, z };
+        for tile in self.children.get(chunk).into_iter().flatten() {
+            if self.tile_nodes.get(*tile).is_ok_and(|tile_node| *tile_node == key) {
+                self.commands.entity(*tile).despawn();
+            }
+        }
+        if let Some(instance) = node {
+            spawn_node(&mut self.commands, &self.generator, chunk, coord, key, instance);
+        }
+        Some(previous)
+    }
+
+    /// Despawn a loaded chunk and spawn it again from its cached nodes.
+    pub fn respawn(&mut self, coord: IVec2) {
+        let (Some(entity), Some(nodes)) = (self.chunks.loaded(coord), self.chunks.generated.get(&coord)) else {
+            return;
+        };
+        self.commands.entity(entity).despawn();
+        self.chunk_unloaded.write(ChunkUnloaded { coord });
+
+        let entity = spawn_chunk(&mut self.commands, &self.generator, coord, nodes);
+        self.chunks.loaded.insert(coord, entity);
+        self.chunk_loaded.write(ChunkLoaded { coord });
+    }
+
+    /// Despawn every chunk and forget the generated ones, so the world is generated again
+    /// (e.g. after the seed changed).
+    pub fn reset(&mut self) {
+        let chunks = &mut *self.chunks;
+        for (coord, entity) in chunks.loaded.drain() {
+            self.commands.entity(entity).despawn();
+            self.chunk_unloaded.write(ChunkUnloaded { coord });
+        }
+        chunks.generated.clear();
+        chunks.pending = 0;
+    }
+
+    /// Despawn every chunk and replace the generated ones (e.g. with a loaded map).
+    /// They are spawned again as they come into range, without being generated.
+    pub fn replace(&mut self, generated: HashMap<IVec2, ChunkNodes>) {
+        self.reset();
+        self.chunks.generated = generated;
+    }
+}
+
+/// Chunk containing a world position.
+pub fn chunk_of(world_pos: Vec2) -> IVec2 {
+    (world_pos / CHUNK_WORLD_SIZE).floor().as_ivec2()
+}
+
+/// World position of a chunk's bottom-left corner.
+pub fn chunk_origin(coord: IVec2) -> Vec2 {
+    coord.as_vec2() * CHUNK_WORLD_SIZE
+}
+
+/// Grid coordinates of a chunk's bottom-left tile (see `CollisionMap`).
+pub fn chunk_min_cell(coord: IVec2) -> IVec2 {
+    coord * CHUNK_SIZE as i32
+}
+
+/// Chunks overlapping the square of half-size `distance` around `center`.
+fn chunks_in_range(center: Vec2, distance: f32) -> impl Iterator<Item = IVec2> {
+    let min = chunk_of(center - Vec2::splat(distance));
+    let max = chunk_of(center + Vec2::splat(distance));
+    (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+}
+
+/// Sort chunks by the distance from their center to `center`, closest first.
+pub fn sort_nearest_first(coords: &mut [IVec2], center: Vec2) {
+    let chunk_center = |coord: IVec2| chunk_origin(coord) + Vec2::splat(CHUNK_WORLD_SIZE / 2.0);
+    coords.sort_by(|a, b| {
+        let da = chunk_center(*a).distance_squared(center);
+        let db = chunk_center(*b).distance_squared(center);
+        da.total_cmp(&db)
+    });
+}
+
+/// Generation class of a chunk (0 to 3), from the parity of its coordinates. Neighbouring
+/// chunks never share a class, and a chunk only lines up with its neighbours of a lower
+/// class, so its nodes only depend on the seed, not on the order chunks are generated in.
+fn generation_class(coord: IVec2) -> i32 {
+    coord.x.rem_euclid(2) + 2 * coord.y.rem_euclid(2)
+}
+
+/// Neighbours whose edges chunk `coord` is generated against: the ones of a lower
+/// generation class (none for class 0, all eight for class 3).
+pub fn seam_neighbours(coord: IVec2) -> impl Iterator<Item = IVec2> {
+    let class = generation_class(coord);
+    (-1..=1)
+        .flat_map(move |dy| (-1..=1).map(move |dx| coord + IVec2::new(dx, dy)))
+        .filter(move |neighbour| generation_class(*neighbour) < class)
+}
+
+/// Next chunk to generate on the way to `coord`: a missing chunk it is lined up with
+/// (see [`seam_neighbours`]), lowest class first, or `coord` itself once they are all
+/// generated. Never more than three chunks away.
+pub fn next_to_generate(coord: IVec2, generated: &HashMap<IVec2, ChunkNodes>) -> IVec2 {
+    seam_neighbours(coord)
+        .filter(|neighbour| !generated.contains_key(neighbour))
+        .min_by_key(|neighbour| generation_class(*neighbour))
+        .map_or(coord, |neighbour| next_to_generate(neighbour, generated))
+}
+
+/// Z offset used for 2D layering: the lower on screen, the closer to the camera.
+/// Computed from the world Y so it stays consistent across chunks (and characters).
+pub fn z_offset_from_y(world_y: f32) -> f32 {
+    let t = (world_y / DEPTH_SORT_HEIGHT + 0.5).clamp(0.0, 1.0);
+    NODE_SIZE_Z * (1.0 - t)
+}
+
+impl ChunkGenerator {
+    /// Layer and index of a model from its reference (`layer.name` or `layer.name.N`).
+    pub fn find_model(&self, reference: &str) -> Option<(u32, ModelIndex)> {
+        let index = self.model_references.iter().position(|name| name == reference)?;
+        Some((self.model_layers[index], index))
+    }
+
+    /// Stand-in for a chunk that can't be generated: every cell of its layer set to
+    /// [`FALLBACK_CHUNK_MODEL`], the other layers left empty. Without that model in the
+    /// rules the chunk has no node at all.
+    pub fn fallback_chunk(&self) -> ChunkNodes {
+        let mut nodes = ChunkNodes::empty(self.layers);
+        let Some((z, model_index)) = self.find_model(FALLBACK_CHUNK_MODEL) else {
+            warn!("No {} model in the terrain rules, the chunk is left empty", FALLBACK_CHUNK_MODEL);
+            return nodes;
+        };
+        let node = ModelInstance { model_index, rotation: ModelRotation::Rot0 };
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                nodes.set(x, y, z, Some(node));
+            }
+        }
+        nodes
+    }
+
+    /// Generate the nodes of chunk `coord`, whose [`seam_neighbours`] should be generated
+    /// first (see [`ChunkGenerator::generate_with_dependencies`]).
+    ///
+    /// The chunk is generated with a one node border on each side. Where it is lined up with
+    /// a neighbour, that border is preset to the neighbour's edge nodes, so the new chunk has
+    /// to connect to it. The border itself is thrown away afterwards. Preset nodes falling in
+    /// the padded grid are set too, so the generator fills in around them.
+    pub fn generate_chunk(
+        &self,
+        coord: IVec2,
+        seed: WorldSeed,
+        generated: &HashMap<IVec2, ChunkNodes>,
+    ) -> Result<ChunkNodes, GeneratorError> {
+        let border = self.neighbour_border(coord, generated);
+        let presets = self.preset_nodes(coord, &border);
+        if border.is_empty() && presets.is_empty() {
+            return self.generate_with_nodes(coord, seed, Vec::new());
+        }
+
+        let all = border.iter().chain(&presets).copied().collect();
+        self.generate_with_nodes(coord, seed, all)
+            .or_else(|err| {
+                if border.is_empty() || presets.is_empty() {
+                    return Err(err);
+                }
+                warn!("Chunk {} could not match its neighbours, generating it without seam constraints", coord);
+                self.generate_with_nodes(coord, seed, presets.clone())
+            })
+            .or_else(|_| {
+                warn!("Chunk {} could not be generated around its constraints, generating it freely", coord);
+                self.generate_with_nodes(coord, seed, Vec::new())
+            })
+    }
+
+    /// Generate chunk `coord` into `generated`, after the missing chunks it is lined up
+    /// with. Does nothing if it is generated already. Like in the game, a chunk that can't
+    /// be generated is replaced by [`ChunkGenerator::fallback_chunk`]; the chunks this
+    /// happened to are returned with their error.
+    pub fn generate_with_dependencies(
+        &self,
+        coord: IVec2,
+        seed: WorldSeed,
+        generated: &mut HashMap<IVec2, ChunkNodes>,
+    ) -> Vec<(IVec2, GeneratorError)> {
+        let mut failed = Vec::new();
+        while !generated.contains_key(&coord) {
+            let next = next_to_generate(coord, generated);
+            let nodes = self.generate_chunk(next, seed, generated).unwrap_or_else(|err| {
+                failed.push((next, err));
+                self.fallback_chunk()
+            });
+            generated.insert(next, nodes);
+        }
+        failed
+    }
+
+    /// Preset nodes of the cells covered by the padded grid, except where the border is set.
+    fn preset_nodes(&self, coord: IVec2, border: &[InitialNode]) -> Vec<InitialNode> {
+        let padded = CHUNK_SIZE + 2;
+        let taken: HashSet<(u32, u32, u32)> = border.iter().map(|(position, _)| *position).collect();
+        let min = chunk_min_cell(coord) - IVec2::ONE;
+
+        let mut nodes = Vec::new();
+        for y in 0..padded {
+            for x in 0..padded {
+                for &(z, model_index) in self.presets.at(min + IVec2::new(x as i32, y as i32)) {
+                    if !taken.contains(&(x, y, z)) {
+                        nodes.push(((x, y, z), (model_index, ModelRotation::Rot0)));
+                    }
+                }
+            }
+        }
+        nodes
+    }
+
+    /// Edge nodes of the generated [`seam_neighbours`] of `coord`, placed on the border of
+    /// the padded grid. Other neighbours are ignored, even if generated.
+    fn neighbour_border(
+        &self,
+        coord: IVec2,
+        generated: &HashMap<IVec2, ChunkNodes>,
+    ) -> Vec<InitialNode> {
+        let padded = CHUNK_SIZE + 2;
+        let mut border = Vec::new();
+
+        for neighbour_coord in seam_neighbours(coord) {
+            let Some(neighbour) = generated.get(&neighbour_coord) else {
+                continue;
+            };
+            let IVec2 { x: dx, y: dy } = neighbour_coord - coord;
+
+            for y in border_range(dy, padded) {
+                for x in border_range(dx, padded) {
+                    // Padded coordinates are shifted by one, then moved into the neighbour
+                    let nx = (x as i32 - 1 - dx * CHUNK_SIZE as i32) as u32;
+                    let ny = (y as i32 - 1 - dy * CHUNK_SIZE as i32) as u32;
+                    // Removed nodes are left for the generator to fill in
+                    for z in 0..self.layers {
+                        if let Some(node) = neighbour.get(nx, ny, z) {
+                            border.push(((x, y, z), (node.model_index, node.rotation)));
+                        }
+                    }
+                }
+            }
+        }
+        border
+    }
+
+    fn generate_with_nodes(
+        &self,
+        coord: IVec2,
+        seed: WorldSeed,
+        initial_nodes: Vec<InitialNode>,
+    ) -> Result<ChunkNodes, GeneratorError> {
+        let padded = CHUNK_SIZE + 2;
+        let grid = CartesianGrid::new_cartesian_3d(padded, padded, self.layers, false, false, false);
+
+        let mut generator = GeneratorBuilder::new()
+            .with_shared_rules(self.rules.clone())
+            .with_grid(grid.clone())
+            .with_rng(RngMode::Seeded(seed.for_chunk(coord)))
+            .with_max_retry_count(CHUNK_MAX_RETRIES)
+            .with_node_heuristic(NodeSelectionHeuristic::MinimumRemainingValue)
+            .with_model_heuristic(ModelSelectionHeuristic::WeightedProbability)
+            .with_initial_nodes(initial_nodes)
+            .and_then(|mut builder| {
+                // The generator only counts the nodes set through `set_and_propagate` when it
+                // has an observer, and never reports being done otherwise. Nobody listens to it.
+                builder.add_queued_observer();
+                builder.build()
+            })
+            // Initial nodes that contradict each other fail like a failed generation
+            .map_err(|_| GeneratorError { node_index: 0 })?;
+
+        let grid_data = if self.biomes.biomes().is_empty() {
+            generator.generate_grid()?.1
+        } else {
+            self.generate_in_biomes(&mut generator, coord, seed)?;
+            generator
+                .to_grid_data()
+                .ok_or(GeneratorError { node_index: 0 })?
+        };
+
+        let mut nodes = Vec::with_capacity((CHUNK_SIZE * CHUNK_SIZE * self.layers) as usize);
+        for z in 0..self.layers {
+            for y in 1..=CHUNK_SIZE {
+                for x in 1..=CHUNK_SIZE {
+                    nodes.push(Some(*grid_data.get(grid.index_from_coords(x, y, z))));
+                }
+            }
+        }
+        Ok(ChunkNodes { nodes })
+    }
+
+    /// Generate with the model weights scaled by the biome of each cell. The generator only
+    /// knows global weights, so nodes are picked here one at a time, in a random order,
+    /// and set on the generator which propagates the constraints. A contradiction restarts
+    /// the chunk, up to `CHUNK_MAX_RETRIES` times.
+    fn generate_in_biomes(
+        &self,
+        generator: &mut Generator<Cartesian3D, CartesianGrid<Cartesian3D>>,
+        coord: IVec2,
+        seed: WorldSeed,
+    ) -> Result<(), GeneratorError> {
+        let padded = CHUNK_SIZE + 2;
+        let min = chunk_min_cell(coord) - IVec2::ONE;
+        let biomes: Vec<Option<&Biome>> = (0..padded * padded)
+            .map(|index| {
+                let cell = min + IVec2::new((index % padded) as i32, (index / padded) as i32);
+                self.biomes.at(seed, cell)
+            })
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(seed.for_chunk(coord));
+        let mut order: Vec<usize> = (0..(padded * padded * self.layers) as usize).collect();
+        let mut result = Ok(());
+        for _ in 0..=CHUNK_MAX_RETRIES {
+            order.shuffle(&mut rng);
+            result = self.collapse_nodes(generator, &order, &biomes, &mut rng);
+            if result.is_ok() {
+                break;
+            }
+            generator.reinitialize();
+        }
+        result
+    }
+
+    /// Set every node in `order` that still has a choice, picking its model with the
+    /// weights of the biome of its cell (`biomes` holds one biome per padded grid column).
+    fn collapse_nodes(
+        &self,
+        generator: &mut Generator<Cartesian3D, CartesianGrid<Cartesian3D>>,
+        order: &[usize],
+        biomes: &[Option<&Biome>],
+        rng: &mut StdRng,
+    ) -> Result<(), GeneratorError> {
+        let padded = CHUNK_SIZE + 2;
+        for &node_index in order {
+            let models = generator.get_models_on(node_index);
+            if models.len() <= 1 {
+                continue; // Set, or forced by its neighbours
+            }
+
+            let position = generator.grid().pos_from_index(node_index);
+            let biome = biomes[(position.y * padded + position.x) as usize];
+            let weights: Vec<f32> = models
+                .iter()
+                .map(|model| {
+                    let weight = self
+                        .rules
+                        .variant_index(model.model_index, model.rotation)
+                        .and_then(|variant| self.rules.weight(variant))
+                        .unwrap_or(0.0);
+                    weight * biome.map_or(1.0, |biome| biome.weight_scale(model.model_index))
+                })
+                .collect();
+            // A biome can zero every remaining model, any of them will do then
+            let choice = match WeightedIndex::new(&weights) {
+                Ok(distribution) => distribution.sample(rng),
+                Err(_) => rng.gen_range(0..models.len()),
+            };
+
+            let model = &models[choice];
+            match generator.set_and_propagate(node_index, (model.model_index, model.rotation), false) {
+                Ok(GenerationStatus::Done) => return Ok(()),
+                Ok(GenerationStatus::Ongoing) => {}
+                Err(NodeSetError::GenerationError(err)) => return Err(err),
+                Err(_) => return Err(GeneratorError { node_index }),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Padded grid rows (or columns) shared with the neighbour in direction `d`.
+fn border_range(d: i32, padded: u32) -> std::ops::RangeInclusive<u32> {
+    match d {
+        -1 => 0..=0,
+        1 => padded - 1..=padded - 1,
+        _ => 1..=CHUNK_SIZE,
+    }
+}
+
+/// Spawn the tiles of a chunk as children of a new chunk entity.
+pub fn spawn_chunk(
+    commands: &mut Commands,
+    generator: &TerrainGenerator,
+    coord: IVec2,
+    nodes: &ChunkNodes,
+) -> Entity {
+    let origin = chunk_origin(coord);
+    let chunk = commands
+        .spawn((
+            Chunk,
+            Transform::from_translation(origin.extend(0.0)),
+            Visibility::default(),
+        ))
+        .id();
+
+    for z in 0..generator.chunks.layers {
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                if let Some(instance) = nodes.get(x, y, z) {
+                    spawn_node(commands, generator, chunk, coord, ChunkNode { x, y, z }, instance);
+                }
+            }
+        }
+    }
+
+    chunk
+}
+
+/// Spawn the tiles of one node as children of `chunk`.
+fn spawn_node(
+    commands: &mut Commands,
+    generator: &TerrainGenerator,
+    chunk: Entity,
+    coord: IVec2,
+    node: ChunkNode,
+    instance: ModelInstance,
+) {
+    let Some(assets) = generator.models_assets.get(&instance.model_index) else {
+        return;
+    };
+    let origin = chunk_origin(coord);
+    let ChunkNode { x, y, z } = node;
+
+    for asset in assets {
+        // Center the sprite in its node, like `NodesSpawner` does
+        let position = Vec3::new(
+            (x as i32 + asset.grid_offset.dx) as f32 + 0.5,
+            (y as i32 + asset.grid_offset.dy) as f32 + 0.5,
+            (z as i32 + asset.grid_offset.dz) as f32 + 0.5,
+        );
+        let mut translation = asset.world_offset + NODE_SIZE * position;
+        translation.z += z_offset_from_y(origin.y + y as f32 * TILE_SIZE);
+
+        let mut entity = commands.spawn((ChildOf(chunk), node));
+        asset
+            .assets_bundle
+            .insert_bundle(&mut entity, translation, ASSETS_SCALE, instance.rotation);
+        (asset.spawn_commands)(&mut entity);
+    }
+}
+
+/// Generate and spawn the chunks around the player, and despawn the ones left far behind.
+/// Before the player exists, chunks are streamed around the spawn point.
+pub fn stream_chunks(
+    mut commands: Commands,
+    generator: Option<Res<TerrainGenerator>>,
+    seed: Res<WorldSeed>,
+    mut chunks: ResMut<WorldChunks>,
+    player_query: Query<&Transform, With<Player>>,
+    mut chunk_loaded: MessageWriter<ChunkLoaded>,
+    mut chunk_unloaded: MessageWriter<ChunkUnloaded>,
+) {
+    let Some(generator) = generator else {
+        return;
+    };
+
+    let center = player_query
+        .single()
+        .map(|transform| transform.translation.truncate())
+        .unwrap_or(Vec2::ZERO);
+
+    // Despawn far chunks, keeping their nodes cached
+    let keep: HashSet<IVec2> = chunks_in_range(center, CHUNK_UNLOAD_DISTANCE).collect();
+    let far: Vec<IVec2> = chunks
+        .loaded
+        .keys()
+        .filter(|coord| !keep.contains(coord))
+        .copied()
+        .collect();
+    for coord in far {
+        if let Some(entity) = chunks.loaded.remove(&coord) {
+            commands.entity(entity).despawn();
+            chunk_unloaded.write(ChunkUnloaded { coord });
+        }
+    }
+
+    // Spawn missing chunks, closest first
+    let mut missing: Vec<IVec2> = chunks_in_range(center, CHUNK_LOAD_DISTANCE)
+        .filter(|coord| !chunks.loaded.contains_key(coord))
+        .collect();
+    sort_nearest_first(&mut missing, center);
+
+    let mut generation_budget = CHUNKS_PER_FRAME;
+    let mut pending = 0;
+    for coord in missing {
+        // Cached chunks are cheap to respawn, only generation is rate limited
+        if !chunks.generated.contains_key(&coord) {
+            if generation_budget == 0 {
+                pending += 1;
+                continue;
+            }
+            generation_budget -= 1;
+
+            // The chunks it lines up with come first, even beyond the load distance
+            let next = next_to_generate(coord, &chunks.generated);
+            let nodes = generator
+                .chunks
+                .generate_chunk(next, *seed, &chunks.generated)
+                .unwrap_or_else(|err| {
+                    // Generating it again would fail the same way (same seed and neighbours)
+                    error!(
+                        "Failed to generate chunk {}: contradiction at node {}, filling it with {}",
+                        next, err.node_index, FALLBACK_CHUNK_MODEL
+                    );
+                    generator.chunks.fallback_chunk()
+                });
+            chunks.generated.insert(next, nodes);
+            if next != coord {
+                pending += 1;
+                continue;
+            }
+        }
+
+        let entity = spawn_chunk(&mut commands, &generator, coord, &chunks.generated[&coord]);
+        chunks.loaded.insert(coord, entity);
+        chunk_loaded.write(ChunkLoaded { coord });
+    }
+    chunks.pending = pending;
+}
@@ -0,0 +1,72 @@
+// src/map/edit.rs
+use bevy::prelude::*;
+use bevy_procedural_tilemaps::prelude::*;
+use bevy_procedural_tilemaps::proc_gen::generator::model::ModelInstance;
+
+use crate::map::chunks::{ChunkEditor, TerrainGenerator};
+
+/// Change one node of the terrain at runtime: chop a tree into a stump, burn a bush,
+/// place a rock... The collision map follows (see [`TerrainChanged`]).
+#[derive(Message, Debug, Clone)]
+pub struct TerrainEdit {
+    /// Cell of the node (see `CollisionMap`)
+    pub cell: IVec2,
+    /// New model, as `layer.name` (e.g. `props.tree_stump_1`, or `props.void` to clear
+    /// the props layer). It replaces the node on its own layer.
+    pub model: String,
+}
+
+impl TerrainEdit {
+    pub fn new(cell: IVec2, model: impl Into<String>) -> Self {
+        Self {
+            cell,
+            model: model.into(),
+        }
+    }
+}
+
+/// Sent once edited nodes are respawned, with every cell their tiles cover (before and
+/// after the edit). The collision map is patched from it.
+#[derive(Message, Debug, Clone)]
+pub struct TerrainChanged {
+    pub cells: Vec<IVec2>,
+}
+
+/// Apply the edits of this frame to the generated chunks and their spawned tiles.
+pub fn apply_terrain_edits(
+    mut edits: MessageReader<TerrainEdit>,
+    mut editor: ChunkEditor,
+    generator: Res<TerrainGenerator>,
+    mut terrain_changed: MessageWriter<TerrainChanged>,
+) {
+    let mut cells = Vec::new();
+    for edit in edits.read() {
+        let Some((z, model_index)) = generator.chunks.find_model(&edit.model) else {
+            warn!("Terrain edit at {}: unknown model '{}'", edit.cell, edit.model);
+            continue;
+        };
+        let instance = ModelInstance {
+            model_index,
+            rotation: ModelRotation::Rot0,
+        };
+        let Some(previous) = editor.set_node(edit.cell, z, Some(instance)) else {
+            warn!("Terrain edit at {}: the chunk isn't generated yet", edit.cell);
+            continue;
+        };
+
+        // Multi-tile models (big trees) cover more than their own cell
+        for model in previous.iter().chain([&instance]) {
+            for asset in &generator.assets_definitions[model.model_index] {
+                let offset = asset.grid_offset();
+                cells.push(edit.cell + IVec2::new(offset.dx, offset.dy));
+            }
+        }
+        cells.push(edit.cell);
+    }
+
+    if !cells.is_empty() {
+        cells.sort_by_key(|cell| (cell.y, cell.x));
+        cells.dedup();
+        terrain_changed.write(TerrainChanged { cells });
+    }
+}
@@ -0,0 +1,236 @@
+// src/map/export.rs
+use std::collections::HashMap;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_procedural_tilemaps::prelude::*;
+use bevy_procedural_tilemaps::proc_gen::GeneratorError;
+
+use crate::collision::{water_edge_tile, TileType};
+use crate::config::map::CHUNK_SIZE;
+use crate::map::assets::SpawnableAsset;
+use crate::map::chunks::{chunk_min_cell, ChunkGenerator, ChunkNodes};
+use crate::map::seed::WorldSeed;
+use crate::map::tilemap::TilemapDefinition;
+use crate::map::tiles::{self, tile_priority, TilePriority};
+
+/// A rectangle of cells generated outside the game, for exporting as text or image.
+pub struct GeneratedArea {
+    /// Bottom-left cell, in the grid coordinates of `CollisionMap`
+    min_cell: IVec2,
+    size: UVec2,
+    layers: u32,
+    chunks: HashMap<IVec2, ChunkNodes>,
+}
+
+/// One asset placed in a cell of the area.
+struct Placement<'a> {
+    /// Cell relative to the top-left corner of the area (image coordinates)
+    pixel_cell: UVec2,
+    asset: &'a SpawnableAsset,
+    rotation: ModelRotation,
+    /// Draw order (see `tile_priority`), so the output doesn't depend on chunk order
+    order: TilePriority,
+}
+
+impl GeneratedArea {
+    /// Generate every chunk overlapping the area, and the chunks they line up with, the
+    /// same way the game does (see `seam_neighbours`). Chunks only depend on the seed, so
+    /// the area matches the game's world, before the connectivity pass edits it. Chunks
+    /// that can't be generated are filled like in the game, and returned with their error.
+    pub fn generate(
+        generator: &ChunkGenerator,
+        seed: WorldSeed,
+        min_cell: IVec2,
+        size: UVec2,
+    ) -> (Self, Vec<(IVec2, GeneratorError)>) {
+        let chunk_size = CHUNK_SIZE as i32;
+        let min_chunk = min_cell.div_euclid(IVec2::splat(chunk_size));
+        let max_chunk = (min_cell + size.as_ivec2() - IVec2::ONE).div_euclid(IVec2::splat(chunk_size));
+
+        let mut chunks = HashMap::new();
+        let mut failed = Vec::new();
+        for y in min_chunk.y..=max_chunk.y {
+            for x in min_chunk.x..=max_chunk.x {
+                failed.extend(generator.generate_with_dependencies(IVec2::new(x, y), seed, &mut chunks));
+            }
+        }
+
+        (Self::from_chunks(generator.layers, chunks, min_cell, size), failed)
+    }
+
+    /// An area over chunks generated elsewhere (e.g. in the game, or loaded from a map file).
+    /// Cells of missing chunks are left empty.
+    pub fn from_chunks(layers: u32, chunks: HashMap<IVec2, ChunkNodes>, min_cell: IVec2, size: UVec2) -> Self {
+        Self {
+            min_cell,
+            size,
+            layers,
+            chunks,
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn chunks(&self) -> &HashMap<IVec2, ChunkNodes> {
+        &self.chunks
+    }
+
+    /// Every asset of the area, in draw order. `assets` are the assets of each model,
+    /// as returned by `build_chunk_generator`.
+    fn placements<'a>(&self, assets: &'a [Vec<SpawnableAsset>]) -> Vec<Placement<'a>> {
+        let mut placements = Vec::new();
+        let chunk_size = CHUNK_SIZE as i32;
+
+        for (coord, nodes) in &self.chunks {
+            let chunk_min = chunk_min_cell(*coord);
+            for z in 0..self.layers {
+                for y in 0..chunk_size {
+                    for x in 0..chunk_size {
+                        let Some(instance) = nodes.get(x as u32, y as u32, z) else {
+                            continue;
+                        };
+                        let Some(model_assets) = assets.get(instance.model_index) else {
+                            continue;
+                        };
+
+                        for asset in model_assets {
+                            let offset = asset.grid_offset();
+                            let cell = chunk_min + IVec2::new(x + offset.dx, y + offset.dy) - self.min_cell;
+                            if cell.x < 0
+                                || cell.y < 0
+                                || cell.x >= self.size.x as i32
+                                || cell.y >= self.size.y as i32
+                            {
+                                continue;
+                            }
+
+                            placements.push(Placement {
+                                // Rows go down in images and text, but up in the world
+                                pixel_cell: UVec2::new(cell.x as u32, self.size.y - 1 - cell.y as u32),
+                                asset,
+                                rotation: instance.rotation,
+                                order: tile_priority(z, chunk_min + IVec2::new(x, y), offset.dz),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        placements.sort_by_key(|placement| placement.order);
+        placements
+    }
+
+    /// Tile type of each cell as in the collision map: the topmost one, with water next
+    /// to land turned into shore (see `water_edge_tile`). Row by row from the top; cells
+    /// without any tile type are `None`.
+    pub fn tile_types(&self, assets: &[Vec<SpawnableAsset>]) -> Vec<Option<TileType>> {
+        // One more cell all around, for the shore of the cells on the edge
+        let margin_min = self.min_cell - IVec2::ONE;
+        let margin_size = (self.size + UVec2::splat(2)).as_ivec2();
+        let tiles = tiles::tile_types(&self.chunks, self.layers, assets, margin_min, margin_size.as_uvec2());
+        let tile_at = |cell: IVec2| {
+            let local = cell - margin_min;
+            if local.cmplt(IVec2::ZERO).any() || local.cmpge(margin_size).any() {
+                return None;
+            }
+            tiles[(local.y * margin_size.x + local.x) as usize]
+        };
+
+        // Rows go down in images and text, but up in the world
+        let (min_cell, size) = (self.min_cell, self.size.as_ivec2());
+        (0..size.y)
+            .rev()
+            .flat_map(|y| (0..size.x).map(move |x| min_cell + IVec2::new(x, y)))
+            .map(|cell| water_edge_tile(tile_at, cell).or_else(|| tile_at(cell)))
+            .collect()
+    }
+
+    /// One line per row of cells, one character per cell (see `TileType::ascii`).
+    /// Cells without any tile type are blank.
+    pub fn to_ascii(&self, assets: &[Vec<SpawnableAsset>]) -> String {
+        let tiles = self.tile_types(assets);
+        let mut text = String::with_capacity(tiles.len() + self.size.y as usize);
+        for row in tiles.chunks(self.size.x as usize) {
+            text.extend(row.iter().map(|tile| tile.map_or(' ', |tile| tile.ascii())));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Draw the area with the sprites of the tile atlas, one atlas tile per cell.
+    /// `atlas` must be an RGBA8 sRGB image, like PNGs loaded with alpha.
+    pub fn render(
+        &self,
+        assets: &[Vec<SpawnableAsset>],
+        tilemap: &TilemapDefinition,
+        atlas: &Image,
+    ) -> Image {
+        let tile = tilemap.tile_size();
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: self.size.x * tile.x,
+                height: self.size.y * tile.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::MAIN_WORLD,
+        );
+        let (Some(source), Some(target)) = (atlas.data.as_ref(), image.data.as_mut()) else {
+            return image;
+        };
+        let source_width = atlas.width();
+        let target_width = self.size.x * tile.x;
+
+        for placement in self.placements(assets) {
+            // Unknown names are reported by `TilemapDefinition::validate` before rendering
+            let Some(index) = tilemap.sprite_index(placement.asset.sprite_name()) else {
+                continue;
+            };
+            let rect = tilemap.sprite_rect(index);
+            let origin = placement.pixel_cell * tile;
+
+            for y in 0..tile.y {
+                for x in 0..tile.x {
+                    let from = rect.min + rotated_pixel(UVec2::new(x, y), tile, placement.rotation);
+                    let from = ((from.y * source_width + from.x) * 4) as usize;
+                    let to = (((origin.y + y) * target_width + origin.x + x) * 4) as usize;
+                    blend(&mut target[to..to + 4], &source[from..from + 4]);
+                }
+            }
+        }
+
+        image
+    }
+}
+
+/// Source pixel shown at `pixel` of a sprite rotated counterclockwise (square tiles).
+fn rotated_pixel(pixel: UVec2, tile: UVec2, rotation: ModelRotation) -> UVec2 {
+    let max = tile - UVec2::ONE;
+    match rotation {
+        ModelRotation::Rot0 => pixel,
+        ModelRotation::Rot90 => UVec2::new(max.x - pixel.y, pixel.x),
+        ModelRotation::Rot180 => max - pixel,
+        ModelRotation::Rot270 => UVec2::new(pixel.y, max.y - pixel.x),
+    }
+}
+
+/// Alpha blend an RGBA8 pixel over another.
+fn blend(target: &mut [u8], source: &[u8]) {
+    let alpha = source[3] as f32 / 255.0;
+    if alpha <= 0.0 {
+        return;
+    }
+    for channel in 0..3 {
+        let mixed = source[channel] as f32 * alpha + target[channel] as f32 * (1.0 - alpha);
+        target[channel] = mixed.round() as u8;
+    }
+    let target_alpha = target[3] as f32 / 255.0;
+    target[3] = ((alpha + target_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+}
@@ -1,73 +1,143 @@
 // src/map/generate.rs
 use bevy_procedural_tilemaps::prelude::*;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use std::sync::Arc;
 
-use crate::config::map::{GRID_X, GRID_Y, NODE_SIZE_Z, TILE_SIZE}; // Line update alert
+use crate::config::map::{NODE_SIZE_Z, TILE_SIZE}; // Line update alert
 
 
 use crate::map::{
-    assets::{load_assets, prepare_tilemap_handles},
-    rules::build_world,
+    animation::TileAnimations,
+    assets::{load_assets, prepare_tilemap_handles, SpawnableAsset, TilemapDefinitionResource},
+    chunks::{ChunkGenerator, TerrainGenerator},
+    rules::{build_world, RulesError, TerrainRules, TerrainRulesResource},
+    seed::WorldSeed,
+    tilemap::TilemapDefinition,
 };
 
-// -----------------  Configurable values ---------------------------
-/// Modify these values to control the map size.
+/// Tile atlas image, relative to the asset folder
+pub const ASSETS_PATH: &str = "tile_layers";
+pub const TILEMAP_FILE: &str = "tilemap.png";
+/// Size of a grid node in world units (in Bevy 2d, 1 pixel is 1 world unit)
+pub const NODE_SIZE: Vec3 = Vec3::new(TILE_SIZE, TILE_SIZE, NODE_SIZE_Z);
 
-// ------------------------------------------------------------------
+pub const ASSETS_SCALE: Vec3 = Vec3::new(2.0, 2.0, 1.0);
 
-const ASSETS_PATH: &str = "tile_layers";
-const TILEMAP_FILE: &str = "tilemap.png";
-/// Size of a block in world units (in Bevy 2d, 1 pixel is 1 world unit)
-/// Size of a grid node in world units
-const NODE_SIZE: Vec3 = Vec3::new(TILE_SIZE, TILE_SIZE, NODE_SIZE_Z);
 
-const ASSETS_SCALE: Vec3 = Vec3::new(2.0, 2.0, 1.0);
-/// Number of z layers in the map, derived from the default terrain layers.
-const GRID_Z: u32 = 5;
 
+/// The loaded tile atlas definition and terrain rules.
+#[derive(SystemParam)]
+pub struct TerrainDefinitions<'w> {
+    tilemaps: Res<'w, Assets<TilemapDefinition>>,
+    tilemap_res: Res<'w, TilemapDefinitionResource>,
+    terrain_rules: Res<'w, Assets<TerrainRules>>,
+    terrain_rules_res: Res<'w, TerrainRulesResource>,
+}
 
+impl TerrainDefinitions<'_> {
+    pub fn get(&self) -> Option<(&TilemapDefinition, &TerrainRules)> {
+        Some((
+            self.tilemaps.get(&self.tilemap_res.handle)?,
+            self.terrain_rules.get(&self.terrain_rules_res.handle)?,
+        ))
+    }
+}
 
 pub fn setup_generator(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+    seed: Res<WorldSeed>,
+    definitions: TerrainDefinitions,
 ) {
-    // 1. Rules Initialization - Get tile definitions and connection rules
-    let (assets_definitions, models, socket_collection) = build_world();
+    info!("World seed: {} (pass --seed {} or set WORLD_SEED to reproduce)", seed.0, seed.0);
 
-    let rules = RulesBuilder::new_cartesian_3d(models, socket_collection)
+    // The loading state waits for the atlas and the rules, so they are available here
+    let Some((tilemap, terrain_rules)) = definitions.get() else {
+        error!("Tile atlas or terrain rules are not loaded, skipping world generation");
+        return;
+    };
+
+    // 1. Rules Initialization - Compile tile definitions and connection rules
+    let (assets_definitions, chunk_generator) = match build_chunk_generator(terrain_rules) {
+        Ok(compiled) => compiled,
+        Err(errors) => {
+            for err in &errors {
+                error!("Invalid terrain rules: {}", err);
+            }
+            return;
+        }
+    };
+
+    let referenced = assets_definitions
+        .iter()
+        .flatten()
+        .map(|asset| asset.sprite_name());
+    let errors = tilemap.validate(referenced);
+    if !errors.is_empty() {
+        for err in &errors {
+            error!("Invalid tile atlas: {}", err);
+        }
+        return;
+    }
+
+    // 2. Loading Assets - Load sprite atlas and convert to renderable assets
+    let tilemap_handles = prepare_tilemap_handles(
+        &asset_server,
+        &mut atlas_layouts,
+        tilemap,
+        ASSETS_PATH,
+        TILEMAP_FILE,
+    );
+    let models_assets = load_assets(tilemap, &tilemap_handles, &assets_definitions);
+    commands.insert_resource(TileAnimations::new(tilemap));
+
+    // 3. Chunks are generated around the player from here on, one z level per terrain layer
+    commands.insert_resource(TerrainGenerator {
+        chunks: chunk_generator,
+        models_assets: Arc::new(models_assets),
+        assets_definitions: Arc::new(assets_definitions),
+    });
+}
+
+/// Compile the terrain rules into a chunk generator with one z level per layer,
+/// along with the assets of each model (indexed by model index).
+pub fn build_chunk_generator(
+    terrain_rules: &TerrainRules,
+) -> Result<(Vec<Vec<SpawnableAsset>>, ChunkGenerator), Vec<RulesError>> {
+    let world = build_world(terrain_rules)?;
+
+    let rules = RulesBuilder::new_cartesian_3d(world.models, world.sockets)
         // Use ZForward as the up axis (rotation axis for models) since we are using Bevy in 2D
         .with_rotation_axis(Direction::ZForward)
         .build()
         .unwrap();
 
-    // 2. Grid - Create 3D world space with wrapping behavior (false, false, false)
-    let grid = CartesianGrid::new_cartesian_3d(GRID_X, GRID_Y, GRID_Z, false, false, false);
-
-    // 3. Configuring the Algorithm - Set up WFC behavior
-    let gen_builder = GeneratorBuilder::new()
-        .with_rules(rules)
-        .with_grid(grid.clone())
-        .with_rng(RngMode::RandomSeed)
-        .with_node_heuristic(NodeSelectionHeuristic::MinimumRemainingValue)
-        .with_model_heuristic(ModelSelectionHeuristic::WeightedProbability);
-    
-    let generator = gen_builder.build().unwrap();
-
-    // 4. Loading Assets - Load sprite atlas and convert to renderable assets
-    let tilemap_handles =
-        prepare_tilemap_handles(&asset_server, &mut atlas_layouts, ASSETS_PATH, TILEMAP_FILE);
-    let models_assets = load_assets(&tilemap_handles, assets_definitions);
-
-    // 5. Spawning the Generator - Create entity with Transform and NodesSpawner
-    commands.spawn((
-        Transform::from_translation(Vec3 {
-            x: -TILE_SIZE * grid.size_x() as f32 / 2.,
-            y: -TILE_SIZE * grid.size_y() as f32 / 2.,
-            z: 0.,
-        }),
-        grid,
-        generator,
-        NodesSpawner::new(models_assets, NODE_SIZE, ASSETS_SCALE).with_z_offset_from_y(true),
-    ));
-}
\ No newline at end of file
+    let chunk_generator = ChunkGenerator {
+        rules: Arc::new(rules),
+        layers: terrain_rules.layers.len() as u32,
+        presets: Arc::new(world.presets),
+        biomes: Arc::new(world.biomes),
+        model_references: Arc::new(world.references),
+        model_layers: Arc::new(world.model_layers),
+    };
+    Ok((world.assets, chunk_generator))
+}
+
+/// The game's terrain rules, read from the source tree, for tests.
+#[cfg(test)]
+pub fn game_terrain_rules() -> TerrainRules {
+    ron::de::from_bytes(include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/assets/tile_layers/terrain.rules.ron"
+    )))
+    .unwrap()
+}
+
+/// Chunk generator compiled from [`game_terrain_rules`], with the assets of each model, for tests.
+#[cfg(test)]
+pub fn game_chunk_generator() -> (Vec<Vec<SpawnableAsset>>, ChunkGenerator) {
+    build_chunk_generator(&game_terrain_rules())
+        .unwrap_or_else(|errors| panic!("invalid terrain rules: {errors:?}"))
+}
@@ -1,6 +1,88 @@
+pub mod animation;
 pub mod assets; 
+pub mod biomes;
 pub mod tilemap;
 pub mod rules;
 pub mod models;
-pub mod sockets;
-pub mod generate;
\ No newline at end of file
+pub mod generate;
+pub mod seed;
+pub mod tiles;
+pub mod chunks;
+pub mod export;
+pub mod edit;
+pub mod regenerate;
+pub mod save;
+
+use bevy::prelude::*;
+use bevy_common_assets::ron::RonAssetPlugin;
+use crate::state::GameState;
+use chunks::{ChunkLoaded, ChunkUnloaded, WorldChunks};
+use edit::{TerrainChanged, TerrainEdit};
+use regenerate::RegenerateWorld;
+use save::{LoadMap, SaveMap};
+use rules::TerrainRules;
+use tilemap::TilemapDefinition;
+
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<TilemapDefinition>::new(&["tilemap.ron"]))
+            .add_plugins(RonAssetPlugin::<TerrainRules>::new(&["rules.ron"]))
+            .init_resource::<WorldChunks>()
+            .add_message::<ChunkLoaded>()
+            .add_message::<ChunkUnloaded>()
+            .add_message::<TerrainEdit>()
+            .add_message::<TerrainChanged>()
+            .add_message::<RegenerateWorld>()
+            .add_message::<SaveMap>()
+            .add_message::<LoadMap>()
+            // Start loading the atlas definition and rules; the loading state waits for them
+            .add_systems(Startup, (assets::load_tilemap_definition, rules::load_terrain_rules))
+            // Compile the rules once all assets are ready
+            .add_systems(OnExit(GameState::Loading), generate::setup_generator)
+            // Generate and spawn chunks around the player
+            .add_systems(
+                Update,
+                chunks::stream_chunks.run_if(in_state(GameState::Playing)),
+            )
+            // Water shimmer and other animated tiles
+            .add_systems(
+                Update,
+                animation::animate_tiles
+                    .run_if(resource_exists::<animation::TileAnimations>)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            // Runtime terrain changes, once the streamed chunks are in
+            .add_systems(
+                Update,
+                edit::apply_terrain_edits
+                    .after(chunks::stream_chunks)
+                    .run_if(resource_exists::<chunks::TerrainGenerator>)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            // New world on request, before streaming starts over
+            .add_systems(
+                Update,
+                (regenerate::regenerate_on_key, regenerate::regenerate_world)
+                    .chain()
+                    .before(chunks::stream_chunks)
+                    .run_if(resource_exists::<chunks::TerrainGenerator>)
+                    .run_if(
+                        in_state(GameState::Playing)
+                            .or(in_state(GameState::Paused))
+                            .or(in_state(GameState::GameOver)),
+                    ),
+            )
+            // Save the generated chunks, or replace the world with a saved map
+            .add_systems(
+                Update,
+                (save::save_load_on_key, save::save_map, save::load_map)
+                    .chain()
+                    .after(regenerate::regenerate_world)
+                    .before(chunks::stream_chunks)
+                    .run_if(resource_exists::<chunks::TerrainGenerator>)
+                    .run_if(in_state(GameState::Playing).or(in_state(GameState::Paused))),
+            );
+    }
+}
@@ -8,6 +8,12 @@ pub struct TerrainModelBuilder {
     pub assets: Vec<Vec<SpawnableAsset>>,
 }
 
+impl Default for TerrainModelBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TerrainModelBuilder {
     pub fn new() -> Self {
         Self {
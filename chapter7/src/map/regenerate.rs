@@ -0,0 +1,131 @@
+// src/map/regenerate.rs
+use std::collections::HashMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::characters::input::Player;
+use crate::characters::spawn::PlayerSpawned;
+use crate::collision::{CollisionMap, CollisionMapBuilt, SkipConnectivity};
+use crate::combat::Projectile;
+use crate::config::map::TILE_SIZE;
+use crate::enemy::spawn::EnemiesSpawned;
+use crate::enemy::Enemy;
+use crate::inventory::Pickable;
+use crate::map::chunks::{ChunkEditor, ChunkNodes};
+use crate::map::save::MapZone;
+use crate::map::seed::WorldSeed;
+use crate::particles::components::{Particle, ParticleEmitter};
+use crate::state::GameState;
+
+/// Request a new world. The inventory and the selected character are kept.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct RegenerateWorld {
+    /// Seed of the new world, `None` for a random one
+    pub seed: Option<WorldSeed>,
+}
+
+/// Everything spawned on top of the terrain that belongs to the current world.
+/// Pickables inside chunks go away with their chunk.
+type WorldEntityFilter = Or<(
+    With<Player>,
+    With<Enemy>,
+    With<Projectile>,
+    With<Particle>,
+    With<ParticleEmitter>,
+    With<MapZone>,
+    (With<Pickable>, Without<ChildOf>),
+)>;
+
+/// The collision map and the one-shot flags that gate spawning on it.
+#[derive(SystemParam)]
+pub struct GenerationProgress<'w> {
+    collision_map: ResMut<'w, CollisionMap>,
+    collision_map_built: ResMut<'w, CollisionMapBuilt>,
+    skip_connectivity: ResMut<'w, SkipConnectivity>,
+    player_spawned: ResMut<'w, PlayerSpawned>,
+    enemies_spawned: ResMut<'w, EnemiesSpawned>,
+}
+
+impl GenerationProgress<'_> {
+    /// Empty the collision map and clear the flags, so the connectivity pass (unless
+    /// `skip_connectivity`) and the player and enemy spawners run again on the next map.
+    pub fn reset(&mut self, skip_connectivity: bool) {
+        *self.collision_map = CollisionMap::new(TILE_SIZE);
+        self.collision_map_built.0 = false;
+        self.skip_connectivity.0 = skip_connectivity;
+        self.player_spawned.0 = false;
+        self.enemies_spawned.0 = false;
+    }
+}
+
+/// Everything needed to throw the current world away.
+#[derive(SystemParam)]
+pub struct WorldReset<'w, 's> {
+    commands: Commands<'w, 's>,
+    editor: ChunkEditor<'w, 's>,
+    progress: GenerationProgress<'w>,
+    world_entities: Query<'w, 's, Entity, WorldEntityFilter>,
+}
+
+impl WorldReset<'_, '_> {
+    /// Despawn chunks, player, enemies, projectiles, loose pickables, particles and map
+    /// zones, empty the collision map and reset the spawn flags so everything spawns again once the new map
+    /// is built. The new world is generated from the current seed.
+    pub fn reset(&mut self) {
+        self.start_over(HashMap::new(), false);
+    }
+
+    /// Same as [`WorldReset::reset`], but the world starts over from the `generated` chunks
+    /// of a map file, kept as they are (see [`SkipConnectivity`]). Missing chunks are
+    /// generated as usual.
+    pub fn load(&mut self, generated: HashMap<IVec2, ChunkNodes>) {
+        self.start_over(generated, true);
+    }
+
+    fn start_over(&mut self, generated: HashMap<IVec2, ChunkNodes>, skip_connectivity: bool) {
+        for entity in &self.world_entities {
+            self.commands.entity(entity).despawn();
+        }
+        self.editor.replace(generated);
+        self.progress.reset(skip_connectivity);
+    }
+}
+
+/// F5 starts a new world with a random seed (while playing, paused or after dying);
+/// R in the pause menu or the game over screen generates the current world again.
+pub fn regenerate_on_key(
+    input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    seed: Res<WorldSeed>,
+    mut regenerate: MessageWriter<RegenerateWorld>,
+) {
+    if input.just_pressed(KeyCode::F5) {
+        regenerate.write(RegenerateWorld { seed: None });
+    } else if matches!(state.get(), GameState::Paused | GameState::GameOver)
+        && input.just_pressed(KeyCode::KeyR)
+    {
+        regenerate.write(RegenerateWorld { seed: Some(*seed) });
+    }
+}
+
+/// Throw the current world away and let generation start over (see [`WorldReset`]).
+pub fn regenerate_world(
+    mut requests: MessageReader<RegenerateWorld>,
+    mut seed: ResMut<WorldSeed>,
+    mut reset: WorldReset,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // Several requests in one frame: the last one wins
+    let Some(request) = requests.read().last().copied() else {
+        return;
+    };
+
+    *seed = request.seed.unwrap_or_else(WorldSeed::random);
+    info!("Regenerating the world with seed {}", seed.0);
+
+    reset.reset();
+
+    // Chunks only stream while playing
+    next_state.set(GameState::Playing);
+}
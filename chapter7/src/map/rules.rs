@@ -1,672 +1,779 @@
 // src/map/rules.rs
-use crate::map::assets::SpawnableAsset;
-use crate::collision::TileType;
-use crate::map::models::TerrainModelBuilder;
-use crate::map::sockets::*;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::fmt;
+
+use bevy::prelude::*;
 use bevy_procedural_tilemaps::prelude::*;
+use bevy_procedural_tilemaps::proc_gen::generator::model::ModelIndex;
+use serde::{Deserialize, Serialize};
+
+use crate::collision::{TileType, TriggerZone};
 use crate::inventory::ItemKind;
+use crate::map::assets::SpawnableAsset;
+use crate::map::biomes::{Biome, BiomeMap};
+use crate::map::models::TerrainModelBuilder;
 
-fn build_dirt_layer(
-    terrain_model_builder: &mut TerrainModelBuilder,
-    terrain_sockets: &TerrainSockets,
-    socket_collection: &mut SocketCollection,
-) {
-    terrain_model_builder
-        .create_model(
-            SocketsCartesian3D::Simple {
-                x_pos: terrain_sockets.dirt.material, // right
-                x_neg: terrain_sockets.dirt.material, // left
-                z_pos: terrain_sockets.dirt.layer_up, // top
-                z_neg: terrain_sockets.dirt.layer_down, // bottom
-                y_pos: terrain_sockets.dirt.material, // up
-                y_neg: terrain_sockets.dirt.material, // down
-            },
-            vec![SpawnableAsset::new("dirt").with_tile_type(TileType::Dirt)],
-        )
-        .with_weight(20.);
+/// Terrain rules loaded from `tile_layers/terrain.rules.ron`.
+/// Each layer becomes one z level of the generation grid, in file order.
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
+pub struct TerrainRules {
+    /// Sockets usable from every layer without a layer prefix (e.g. `void`)
+    #[serde(default)]
+    pub shared_sockets: Vec<String>,
+    pub layers: Vec<LayerRules>,
+    /// Nodes set before generation, which the generator fills in around
+    #[serde(default)]
+    pub presets: PresetRules,
+    /// Regions scaling the weights of some models, for variety within a map
+    #[serde(default)]
+    pub biomes: BiomeRules,
+}
 
-    socket_collection.add_connections(vec![(
-        terrain_sockets.dirt.material,
-        vec![terrain_sockets.dirt.material],
-    )]);
+#[derive(Debug, Clone, Deserialize)]
+pub struct LayerRules {
+    pub name: String,
+    /// Sockets owned by this layer, referenced as `layer.socket`
+    pub sockets: Vec<String>,
+    /// Named socket layouts shared by several models of this layer
+    #[serde(default)]
+    pub templates: HashMap<String, SocketsRule>,
+    pub models: Vec<ModelRule>,
+    #[serde(default)]
+    pub connections: Vec<ConnectionRule>,
 }
 
-fn build_yellow_grass_layer(
-    terrain_model_builder: &mut TerrainModelBuilder,
-    terrain_sockets: &TerrainSockets,
-    socket_collection: &mut SocketCollection,
-) {
-    // Void model - empty space where no yellow grass exists
-    terrain_model_builder.create_model(
-        SocketsCartesian3D::Simple {
-            x_pos: terrain_sockets.void,
-            x_neg: terrain_sockets.void,
-            z_pos: terrain_sockets.yellow_grass.layer_up,
-            z_neg: terrain_sockets.yellow_grass.layer_down,
-            y_pos: terrain_sockets.void,
-            y_neg: terrain_sockets.void,
-        },
-        Vec::new(),
-    );
-
-    // Main yellow grass tile
-    terrain_model_builder
-        .create_model(
-            SocketsCartesian3D::Simple {
-                x_pos: terrain_sockets.grass.material,
-                x_neg: terrain_sockets.grass.material,
-                z_pos: terrain_sockets.yellow_grass.layer_up,
-                z_neg: terrain_sockets.yellow_grass.yellow_grass_fill_down,
-                y_pos: terrain_sockets.grass.material,
-                y_neg: terrain_sockets.grass.material,
-            },
-            vec![SpawnableAsset::new("yellow_grass").with_tile_type(TileType::YellowGrass)],
-        )
-        .with_weight(5.);
-
-    // Outer corner template
-    let yellow_grass_corner_out = SocketsCartesian3D::Simple {
-        x_pos: terrain_sockets.grass.void_and_grass,
-        x_neg: terrain_sockets.void,
-        z_pos: terrain_sockets.yellow_grass.layer_up,
-        z_neg: terrain_sockets.yellow_grass.yellow_grass_fill_down,
-        y_pos: terrain_sockets.void,
-        y_neg: terrain_sockets.grass.grass_and_void,
+/// Mirrors `SocketsCartesian3D`, with sockets referenced by name.
+#[derive(Debug, Clone, Deserialize)]
+pub enum SocketsRule {
+    Simple {
+        x_pos: String,
+        x_neg: String,
+        z_pos: String,
+        z_neg: String,
+        y_pos: String,
+        y_neg: String,
+    },
+    Multiple {
+        x_pos: Vec<String>,
+        x_neg: Vec<String>,
+        z_pos: Vec<String>,
+        z_neg: Vec<String>,
+        y_pos: Vec<String>,
+        y_neg: Vec<String>,
+    },
+    /// Use one of the layer's `templates`
+    Template(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelRule {
+    /// Name used to reference the model from presets, as `layer.name`
+    /// (or `layer.name.N` for its Nth variant)
+    #[serde(default)]
+    pub name: Option<String>,
+    pub sockets: SocketsRule,
+    #[serde(default)]
+    pub weight: Option<f32>,
+    /// One model is created per variant, using the sockets rotated by the variant's rotation
+    pub variants: Vec<VariantRule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VariantRule {
+    #[serde(default)]
+    pub rotation: RuleRotation,
+    pub assets: Vec<AssetRule>,
+}
+
+/// Rotation around the Z axis (counterclockwise), see `ModelRotation`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleRotation {
+    #[default]
+    Rot0,
+    Rot90,
+    Rot180,
+    Rot270,
+}
+
+impl From<RuleRotation> for ModelRotation {
+    fn from(rotation: RuleRotation) -> Self {
+        match rotation {
+            RuleRotation::Rot0 => ModelRotation::Rot0,
+            RuleRotation::Rot90 => ModelRotation::Rot90,
+            RuleRotation::Rot180 => ModelRotation::Rot180,
+            RuleRotation::Rot270 => ModelRotation::Rot270,
+        }
     }
-    .to_template();
-
-    // Inner corner template
-    let yellow_grass_corner_in = SocketsCartesian3D::Simple {
-        x_pos: terrain_sockets.grass.grass_and_void,
-        x_neg: terrain_sockets.grass.material,
-        z_pos: terrain_sockets.yellow_grass.layer_up,
-        z_neg: terrain_sockets.yellow_grass.yellow_grass_fill_down,
-        y_pos: terrain_sockets.grass.material,
-        y_neg: terrain_sockets.grass.void_and_grass,
+}
+
+impl From<ModelRotation> for RuleRotation {
+    fn from(rotation: ModelRotation) -> Self {
+        match rotation {
+            ModelRotation::Rot0 => RuleRotation::Rot0,
+            ModelRotation::Rot90 => RuleRotation::Rot90,
+            ModelRotation::Rot180 => RuleRotation::Rot180,
+            ModelRotation::Rot270 => RuleRotation::Rot270,
+        }
     }
-    .to_template();
-
-    // Side edge template
-    let yellow_grass_side = SocketsCartesian3D::Simple {
-        x_pos: terrain_sockets.grass.void_and_grass,
-        x_neg: terrain_sockets.grass.grass_and_void,
-        z_pos: terrain_sockets.yellow_grass.layer_up,
-        z_neg: terrain_sockets.yellow_grass.yellow_grass_fill_down,
-        y_pos: terrain_sockets.void,
-        y_neg: terrain_sockets.grass.material,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssetRule {
+    /// Name of the sprite inside the tile atlas
+    pub sprite: String,
+    #[serde(default)]
+    pub tile_type: Option<TileType>,
+    #[serde(default)]
+    pub pickable: Option<ItemKind>,
+    /// Trigger zone placed on the asset, e.g. `Some((name: "graveyard", shape: Circle(radius: 192.0)))`
+    #[serde(default)]
+    pub zone: Option<TriggerZone>,
+    /// Offset in grid coordinates (for multi-tile objects)
+    #[serde(default)]
+    pub grid_offset: (i32, i32, i32),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum ConnectionRule {
+    /// `SocketCollection::add_connections`
+    Connect(String, Vec<String>),
+    /// `SocketCollection::add_rotated_connection`
+    Rotated(String, Vec<String>),
+}
+
+/// Hand-placed nodes, in grid coordinates (see `CollisionMap`).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PresetRules {
+    /// Clearing around the player spawn (the world origin)
+    #[serde(default)]
+    pub spawn_clearing: Option<ClearingRule>,
+    #[serde(default)]
+    pub landmarks: Vec<LandmarkRule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClearingRule {
+    /// Radius in tiles; every cell whose center is within it is part of the clearing
+    pub radius: f32,
+    /// Model set on every cell of the clearing, at most one per layer
+    pub models: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LandmarkRule {
+    pub cell: (i32, i32),
+    /// Models set on the cell, at most one per layer
+    pub models: Vec<String>,
+}
+
+/// Noise-based biome regions. Models are referenced as `layer.name` (every variant).
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BiomeRules {
+    /// Typical size of a biome region, in tiles
+    #[serde(default)]
+    pub scale: f32,
+    /// Named lists of models that biomes can scale together
+    #[serde(default)]
+    pub groups: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub definitions: Vec<BiomeRule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BiomeRule {
+    pub name: String,
+    /// Weight multiplier by group or model; models not listed keep their weight
+    #[serde(default)]
+    pub weights: HashMap<String, f32>,
+}
+
+/// Problems found while compiling [`TerrainRules`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RulesError {
+    /// A socket name is declared twice
+    DuplicateSocket(String),
+    /// A model, template or connection references a socket that was never declared
+    UnknownSocket { layer: String, socket: String },
+    /// A model references a template missing from its layer
+    UnknownTemplate { layer: String, template: String },
+    /// A template is defined in terms of another template
+    NestedTemplate { layer: String, template: String },
+    /// A model has no variants, so it would never be created
+    NoVariants { layer: String, model: usize },
+    /// Two models of a layer have the same name
+    DuplicateModel { layer: String, model: String },
+    /// A preset or biome group references a model (or variant) that doesn't exist
+    UnknownModel(String),
+    /// A preset sets two models on the same layer of a cell
+    ConflictingPreset { cell: (i32, i32), layer: String },
+    /// Biomes are defined without a positive `scale`
+    InvalidBiomeScale,
+    /// A biome scales a name that is neither a group nor a model
+    UnknownBiomeWeight { biome: String, name: String },
+    /// A biome weight multiplier is negative or not a number
+    InvalidBiomeWeight { biome: String, name: String },
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesError::DuplicateSocket(name) => {
+                write!(f, "socket '{}' is declared more than once", name)
+            }
+            RulesError::UnknownSocket { layer, socket } => {
+                write!(f, "layer '{}' references unknown socket '{}'", layer, socket)
+            }
+            RulesError::UnknownTemplate { layer, template } => {
+                write!(f, "layer '{}' references unknown template '{}'", layer, template)
+            }
+            RulesError::NestedTemplate { layer, template } => {
+                write!(f, "template '{}' in layer '{}' cannot use another template", template, layer)
+            }
+            RulesError::NoVariants { layer, model } => {
+                write!(f, "model #{} in layer '{}' has no variants", model, layer)
+            }
+            RulesError::DuplicateModel { layer, model } => {
+                write!(f, "model '{}' is declared more than once in layer '{}'", model, layer)
+            }
+            RulesError::UnknownModel(reference) => {
+                write!(f, "reference to unknown model '{}'", reference)
+            }
+            RulesError::ConflictingPreset { cell, layer } => {
+                write!(f, "cell {:?} has more than one preset model in layer '{}'", cell, layer)
+            }
+            RulesError::InvalidBiomeScale => write!(f, "biomes need a positive scale"),
+            RulesError::UnknownBiomeWeight { biome, name } => {
+                write!(f, "biome '{}' scales unknown group or model '{}'", biome, name)
+            }
+            RulesError::InvalidBiomeWeight { biome, name } => {
+                write!(f, "biome '{}' has an invalid multiplier for '{}'", biome, name)
+            }
+        }
     }
-    .to_template();
-
-    // Create rotated versions of outer corners
-    terrain_model_builder.create_model(
-        yellow_grass_corner_out.clone(),
-        vec![SpawnableAsset::new("yellow_grass_corner_out_tl").with_tile_type(TileType::YellowGrass)],
-    );
-    terrain_model_builder.create_model(
-        yellow_grass_corner_out.rotated(ModelRotation::Rot90, Direction::ZForward),
-        vec![SpawnableAsset::new("yellow_grass_corner_out_bl").with_tile_type(TileType::YellowGrass)],
-    );
-    terrain_model_builder.create_model(
-        yellow_grass_corner_out.rotated(ModelRotation::Rot180, Direction::ZForward),
-        vec![SpawnableAsset::new("yellow_grass_corner_out_br").with_tile_type(TileType::YellowGrass)],
-    );
-    terrain_model_builder.create_model(
-        yellow_grass_corner_out.rotated(ModelRotation::Rot270, Direction::ZForward),
-        vec![SpawnableAsset::new("yellow_grass_corner_out_tr").with_tile_type(TileType::YellowGrass)],
-    );
-
-    // Create rotated versions of inner corners
-    terrain_model_builder.create_model(
-        yellow_grass_corner_in.clone(),
-        vec![SpawnableAsset::new("yellow_grass_corner_in_tl").with_tile_type(TileType::YellowGrass)],
-    );
-    terrain_model_builder.create_model(
-        yellow_grass_corner_in.rotated(ModelRotation::Rot90, Direction::ZForward),
-        vec![SpawnableAsset::new("yellow_grass_corner_in_bl").with_tile_type(TileType::YellowGrass)],
-    );
-    terrain_model_builder.create_model(
-        yellow_grass_corner_in.rotated(ModelRotation::Rot180, Direction::ZForward),
-        vec![SpawnableAsset::new("yellow_grass_corner_in_br").with_tile_type(TileType::YellowGrass)],
-    );
-    terrain_model_builder.create_model(
-        yellow_grass_corner_in.rotated(ModelRotation::Rot270, Direction::ZForward),
-        vec![SpawnableAsset::new("yellow_grass_corner_in_tr").with_tile_type(TileType::YellowGrass)],
-    );
-
-    // Create rotated versions of side edges
-    terrain_model_builder.create_model(
-        yellow_grass_side.clone(),
-        vec![SpawnableAsset::new("yellow_grass_side_t").with_tile_type(TileType::YellowGrass)],
-    );
-    terrain_model_builder.create_model(
-        yellow_grass_side.rotated(ModelRotation::Rot90, Direction::ZForward),
-        vec![SpawnableAsset::new("yellow_grass_side_l").with_tile_type(TileType::YellowGrass)],
-    );
-    terrain_model_builder.create_model(
-        yellow_grass_side.rotated(ModelRotation::Rot180, Direction::ZForward),
-        vec![SpawnableAsset::new("yellow_grass_side_b").with_tile_type(TileType::YellowGrass)],
-    );
-    terrain_model_builder.create_model(
-        yellow_grass_side.rotated(ModelRotation::Rot270, Direction::ZForward),
-        vec![SpawnableAsset::new("yellow_grass_side_r").with_tile_type(TileType::YellowGrass)],
-    );
-
-    // Add connection rules
-    socket_collection
-        .add_rotated_connection(
-            terrain_sockets.grass.layer_up,
-            vec![terrain_sockets.yellow_grass.layer_down],
-        )
-        .add_rotated_connection(
-            terrain_sockets.yellow_grass.yellow_grass_fill_down,
-            vec![terrain_sockets.grass.grass_fill_up],
-        );
 }
 
-// src/map/rules.rs - Add this function after build_dirt_layer
-fn build_grass_layer(
-    terrain_model_builder: &mut TerrainModelBuilder,
-    terrain_sockets: &TerrainSockets,
-    socket_collection: &mut SocketCollection,
-) {
-    // Void model - empty space above dirt where no grass exists
-    terrain_model_builder.create_model(
-        SocketsCartesian3D::Simple {
-            x_pos: terrain_sockets.void,
-            x_neg: terrain_sockets.void,
-            z_pos: terrain_sockets.grass.layer_up,
-            z_neg: terrain_sockets.grass.layer_down,
-            y_pos: terrain_sockets.void,
-            y_neg: terrain_sockets.void,
-        },
-        Vec::new(),
-    );
-
-    // Main grass tile
-    terrain_model_builder
-        .create_model(
-            SocketsCartesian3D::Multiple {
-                x_pos: vec![terrain_sockets.grass.material],
-                x_neg: vec![terrain_sockets.grass.material],
-                z_pos: vec![
-                    terrain_sockets.grass.layer_up,
-                    terrain_sockets.grass.grass_fill_up,
-                ],
-                z_neg: vec![terrain_sockets.grass.layer_down],
-                y_pos: vec![terrain_sockets.grass.material],
-                y_neg: vec![terrain_sockets.grass.material],
-            },
-            vec![SpawnableAsset::new("green_grass").with_tile_type(TileType::Grass)],
-        )
-        .with_weight(5.);
-
-    // Outer corner template
-    let green_grass_corner_out = SocketsCartesian3D::Simple {
-        x_pos: terrain_sockets.grass.void_and_grass,
-        x_neg: terrain_sockets.void,
-        z_pos: terrain_sockets.grass.layer_up,
-        z_neg: terrain_sockets.grass.layer_down,
-        y_pos: terrain_sockets.void,
-        y_neg: terrain_sockets.grass.grass_and_void,
+/// Handle to the terrain rules, loaded at startup.
+#[derive(Resource)]
+pub struct TerrainRulesResource {
+    pub handle: Handle<TerrainRules>,
+}
+
+/// Start loading the terrain rules (checked by the loading state before generation).
+pub fn load_terrain_rules(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle: Handle<TerrainRules> = asset_server.load("tile_layers/terrain.rules.ron");
+    commands.insert_resource(TerrainRulesResource { handle });
+}
+
+/// Everything the generator needs from the rules.
+pub struct WorldDefinition {
+    /// Assets of each model, indexed by model index
+    pub assets: Vec<Vec<SpawnableAsset>>,
+    pub models: ModelCollection<Cartesian3D>,
+    pub sockets: SocketCollection,
+    pub presets: NodePresets,
+    pub biomes: BiomeMap,
+    /// Reference of each model, indexed by model index (see [`model_references`])
+    pub references: Vec<String>,
+    /// Layer (z level) of each model, indexed by model index
+    pub model_layers: Vec<u32>,
+}
+
+/// Preset nodes by cell: z level (layer) and model.
+#[derive(Debug, Clone, Default)]
+pub struct NodePresets {
+    cells: HashMap<IVec2, Vec<(u32, ModelIndex)>>,
+}
+
+impl NodePresets {
+    /// Models preset on a cell, with their z level.
+    pub fn at(&self, cell: IVec2) -> &[(u32, ModelIndex)] {
+        self.cells.get(&cell).map_or(&[], Vec::as_slice)
     }
-    .to_template();
-
-    // Inner corner template
-    let green_grass_corner_in = SocketsCartesian3D::Simple {
-        x_pos: terrain_sockets.grass.grass_and_void,
-        x_neg: terrain_sockets.grass.material,
-        z_pos: terrain_sockets.grass.layer_up,
-        z_neg: terrain_sockets.grass.layer_down,
-        y_pos: terrain_sockets.grass.material,
-        y_neg: terrain_sockets.grass.void_and_grass,
+}
+
+/// Model indices of each variant of the named models, by `layer.name`, with the layer's z level.
+type ModelNames = HashMap<String, (u32, Vec<ModelIndex>)>;
+
+/// Resolves socket names to the sockets created in the collection.
+struct SocketTable {
+    sockets: HashMap<String, Socket>,
+    /// Stand-in for unknown names so compilation can keep going and report every error
+    missing: Socket,
+}
+
+impl SocketTable {
+    fn new(rules: &TerrainRules, collection: &mut SocketCollection, errors: &mut Vec<RulesError>) -> Self {
+        let mut sockets = HashMap::new();
+        let layer_sockets = rules.layers.iter().flat_map(|layer| {
+            layer.sockets.iter().map(move |socket| format!("{}.{}", layer.name, socket))
+        });
+
+        for name in rules.shared_sockets.iter().cloned().chain(layer_sockets) {
+            match sockets.entry(name) {
+                Entry::Occupied(entry) => errors.push(RulesError::DuplicateSocket(entry.key().clone())),
+                Entry::Vacant(entry) => {
+                    entry.insert(collection.create());
+                }
+            }
+        }
+
+        Self {
+            sockets,
+            missing: collection.create(),
+        }
     }
-    .to_template();
-
-    // Side edge template
-    let green_grass_side = SocketsCartesian3D::Simple {
-        x_pos: terrain_sockets.grass.void_and_grass,
-        x_neg: terrain_sockets.grass.grass_and_void,
-        z_pos: terrain_sockets.grass.layer_up,
-        z_neg: terrain_sockets.grass.layer_down,
-        y_pos: terrain_sockets.void,
-        y_neg: terrain_sockets.grass.material,
+
+    fn get(&self, layer: &str, name: &str, errors: &mut Vec<RulesError>) -> Socket {
+        match self.sockets.get(name) {
+            Some(socket) => *socket,
+            None => {
+                errors.push(RulesError::UnknownSocket {
+                    layer: layer.to_string(),
+                    socket: name.to_string(),
+                });
+                self.missing
+            }
+        }
+    }
+
+    fn get_all(&self, layer: &str, names: &[String], errors: &mut Vec<RulesError>) -> Vec<Socket> {
+        names.iter().map(|name| self.get(layer, name, errors)).collect()
     }
-    .to_template();
-
-    // Create rotated versions of outer corners
-    terrain_model_builder.create_model(
-        green_grass_corner_out.clone(),
-        vec![SpawnableAsset::new("green_grass_corner_out_tl").with_tile_type(TileType::Grass)],
-    );
-    terrain_model_builder.create_model(
-        green_grass_corner_out.rotated(ModelRotation::Rot90, Direction::ZForward),
-        vec![SpawnableAsset::new("green_grass_corner_out_bl").with_tile_type(TileType::Grass)],
-    );
-    terrain_model_builder.create_model(
-        green_grass_corner_out.rotated(ModelRotation::Rot180, Direction::ZForward),
-        vec![SpawnableAsset::new("green_grass_corner_out_br").with_tile_type(TileType::Grass)],
-    );
-    terrain_model_builder.create_model(
-        green_grass_corner_out.rotated(ModelRotation::Rot270, Direction::ZForward),
-        vec![SpawnableAsset::new("green_grass_corner_out_tr").with_tile_type(TileType::Grass)],
-    );
-
-    // Create rotated versions of inner corners
-    terrain_model_builder.create_model(
-        green_grass_corner_in.clone(),
-        vec![SpawnableAsset::new("green_grass_corner_in_tl").with_tile_type(TileType::Grass)],
-    );
-    terrain_model_builder.create_model(
-        green_grass_corner_in.rotated(ModelRotation::Rot90, Direction::ZForward),
-        vec![SpawnableAsset::new("green_grass_corner_in_bl").with_tile_type(TileType::Grass)],
-    );
-    terrain_model_builder.create_model(
-        green_grass_corner_in.rotated(ModelRotation::Rot180, Direction::ZForward),
-        vec![SpawnableAsset::new("green_grass_corner_in_br").with_tile_type(TileType::Grass)],
-    );
-    terrain_model_builder.create_model(
-        green_grass_corner_in.rotated(ModelRotation::Rot270, Direction::ZForward),
-        vec![SpawnableAsset::new("green_grass_corner_in_tr").with_tile_type(TileType::Grass)],
-    );
-
-    // Create rotated versions of side edges
-    terrain_model_builder.create_model(
-        green_grass_side.clone(),
-        vec![SpawnableAsset::new("green_grass_side_t").with_tile_type(TileType::Grass)],
-    );
-    terrain_model_builder.create_model(
-        green_grass_side.rotated(ModelRotation::Rot90, Direction::ZForward),
-        vec![SpawnableAsset::new("green_grass_side_l").with_tile_type(TileType::Grass)],
-    );
-    terrain_model_builder.create_model(
-        green_grass_side.rotated(ModelRotation::Rot180, Direction::ZForward),
-        vec![SpawnableAsset::new("green_grass_side_b").with_tile_type(TileType::Grass)],
-    );
-    terrain_model_builder.create_model(
-        green_grass_side.rotated(ModelRotation::Rot270, Direction::ZForward),
-        vec![SpawnableAsset::new("green_grass_side_r").with_tile_type(TileType::Grass)],
-    );
-
-    // Add connection rules
-    socket_collection.add_rotated_connection(
-        terrain_sockets.dirt.layer_up,
-        vec![terrain_sockets.grass.layer_down],
-    );
-    socket_collection.add_connections(vec![
-        (terrain_sockets.void, vec![terrain_sockets.void]),
-        (
-            terrain_sockets.grass.material,
-            vec![terrain_sockets.grass.material],
-        ),
-        (
-            terrain_sockets.grass.void_and_grass,
-            vec![terrain_sockets.grass.grass_and_void],
-        ),
-    ]);
 }
 
-pub fn build_water_layer(
+fn compile_sockets(
+    layer: &str,
+    rule: &SocketsRule,
+    table: &SocketTable,
+    errors: &mut Vec<RulesError>,
+) -> Option<ModelTemplate<Cartesian3D>> {
+    let sockets = match rule {
+        SocketsRule::Simple { x_pos, x_neg, z_pos, z_neg, y_pos, y_neg } => SocketsCartesian3D::Simple {
+            x_pos: table.get(layer, x_pos, errors),
+            x_neg: table.get(layer, x_neg, errors),
+            z_pos: table.get(layer, z_pos, errors),
+            z_neg: table.get(layer, z_neg, errors),
+            y_pos: table.get(layer, y_pos, errors),
+            y_neg: table.get(layer, y_neg, errors),
+        },
+        SocketsRule::Multiple { x_pos, x_neg, z_pos, z_neg, y_pos, y_neg } => SocketsCartesian3D::Multiple {
+            x_pos: table.get_all(layer, x_pos, errors),
+            x_neg: table.get_all(layer, x_neg, errors),
+            z_pos: table.get_all(layer, z_pos, errors),
+            z_neg: table.get_all(layer, z_neg, errors),
+            y_pos: table.get_all(layer, y_pos, errors),
+            y_neg: table.get_all(layer, y_neg, errors),
+        },
+        SocketsRule::Template(_) => return None,
+    };
+    Some(sockets.to_template())
+}
+
+fn rotate(template: &ModelTemplate<Cartesian3D>, rotation: RuleRotation) -> ModelTemplate<Cartesian3D> {
+    let rotation = match rotation {
+        RuleRotation::Rot0 => return template.clone(),
+        RuleRotation::Rot90 => ModelRotation::Rot90,
+        RuleRotation::Rot180 => ModelRotation::Rot180,
+        RuleRotation::Rot270 => ModelRotation::Rot270,
+    };
+    // Use ZForward as the rotation axis since we are using Bevy in 2D
+    template.rotated(rotation, Direction::ZForward)
+}
+
+impl AssetRule {
+    fn to_spawnable(&self) -> SpawnableAsset {
+        let (x, y, z) = self.grid_offset;
+        let mut asset = SpawnableAsset::new(self.sprite.clone()).with_grid_offset(GridDelta::new(x, y, z));
+        if let Some(tile_type) = self.tile_type {
+            asset = asset.with_tile_type(tile_type);
+        }
+        if let Some(kind) = self.pickable {
+            asset = asset.with_pickable(kind);
+        }
+        if let Some(zone) = &self.zone {
+            asset = asset.with_trigger_zone(zone.clone());
+        }
+        asset
+    }
+}
+
+fn build_layer(
+    z: u32,
+    layer: &LayerRules,
+    table: &SocketTable,
     terrain_model_builder: &mut TerrainModelBuilder,
-    terrain_sockets: &TerrainSockets,
     socket_collection: &mut SocketCollection,
+    names: &mut ModelNames,
+    errors: &mut Vec<RulesError>,
 ) {
-    // Void model - represents land areas where no water exists
-    terrain_model_builder.create_model(
-        SocketsCartesian3D::Multiple {
-            x_pos: vec![terrain_sockets.void],
-            x_neg: vec![terrain_sockets.void],
-            z_pos: vec![
-                terrain_sockets.water.layer_up,
-                terrain_sockets.water.ground_up,
-            ],
-            z_neg: vec![terrain_sockets.water.layer_down],
-            y_pos: vec![terrain_sockets.void],
-            y_neg: vec![terrain_sockets.void],
-        },
-        Vec::new(),
-    );
-
-    // Main water tile
-    const WATER_WEIGHT: f32 = 0.002;
-    terrain_model_builder
-        .create_model(
-            SocketsCartesian3D::Simple {
-                x_pos: terrain_sockets.water.material,
-                x_neg: terrain_sockets.water.material,
-                z_pos: terrain_sockets.water.layer_up,
-                z_neg: terrain_sockets.water.layer_down,
-                y_pos: terrain_sockets.water.material,
-                y_neg: terrain_sockets.water.material,
+    let mut templates = HashMap::new();
+    for (name, rule) in &layer.templates {
+        match compile_sockets(&layer.name, rule, table, errors) {
+            Some(template) => {
+                templates.insert(name.as_str(), template);
+            }
+            None => errors.push(RulesError::NestedTemplate {
+                layer: layer.name.clone(),
+                template: name.clone(),
+            }),
+        }
+    }
+
+    for (index, model) in layer.models.iter().enumerate() {
+        let template = match &model.sockets {
+            SocketsRule::Template(name) => match templates.get(name.as_str()) {
+                Some(template) => template.clone(),
+                None => {
+                    errors.push(RulesError::UnknownTemplate {
+                        layer: layer.name.clone(),
+                        template: name.clone(),
+                    });
+                    continue;
+                }
             },
-            vec![SpawnableAsset::new("water").with_tile_type(TileType::Water)],
-        )
-        .with_weight(10. * WATER_WEIGHT);
-
-    // Outer corner template
-    let water_corner_out = SocketsCartesian3D::Simple {
-        x_pos: terrain_sockets.water.void_and_water,
-        x_neg: terrain_sockets.void,
-        z_pos: terrain_sockets.water.layer_up,
-        z_neg: terrain_sockets.water.layer_down,
-        y_pos: terrain_sockets.void,
-        y_neg: terrain_sockets.water.water_and_void,
+            rule => match compile_sockets(&layer.name, rule, table, errors) {
+                Some(template) => template,
+                None => continue,
+            },
+        };
+
+        if model.variants.is_empty() {
+            errors.push(RulesError::NoVariants {
+                layer: layer.name.clone(),
+                model: index,
+            });
+        }
+
+        let mut indices = Vec::new();
+        for variant in &model.variants {
+            indices.push(terrain_model_builder.assets.len());
+            let assets = variant.assets.iter().map(AssetRule::to_spawnable).collect();
+            let created = terrain_model_builder.create_model(rotate(&template, variant.rotation), assets);
+            if let Some(weight) = model.weight {
+                created.with_weight(weight);
+            }
+        }
+
+        if let Some(name) = &model.name {
+            match names.entry(format!("{}.{}", layer.name, name)) {
+                Entry::Occupied(_) => errors.push(RulesError::DuplicateModel {
+                    layer: layer.name.clone(),
+                    model: name.clone(),
+                }),
+                Entry::Vacant(entry) => {
+                    entry.insert((z, indices));
+                }
+            }
+        }
     }
-    .to_template()
-    .with_weight(WATER_WEIGHT);
-
-    // Inner corner template
-    let water_corner_in = SocketsCartesian3D::Simple {
-        x_pos: terrain_sockets.water.water_and_void,
-        x_neg: terrain_sockets.water.material,
-        z_pos: terrain_sockets.water.layer_up,
-        z_neg: terrain_sockets.water.layer_down,
-        y_pos: terrain_sockets.water.material,
-        y_neg: terrain_sockets.water.void_and_water,
+
+    for connection in &layer.connections {
+        match connection {
+            ConnectionRule::Connect(from, to) => {
+                let from = table.get(&layer.name, from, errors);
+                let to = table.get_all(&layer.name, to, errors);
+                socket_collection.add_connections(vec![(from, to)]);
+            }
+            ConnectionRule::Rotated(from, to) => {
+                let from = table.get(&layer.name, from, errors);
+                let to = table.get_all(&layer.name, to, errors);
+                socket_collection.add_rotated_connection(from, to);
+            }
+        }
     }
-    .to_template()
-    .with_weight(WATER_WEIGHT);
-
-    // Side edge template
-    let water_side = SocketsCartesian3D::Simple {
-        x_pos: terrain_sockets.water.void_and_water,
-        x_neg: terrain_sockets.water.water_and_void,
-        z_pos: terrain_sockets.water.layer_up,
-        z_neg: terrain_sockets.water.layer_down,
-        y_pos: terrain_sockets.void,
-        y_neg: terrain_sockets.water.material,
+}
+
+/// Resolve `layer.name` (first variant) or `layer.name.N` to a z level and model index.
+fn resolve_model(reference: &str, names: &ModelNames) -> Option<(u32, ModelIndex)> {
+    if let Some((z, variants)) = names.get(reference) {
+        return Some((*z, *variants.first()?));
     }
-    .to_template()
-    .with_weight(WATER_WEIGHT);
-
-    // Create rotated versions of outer corners
-    terrain_model_builder.create_model(
-        water_corner_out.clone(),
-        vec![SpawnableAsset::new("water_corner_out_tl").with_tile_type(TileType::Water)],
-    );
-    terrain_model_builder.create_model(
-        water_corner_out.rotated(ModelRotation::Rot90, Direction::ZForward),
-        vec![SpawnableAsset::new("water_corner_out_bl").with_tile_type(TileType::Water)],
-    );
-    terrain_model_builder.create_model(
-        water_corner_out.rotated(ModelRotation::Rot180, Direction::ZForward),
-        vec![SpawnableAsset::new("water_corner_out_br").with_tile_type(TileType::Water)],
-    );
-    terrain_model_builder.create_model(
-        water_corner_out.rotated(ModelRotation::Rot270, Direction::ZForward),
-        vec![SpawnableAsset::new("water_corner_out_tr").with_tile_type(TileType::Water)],
-    );
-
-    // Create rotated versions of inner corners
-    terrain_model_builder.create_model(
-        water_corner_in.clone(),
-        vec![SpawnableAsset::new("water_corner_in_tl").with_tile_type(TileType::Water)],
-    );
-    terrain_model_builder.create_model(
-        water_corner_in.rotated(ModelRotation::Rot90, Direction::ZForward),
-        vec![SpawnableAsset::new("water_corner_in_bl").with_tile_type(TileType::Water)],
-    );
-    terrain_model_builder.create_model(
-        water_corner_in.rotated(ModelRotation::Rot180, Direction::ZForward),
-        vec![SpawnableAsset::new("water_corner_in_br").with_tile_type(TileType::Water)],
-    );
-    terrain_model_builder.create_model(
-        water_corner_in.rotated(ModelRotation::Rot270, Direction::ZForward),
-        vec![SpawnableAsset::new("water_corner_in_tr").with_tile_type(TileType::Water)],
-    );
-
-    // Create rotated versions of side edges
-    terrain_model_builder.create_model(
-        water_side.clone(),
-        vec![SpawnableAsset::new("water_side_t").with_tile_type(TileType::Water)],
-    );
-    terrain_model_builder.create_model(
-        water_side.rotated(ModelRotation::Rot90, Direction::ZForward),
-        vec![SpawnableAsset::new("water_side_l").with_tile_type(TileType::Water)],
-    );
-    terrain_model_builder.create_model(
-        water_side.rotated(ModelRotation::Rot180, Direction::ZForward),
-        vec![SpawnableAsset::new("water_side_b").with_tile_type(TileType::Water)],
-    );
-    terrain_model_builder.create_model(
-        water_side.rotated(ModelRotation::Rot270, Direction::ZForward),
-        vec![SpawnableAsset::new("water_side_r").with_tile_type(TileType::Water)],
-    );
-
-    // Add connection rules
-    socket_collection.add_connections(vec![
-        (
-            terrain_sockets.water.material,
-            vec![terrain_sockets.water.material],
-        ),
-        (
-            terrain_sockets.water.water_and_void,
-            vec![terrain_sockets.water.void_and_water],
-        ),
-    ]);
+    let (name, variant) = reference.rsplit_once('.')?;
+    let (z, variants) = names.get(name)?;
+    Some((*z, *variants.get(variant.parse::<usize>().ok()?)?))
+}
 
-    // Connect water layer to yellow grass layer
-    socket_collection.add_rotated_connection(
-        terrain_sockets.yellow_grass.layer_up,
-        vec![terrain_sockets.water.layer_down],
-    );
+/// Resolve the models preset on `cell`, at most one per layer.
+fn resolve_models(
+    rules: &TerrainRules,
+    cell: (i32, i32),
+    references: &[String],
+    names: &ModelNames,
+    errors: &mut Vec<RulesError>,
+) -> Vec<(u32, ModelIndex)> {
+    let mut models: Vec<(u32, ModelIndex)> = Vec::new();
+    for reference in references {
+        match resolve_model(reference, names) {
+            None => errors.push(RulesError::UnknownModel(reference.clone())),
+            Some((z, _)) if models.iter().any(|(other, _)| *other == z) => {
+                errors.push(RulesError::ConflictingPreset {
+                    cell,
+                    layer: rules.layers[z as usize].name.clone(),
+                });
+            }
+            Some(model) => models.push(model),
+        }
+    }
+    models
 }
 
-// src/map/rules.rs - Add this function after build_water_layer
-pub fn build_props_layer(
-    terrain_model_builder: &mut TerrainModelBuilder,
-    terrain_sockets: &TerrainSockets,
-    socket_collection: &mut SocketCollection,
-) {
-    // Void model - represents areas where no props exist
-    terrain_model_builder.create_model(
-        SocketsCartesian3D::Multiple {
-            x_pos: vec![terrain_sockets.void],
-            x_neg: vec![terrain_sockets.void],
-            z_pos: vec![terrain_sockets.props.layer_up],
-            z_neg: vec![terrain_sockets.props.layer_down],
-            y_pos: vec![terrain_sockets.void],
-            y_neg: vec![terrain_sockets.void],
-        },
-        Vec::new(),
-    );
-
-    // Weight constants for different prop types
-    const PROPS_WEIGHT: f32 = 0.025;
-    const ROCKS_WEIGHT: f32 = 0.008;
-    const PLANTS_WEIGHT: f32 = 0.025;
-    const STUMPS_WEIGHT: f32 = 0.012;
-
-    // Base prop template - single tile props
-    let prop = SocketsCartesian3D::Simple {
-        x_pos: terrain_sockets.void,
-        x_neg: terrain_sockets.void,
-        z_pos: terrain_sockets.props.layer_up,
-        z_neg: terrain_sockets.props.props_down,
-        y_pos: terrain_sockets.void,
-        y_neg: terrain_sockets.void,
+fn build_presets(rules: &TerrainRules, names: &ModelNames, errors: &mut Vec<RulesError>) -> NodePresets {
+    let mut presets = NodePresets::default();
+
+    if let Some(clearing) = &rules.presets.spawn_clearing {
+        let models = resolve_models(rules, (0, 0), &clearing.models, names, errors);
+        // Centered on the world origin, where the player spawns
+        let reach = clearing.radius.ceil() as i32;
+        for y in -reach..reach {
+            for x in -reach..reach {
+                let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                if center.length() <= clearing.radius {
+                    presets.cells.insert(IVec2::new(x, y), models.clone());
+                }
+            }
+        }
     }
-    .to_template()
-    .with_weight(PROPS_WEIGHT);
-
-    // Create different prop types with different weights
-    let plant_prop = prop.clone().with_weight(PLANTS_WEIGHT);
-    let stump_prop = prop.clone().with_weight(STUMPS_WEIGHT);
-    let rock_prop = prop.clone().with_weight(ROCKS_WEIGHT);
-
-    // Small tree (2 tiles high)
-    terrain_model_builder.create_model(
-        plant_prop.clone(),
-        vec![
-            SpawnableAsset::new("small_tree_bottom").with_tile_type(TileType::Tree),
-            SpawnableAsset::new("small_tree_top").with_grid_offset(GridDelta::new(0, 1, 0)),
-        ],
-    );
-
-    // Big tree 1 (2x2 tiles)
-    terrain_model_builder
-        .create_model(
-            SocketsCartesian3D::Simple {
-                x_pos: terrain_sockets.props.big_tree_1_base,
-                x_neg: terrain_sockets.void,
-                z_pos: terrain_sockets.props.layer_up,
-                z_neg: terrain_sockets.props.props_down,
-                y_pos: terrain_sockets.void,
-                y_neg: terrain_sockets.void,
-            },
-            vec![
-                SpawnableAsset::new("big_tree_1_bl").with_tile_type(TileType::Tree),
-                SpawnableAsset::new("big_tree_1_tl").with_grid_offset(GridDelta::new(0, 1, 0)),
-            ],
-        )
-        .with_weight(PROPS_WEIGHT);
-
-    terrain_model_builder
-        .create_model(
-            SocketsCartesian3D::Simple {
-                x_pos: terrain_sockets.void,
-                x_neg: terrain_sockets.props.big_tree_1_base,
-                z_pos: terrain_sockets.props.layer_up,
-                z_neg: terrain_sockets.props.props_down,
-                y_pos: terrain_sockets.void,
-                y_neg: terrain_sockets.void,
-            },
-            vec![
-                SpawnableAsset::new("big_tree_1_br").with_tile_type(TileType::Tree),
-                SpawnableAsset::new("big_tree_1_tr").with_grid_offset(GridDelta::new(0, 1, 0)),
-            ],
-        )
-        .with_weight(PROPS_WEIGHT);
-
-    // Big tree 2 (2x2 tiles)
-    terrain_model_builder
-        .create_model(
-            SocketsCartesian3D::Simple {
-                x_pos: terrain_sockets.props.big_tree_2_base,
-                x_neg: terrain_sockets.void,
-                z_pos: terrain_sockets.props.layer_up,
-                z_neg: terrain_sockets.props.props_down,
-                y_pos: terrain_sockets.void,
-                y_neg: terrain_sockets.void,
-            },
-            vec![
-                SpawnableAsset::new("big_tree_2_bl").with_tile_type(TileType::Tree),
-                SpawnableAsset::new("big_tree_2_tl").with_grid_offset(GridDelta::new(0, 1, 0)),
-            ],
-        )
-        .with_weight(PROPS_WEIGHT);
-
-    terrain_model_builder
-        .create_model(
-            SocketsCartesian3D::Simple {
-                x_pos: terrain_sockets.void,
-                x_neg: terrain_sockets.props.big_tree_2_base,
-                z_pos: terrain_sockets.props.layer_up,
-                z_neg: terrain_sockets.props.props_down,
-                y_pos: terrain_sockets.void,
-                y_neg: terrain_sockets.void,
-            },
-            vec![
-                SpawnableAsset::new("big_tree_2_br").with_tile_type(TileType::Tree),
-                SpawnableAsset::new("big_tree_2_tr").with_grid_offset(GridDelta::new(0, 1, 0)),
-            ],
-        )
-        .with_weight(PROPS_WEIGHT);
-
-    // Tree stumps
-    terrain_model_builder.create_model(
-        stump_prop.clone(),
-        vec![SpawnableAsset::new("tree_stump_1").with_tile_type(TileType::Tree)],
-    );
-    terrain_model_builder.create_model(
-        stump_prop.clone(),
-        vec![SpawnableAsset::new("tree_stump_2").with_tile_type(TileType::Tree)],
-    );
-    terrain_model_builder.create_model(
-        stump_prop.clone(),
-        vec![SpawnableAsset::new("tree_stump_3").with_tile_type(TileType::Tree)],
-    );
-
-    // Rocks
-    terrain_model_builder.create_model(rock_prop.clone(), vec![SpawnableAsset::new("rock_1").with_tile_type(TileType::Rock)]);
-    terrain_model_builder.create_model(rock_prop.clone(), vec![SpawnableAsset::new("rock_2").with_tile_type(TileType::Rock)]);
-    terrain_model_builder.create_model(rock_prop.clone(), vec![SpawnableAsset::new("rock_3").with_tile_type(TileType::Rock)]);
-    terrain_model_builder.create_model(rock_prop.clone(), vec![SpawnableAsset::new("rock_4").with_tile_type(TileType::Rock)]);
-
-    // Plants
-    terrain_model_builder.create_model(plant_prop.clone(), vec![SpawnableAsset::new("plant_1").with_tile_type(TileType::Grass).with_pickable(ItemKind::Plant1)]);
-    terrain_model_builder.create_model(plant_prop.clone(), vec![SpawnableAsset::new("plant_2").with_tile_type(TileType::Grass).with_pickable(ItemKind::Plant2)]);
-    terrain_model_builder.create_model(plant_prop.clone(), vec![SpawnableAsset::new("plant_3").with_tile_type(TileType::Grass).with_pickable(ItemKind::Plant3)]);
-    terrain_model_builder.create_model(plant_prop.clone(), vec![SpawnableAsset::new("plant_4").with_tile_type(TileType::Grass).with_pickable(ItemKind::Plant4)]);
-
-    // Add connection rules
-    socket_collection.add_connections(vec![
-        (
-            terrain_sockets.props.big_tree_1_base,
-            vec![terrain_sockets.props.big_tree_1_base],
-        ),
-        (
-            terrain_sockets.props.big_tree_2_base,
-            vec![terrain_sockets.props.big_tree_2_base],
-        ),
-    ]);
-
-    // Connect props to water layer
-    socket_collection
-        .add_rotated_connection(
-            terrain_sockets.water.layer_up,
-            vec![terrain_sockets.props.layer_down],
-        )
-        .add_rotated_connection(
-            terrain_sockets.props.props_down,
-            vec![terrain_sockets.water.ground_up],
-        );
+
+    // Landmarks replace the clearing's models on the layers they use
+    let mut placed = HashSet::new();
+    for landmark in &rules.presets.landmarks {
+        let models = resolve_models(rules, landmark.cell, &landmark.models, names, errors);
+        let cell = IVec2::new(landmark.cell.0, landmark.cell.1);
+        let nodes = presets.cells.entry(cell).or_default();
+        for (z, model) in models {
+            if !placed.insert((cell, z)) {
+                errors.push(RulesError::ConflictingPreset {
+                    cell: landmark.cell,
+                    layer: rules.layers[z as usize].name.clone(),
+                });
+                continue;
+            }
+            nodes.retain(|(other, _)| *other != z);
+            nodes.push((z, model));
+        }
+    }
+
+    presets
 }
 
-pub fn build_world() -> (
-    Vec<Vec<SpawnableAsset>>,
-    ModelCollection<Cartesian3D>,
-    SocketCollection,
-) {
+/// Name of every model as presets reference it: `layer.name` for a model's first variant,
+/// `layer.name.N` for the others. Unnamed models get `#index`, which is only stable
+/// as long as the rules don't change.
+fn model_references(names: &ModelNames, model_count: usize) -> Vec<String> {
+    let mut references: Vec<String> = (0..model_count).map(|index| format!("#{}", index)).collect();
+    for (name, (_, indices)) in names {
+        for (variant, &index) in indices.iter().enumerate() {
+            references[index] = match variant {
+                0 => name.clone(),
+                _ => format!("{}.{}", name, variant),
+            };
+        }
+    }
+    references
+}
+
+fn build_biomes(
+    rules: &BiomeRules,
+    names: &ModelNames,
+    model_count: usize,
+    errors: &mut Vec<RulesError>,
+) -> BiomeMap {
+    if rules.definitions.is_empty() {
+        return BiomeMap::default();
+    }
+    if !(rules.scale > 0.0 && rules.scale.is_finite()) {
+        errors.push(RulesError::InvalidBiomeScale);
+    }
+
+    let model_indices = |reference: &String, errors: &mut Vec<RulesError>| match names.get(reference) {
+        Some((_, indices)) => indices.clone(),
+        None => {
+            errors.push(RulesError::UnknownModel(reference.clone()));
+            Vec::new()
+        }
+    };
+    let groups: HashMap<&str, Vec<ModelIndex>> = rules
+        .groups
+        .iter()
+        .map(|(group, models)| {
+            let indices = models.iter().flat_map(|model| model_indices(model, errors)).collect();
+            (group.as_str(), indices)
+        })
+        .collect();
+
+    let biomes = rules
+        .definitions
+        .iter()
+        .map(|biome| {
+            let mut weights = vec![1.0; model_count];
+            for (name, &scale) in &biome.weights {
+                if !(scale >= 0.0 && scale.is_finite()) {
+                    errors.push(RulesError::InvalidBiomeWeight {
+                        biome: biome.name.clone(),
+                        name: name.clone(),
+                    });
+                    continue;
+                }
+                // Groups first, then single models
+                let indices = match (groups.get(name.as_str()), names.get(name)) {
+                    (Some(indices), _) | (None, Some((_, indices))) => indices,
+                    (None, None) => {
+                        errors.push(RulesError::UnknownBiomeWeight {
+                            biome: biome.name.clone(),
+                            name: name.clone(),
+                        });
+                        continue;
+                    }
+                };
+                for &index in indices {
+                    weights[index] *= scale;
+                }
+            }
+            Biome::new(biome.name.clone(), weights)
+        })
+        .collect();
+
+    BiomeMap::new(rules.scale, biomes)
+}
+
+/// Compile the terrain rules into the models, sockets, per-model assets, preset nodes
+/// and biomes used by the generator. Every problem in the file is reported, not just the first one.
+pub fn build_world(rules: &TerrainRules) -> Result<WorldDefinition, Vec<RulesError>> {
+    let mut errors = Vec::new();
     let mut socket_collection = SocketCollection::new();
-    let terrain_sockets = create_sockets(&mut socket_collection);
+    let table = SocketTable::new(rules, &mut socket_collection, &mut errors);
 
     let mut terrain_model_builder = TerrainModelBuilder::new();
+    let mut names = ModelNames::new();
+    let mut model_layers = Vec::new();
+
+    for (z, layer) in rules.layers.iter().enumerate() {
+        build_layer(
+            z as u32,
+            layer,
+            &table,
+            &mut terrain_model_builder,
+            &mut socket_collection,
+            &mut names,
+            &mut errors,
+        );
+        // Models are created layer by layer
+        model_layers.resize(terrain_model_builder.assets.len(), z as u32);
+    }
+
+    let presets = build_presets(rules, &names, &mut errors);
+    let model_count = terrain_model_builder.assets.len();
+    let biomes = build_biomes(&rules.biomes, &names, model_count, &mut errors);
 
-    // Build dirt layer
-    build_dirt_layer(
-        &mut terrain_model_builder,
-        &terrain_sockets,
-        &mut socket_collection,
-    );
-
-    build_grass_layer(
-        &mut terrain_model_builder,
-        &terrain_sockets,
-        &mut socket_collection,
-    );
-
-    // Build yellow grass layer
-    build_yellow_grass_layer(
-        &mut terrain_model_builder,
-        &terrain_sockets,
-        &mut socket_collection,
-    );
-
-    // Build water layer
-    build_water_layer(
-        &mut terrain_model_builder,
-        &terrain_sockets,
-        &mut socket_collection,
-    );
-
-     // Build props layer
-     build_props_layer(
-        &mut terrain_model_builder,
-        &terrain_sockets,
-        &mut socket_collection,
-    );
+    if !errors.is_empty() {
+        return Err(errors);
+    }
 
     let (assets, models) = terrain_model_builder.into_parts();
 
-    (assets, models, socket_collection)
-}
\ No newline at end of file
+    Ok(WorldDefinition {
+        assets,
+        models,
+        sockets: socket_collection,
+        presets,
+        biomes,
+        references: model_references(&names, model_count),
+        model_layers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::generate::game_terrain_rules;
+
+    /// Rules with one mistake of every kind.
+    const BROKEN_RULES: &str = r#"(
+        shared_sockets: ["void"],
+        layers: [
+            (
+                name: "ground",
+                sockets: ["material", "material"],
+                templates: {
+                    "nested": Template("fill"),
+                },
+                models: [
+                    (
+                        name: Some("fill"),
+                        sockets: Simple(
+                            x_pos: "void", x_neg: "void",
+                            z_pos: "void", z_neg: "void",
+                            y_pos: "void", y_neg: "void",
+                        ),
+                        variants: [(assets: [(sprite: "dirt")])],
+                    ),
+                    (
+                        name: Some("fill"),
+                        sockets: Simple(
+                            x_pos: "ground.missing", x_neg: "void",
+                            z_pos: "void", z_neg: "void",
+                            y_pos: "void", y_neg: "void",
+                        ),
+                        variants: [(assets: [(sprite: "dirt")])],
+                    ),
+                    (
+                        sockets: Template("missing"),
+                        variants: [(assets: [(sprite: "dirt")])],
+                    ),
+                    (
+                        sockets: Simple(
+                            x_pos: "void", x_neg: "void",
+                            z_pos: "void", z_neg: "void",
+                            y_pos: "void", y_neg: "void",
+                        ),
+                        variants: [],
+                    ),
+                ],
+            ),
+        ],
+        presets: (
+            landmarks: [
+                (cell: (1, 1), models: ["ground.nothing"]),
+                (cell: (2, 2), models: ["ground.fill", "ground.fill"]),
+            ],
+        ),
+        biomes: (
+            scale: 0.0,
+            definitions: [(name: "wet", weights: {"nothing": 2.0, "ground.fill": -1.0})],
+        ),
+    )"#;
+
+    #[test]
+    fn build_world_reports_every_error() {
+        let rules: TerrainRules = ron::from_str(BROKEN_RULES).unwrap();
+        let Err(errors) = build_world(&rules) else {
+            panic!("broken rules compiled");
+        };
+        let layer = "ground".to_string();
+        let expected = [
+            RulesError::DuplicateSocket("ground.material".to_string()),
+            RulesError::NestedTemplate { layer: layer.clone(), template: "nested".to_string() },
+            RulesError::UnknownSocket { layer: layer.clone(), socket: "ground.missing".to_string() },
+            RulesError::DuplicateModel { layer: layer.clone(), model: "fill".to_string() },
+            RulesError::UnknownTemplate { layer: layer.clone(), template: "missing".to_string() },
+            RulesError::NoVariants { layer, model: 3 },
+            RulesError::UnknownModel("ground.nothing".to_string()),
+            RulesError::ConflictingPreset { cell: (2, 2), layer: "ground".to_string() },
+            RulesError::InvalidBiomeScale,
+            RulesError::UnknownBiomeWeight { biome: "wet".to_string(), name: "nothing".to_string() },
+            RulesError::InvalidBiomeWeight { biome: "wet".to_string(), name: "ground.fill".to_string() },
+        ];
+        for error in &expected {
+            assert!(errors.contains(error), "missing {error:?} in {errors:#?}");
+        }
+        assert_eq!(errors.len(), expected.len(), "{errors:#?}");
+    }
+
+    #[test]
+    fn build_world_accepts_game_rules() {
+        if let Err(errors) = build_world(&game_terrain_rules()) {
+            panic!("{errors:#?}");
+        }
+    }
+}
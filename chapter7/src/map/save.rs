@@ -0,0 +1,427 @@
+// src/map/save.rs
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_procedural_tilemaps::proc_gen::generator::model::{ModelIndex, ModelInstance};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::collision::TriggerZone;
+use crate::config::map::CHUNK_SIZE;
+use crate::config::save::MAP_FILE;
+use crate::map::assets::SpawnableAsset;
+use crate::map::chunks::{chunk_min_cell, ChunkGenerator, ChunkNodes, TerrainGenerator, WorldChunks};
+use crate::map::export::GeneratedArea;
+use crate::map::regenerate::WorldReset;
+use crate::map::rules::RuleRotation;
+use crate::map::seed::WorldSeed;
+use crate::state::GameState;
+
+/// Version written in new map files. Files with another version are refused.
+pub const MAP_FORMAT_VERSION: u32 = 1;
+
+/// Palette index of a missing node (e.g. the upper layers of a fallback chunk)
+const REMOVED_NODE: u16 = u16::MAX;
+
+/// A generated world: every chunk generated so far, and the seed the chunks
+/// that are still missing are generated with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapFile {
+    pub version: u32,
+    pub seed: u64,
+    pub chunks: Vec<ChunkFile>,
+    /// Trigger zones placed on the map (see [`MapZone`])
+    #[serde(default)]
+    pub zones: Vec<ZoneFile>,
+}
+
+/// The nodes of one chunk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkFile {
+    pub coord: (i32, i32),
+    /// Models used in the chunk, by name (see `model_references`) and rotation
+    pub palette: Vec<(String, RuleRotation)>,
+    /// Palette index of every node, in [`ChunkNodes::nodes`] order
+    pub nodes: Vec<u16>,
+    /// Tile types of the chunk as ASCII (see `TileType::ascii`), top row first.
+    /// Only used to warn when the rules changed since the map was saved.
+    pub tiles: Vec<String>,
+}
+
+/// A [`MapZone`] and where it is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZoneFile {
+    pub position: Vec2,
+    pub zone: TriggerZone,
+}
+
+/// Trigger zone placed on the map itself (from code or a map file), rather than with
+/// the assets of a model. Saved with the map and despawned with the world.
+#[derive(Component, Debug, Clone, Copy, Default)]
+#[require(Transform)]
+pub struct MapZone;
+
+/// Only the version, read before the rest so older or newer files get a clear error.
+#[derive(Deserialize)]
+struct MapFileHeader {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum MapFileError {
+    Io(PathBuf, std::io::Error),
+    Format(String),
+    UnsupportedVersion(u32),
+    UnknownModel(String),
+    InvalidChunk(IVec2),
+}
+
+impl fmt::Display for MapFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapFileError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            MapFileError::Format(err) => write!(f, "invalid map file: {}", err),
+            MapFileError::UnsupportedVersion(version) => write!(
+                f,
+                "map file version {} is not supported (expected {})",
+                version, MAP_FORMAT_VERSION
+            ),
+            MapFileError::UnknownModel(name) => write!(f, "map uses unknown model '{}'", name),
+            MapFileError::InvalidChunk(coord) => write!(f, "chunk {} has invalid nodes", coord),
+        }
+    }
+}
+
+impl MapFile {
+    /// Snapshot of the generated chunks.
+    pub fn capture(
+        seed: WorldSeed,
+        chunks: &HashMap<IVec2, ChunkNodes>,
+        generator: &ChunkGenerator,
+        assets: &[Vec<SpawnableAsset>],
+        zones: Vec<ZoneFile>,
+    ) -> Self {
+        // Sorted so the same world always gives the same file
+        let mut coords: Vec<IVec2> = chunks.keys().copied().collect();
+        coords.sort_by_key(|coord| (coord.y, coord.x));
+
+        let chunks = coords
+            .into_iter()
+            .map(|coord| {
+                let mut palette = Vec::new();
+                let mut palette_indices = HashMap::new();
+                let nodes = chunks[&coord]
+                    .nodes()
+                    .iter()
+                    .map(|node| match node {
+                        None => REMOVED_NODE,
+                        Some(instance) => *palette_indices
+                            .entry((instance.model_index, instance.rotation))
+                            .or_insert_with(|| {
+                                palette.push((
+                                    generator.model_references[instance.model_index].clone(),
+                                    RuleRotation::from(instance.rotation),
+                                ));
+                                (palette.len() - 1) as u16
+                            }),
+                    })
+                    .collect();
+                ChunkFile {
+                    coord: (coord.x, coord.y),
+                    palette,
+                    nodes,
+                    tiles: tile_rows(coord, chunks, generator.layers, assets),
+                }
+            })
+            .collect();
+
+        Self {
+            version: MAP_FORMAT_VERSION,
+            seed: seed.0,
+            chunks,
+            zones,
+        }
+    }
+
+    /// The chunks of the map, with the models of the current rules.
+    pub fn restore(
+        &self,
+        generator: &ChunkGenerator,
+        assets: &[Vec<SpawnableAsset>],
+    ) -> Result<HashMap<IVec2, ChunkNodes>, MapFileError> {
+        if self.version != MAP_FORMAT_VERSION {
+            return Err(MapFileError::UnsupportedVersion(self.version));
+        }
+
+        let model_indices: HashMap<&str, ModelIndex> = generator
+            .model_references
+            .iter()
+            .enumerate()
+            .map(|(index, reference)| (reference.as_str(), index))
+            .collect();
+
+        let mut chunks = HashMap::new();
+        for chunk in &self.chunks {
+            let coord = IVec2::new(chunk.coord.0, chunk.coord.1);
+            let palette = chunk
+                .palette
+                .iter()
+                .map(|(reference, rotation)| {
+                    let model_index = *model_indices
+                        .get(reference.as_str())
+                        .ok_or_else(|| MapFileError::UnknownModel(reference.clone()))?;
+                    Ok(ModelInstance {
+                        model_index,
+                        rotation: (*rotation).into(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let nodes = chunk
+                .nodes
+                .iter()
+                .map(|&index| match index {
+                    REMOVED_NODE => Ok(None),
+                    _ => palette
+                        .get(index as usize)
+                        .map(|instance| Some(*instance))
+                        .ok_or(MapFileError::InvalidChunk(coord)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let nodes = ChunkNodes::from_nodes(nodes, generator.layers).ok_or(MapFileError::InvalidChunk(coord))?;
+            chunks.insert(coord, nodes);
+        }
+
+        // Same models, but the rules may give them other sprites or tile types now
+        let changed = self
+            .chunks
+            .iter()
+            .filter(|chunk| {
+                let coord = IVec2::new(chunk.coord.0, chunk.coord.1);
+                tile_rows(coord, &chunks, generator.layers, assets) != chunk.tiles
+            })
+            .count();
+        if changed > 0 {
+            warn!("{} chunks of the loaded map differ from when it was saved (terrain rules changed?)", changed);
+        }
+
+        Ok(chunks)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, MapFileError> {
+        let text = fs::read_to_string(path).map_err(|err| MapFileError::Io(path.to_path_buf(), err))?;
+        let header: MapFileHeader = ron::from_str(&text).map_err(|err| MapFileError::Format(err.to_string()))?;
+        if header.version != MAP_FORMAT_VERSION {
+            return Err(MapFileError::UnsupportedVersion(header.version));
+        }
+        ron::from_str(&text).map_err(|err| MapFileError::Format(err.to_string()))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), MapFileError> {
+        let text = ron::ser::to_string_pretty(self, PrettyConfig::new().compact_arrays(true))
+            .map_err(|err| MapFileError::Format(err.to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| MapFileError::Io(dir.to_path_buf(), err))?;
+        }
+        fs::write(path, text).map_err(|err| MapFileError::Io(path.to_path_buf(), err))
+    }
+}
+
+/// ASCII tile rows of a chunk, with the assets its neighbours place over it (tree tops...).
+fn tile_rows(
+    coord: IVec2,
+    chunks: &HashMap<IVec2, ChunkNodes>,
+    layers: u32,
+    assets: &[Vec<SpawnableAsset>],
+) -> Vec<String> {
+    let nearby = (-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| coord + IVec2::new(dx, dy)))
+        .filter_map(|neighbour| Some((neighbour, chunks.get(&neighbour)?.clone())))
+        .collect();
+    GeneratedArea::from_chunks(layers, nearby, chunk_min_cell(coord), UVec2::splat(CHUNK_SIZE))
+        .to_ascii(assets)
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Save the generated chunks to a map file.
+#[derive(Message, Debug, Clone)]
+pub struct SaveMap {
+    pub path: PathBuf,
+}
+
+/// Replace the world with a saved map. Chunks missing from the map are generated
+/// with its seed as usual.
+#[derive(Message, Debug, Clone)]
+pub struct LoadMap {
+    pub path: PathBuf,
+}
+
+/// F6 saves the map to `MAP_FILE`, F9 loads it back.
+pub fn save_load_on_key(
+    input: Res<ButtonInput<KeyCode>>,
+    mut save: MessageWriter<SaveMap>,
+    mut load: MessageWriter<LoadMap>,
+) {
+    if input.just_pressed(KeyCode::F6) {
+        save.write(SaveMap { path: PathBuf::from(MAP_FILE) });
+    } else if input.just_pressed(KeyCode::F9) {
+        load.write(LoadMap { path: PathBuf::from(MAP_FILE) });
+    }
+}
+
+pub fn save_map(
+    mut requests: MessageReader<SaveMap>,
+    chunks: Res<WorldChunks>,
+    generator: Res<TerrainGenerator>,
+    seed: Res<WorldSeed>,
+    zones: Query<(&TriggerZone, &GlobalTransform), With<MapZone>>,
+) {
+    for request in requests.read() {
+        let zones = zones
+            .iter()
+            .map(|(zone, transform)| ZoneFile {
+                position: transform.translation().truncate(),
+                zone: zone.clone(),
+            })
+            .collect();
+        let file = MapFile::capture(
+            *seed,
+            chunks.generated(),
+            &generator.chunks,
+            &generator.assets_definitions,
+            zones,
+        );
+        match file.write(&request.path) {
+            Ok(()) => info!(
+                "Saved {} chunks and {} zones to {}",
+                file.chunks.len(),
+                file.zones.len(),
+                request.path.display()
+            ),
+            Err(err) => error!("Could not save the map: {}", err),
+        }
+    }
+}
+
+/// Respawn the world from a map file, without running the generator or the connectivity
+/// pass on the saved chunks, and place its zones. The current world is kept if the file can't be loaded.
+pub fn load_map(
+    mut commands: Commands,
+    mut requests: MessageReader<LoadMap>,
+    mut reset: WorldReset,
+    mut seed: ResMut<WorldSeed>,
+    generator: Res<TerrainGenerator>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // Several requests in one frame: the last one wins
+    let Some(request) = requests.read().last() else {
+        return;
+    };
+
+    let chunks = MapFile::read(&request.path).and_then(|file| {
+        let chunks = file.restore(&generator.chunks, &generator.assets_definitions)?;
+        Ok((file.seed, chunks, file.zones))
+    });
+    let (map_seed, chunks, zones) = match chunks {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            error!("Could not load the map: {}", err);
+            return;
+        }
+    };
+
+    info!("Loaded {} chunks from {}", chunks.len(), request.path.display());
+    *seed = WorldSeed(map_seed);
+    reset.load(chunks);
+    for ZoneFile { position, zone } in zones {
+        commands.spawn((MapZone, zone, Transform::from_translation(position.extend(0.0))));
+    }
+
+    // Chunks only stream while playing
+    next_state.set(GameState::Playing);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::generate::game_chunk_generator;
+
+    /// Two generated chunks side by side, with one node removed from the first.
+    fn generated(generator: &ChunkGenerator) -> HashMap<IVec2, ChunkNodes> {
+        let mut chunks = HashMap::new();
+        for coord in [IVec2::ZERO, IVec2::X] {
+            assert!(generator.generate_with_dependencies(coord, WorldSeed(42), &mut chunks).is_empty());
+        }
+        let chunk = chunks.get_mut(&IVec2::ZERO).unwrap();
+        let (x, y, z) = (0..generator.layers)
+            .flat_map(|z| (0..CHUNK_SIZE).flat_map(move |y| (0..CHUNK_SIZE).map(move |x| (x, y, z))))
+            .find(|&(x, y, z)| chunk.get(x, y, z).is_some())
+            .unwrap();
+        chunk.remove(x, y, z);
+        chunks
+    }
+
+    #[test]
+    fn capture_restore_round_trip() {
+        let (assets, generator) = game_chunk_generator();
+        let chunks = generated(&generator);
+
+        let file = MapFile::capture(WorldSeed(42), &chunks, &generator, &assets, Vec::new());
+        let text = ron::ser::to_string(&file).unwrap();
+        let file: MapFile = ron::from_str(&text).unwrap();
+        assert_eq!(file.seed, 42);
+        assert!(file.chunks[0].nodes.contains(&REMOVED_NODE));
+
+        let restored = file.restore(&generator, &assets).unwrap();
+        assert_eq!(restored.len(), chunks.len());
+        for (coord, nodes) in &chunks {
+            assert_eq!(restored[coord].nodes(), nodes.nodes(), "chunk {coord}");
+        }
+    }
+
+    #[test]
+    fn restore_refuses_other_version() {
+        let (assets, generator) = game_chunk_generator();
+        let chunks = generated(&generator);
+        let mut file = MapFile::capture(WorldSeed(42), &chunks, &generator, &assets, Vec::new());
+        file.version = MAP_FORMAT_VERSION + 1;
+
+        let result = file.restore(&generator, &assets);
+        assert!(
+            matches!(result, Err(MapFileError::UnsupportedVersion(version)) if version == MAP_FORMAT_VERSION + 1),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn read_refuses_other_version_before_parsing_the_rest() {
+        let path = std::env::temp_dir().join(format!("chapter7-map-version-{}.ron", std::process::id()));
+        // Not a valid map otherwise: the version alone must give the error
+        fs::write(&path, "(version: 0, chunks: \"?\")").unwrap();
+        let result = MapFile::read(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(MapFileError::UnsupportedVersion(0))), "{result:?}");
+    }
+
+    #[test]
+    fn restore_refuses_unknown_model_and_bad_palette_index() {
+        let (assets, generator) = game_chunk_generator();
+        let chunks = generated(&generator);
+        let file = MapFile::capture(WorldSeed(42), &chunks, &generator, &assets, Vec::new());
+
+        let mut unknown = file.clone();
+        unknown.chunks[0].palette[0].0 = "no_such_model".to_string();
+        let result = unknown.restore(&generator, &assets);
+        assert!(matches!(&result, Err(MapFileError::UnknownModel(name)) if name == "no_such_model"), "{result:?}");
+
+        let mut invalid = file;
+        invalid.chunks[0].nodes[0] = invalid.chunks[0].palette.len() as u16;
+        let result = invalid.restore(&generator, &assets);
+        assert!(matches!(result, Err(MapFileError::InvalidChunk(IVec2::ZERO))), "{result:?}");
+    }
+}
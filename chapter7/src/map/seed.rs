@@ -0,0 +1,87 @@
+// src/map/seed.rs
+use bevy::prelude::*;
+
+/// Command-line flag used to pick the world seed (`--seed 1234` or `--seed=1234`).
+const SEED_ARG: &str = "--seed";
+/// Environment variable read when no `--seed` argument is given.
+const SEED_ENV: &str = "WORLD_SEED";
+/// Mixed into the biome seeds so they don't repeat the chunk seeds.
+const BIOME_SALT: u64 = 0xB10E_5EED;
+
+/// Seed fed to the WFC generator.
+/// The same seed always produces the same map, spawn positions and enemy placement.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldSeed(pub u64);
+
+impl WorldSeed {
+    /// Read the seed from the command line, then from `WORLD_SEED`, otherwise pick a random one.
+    pub fn from_args_or_env() -> Self {
+        if let Some(value) = seed_from_args(std::env::args().skip(1))
+            && let Some(seed) = parse_seed(&value, "command line")
+        {
+            return Self(seed);
+        }
+
+        if let Ok(value) = std::env::var(SEED_ENV)
+            && let Some(seed) = parse_seed(&value, SEED_ENV)
+        {
+            return Self(seed);
+        }
+
+        Self::random()
+    }
+
+    /// A random seed, printed in the pause menu so the world can be reproduced.
+    pub fn random() -> Self {
+        Self(rand::random())
+    }
+
+    /// Seed for a single chunk, mixed from the world seed and the chunk coordinates
+    /// (so neighbouring chunks get unrelated seeds).
+    pub fn for_chunk(&self, coord: IVec2) -> u64 {
+        let coords = ((coord.x as u32 as u64) << 32) | coord.y as u32 as u64;
+        split_mix(self.0 ^ coords)
+    }
+
+    /// Seed of the noise field of biome `index`, unrelated to the chunk seeds.
+    pub fn for_biome(&self, index: usize) -> Self {
+        Self(split_mix(self.0 ^ split_mix(BIOME_SALT.wrapping_add(index as u64))))
+    }
+
+    /// Another seed derived from this one, used when a generated world is rejected.
+    /// Deterministic, so the first seed still reproduces the final world.
+    pub fn reroll(&self) -> Self {
+        Self(split_mix(self.0.wrapping_add(1)))
+    }
+}
+
+/// SplitMix64 finalizer.
+fn split_mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Find the value following `--seed` (either as the next argument or after `=`).
+fn seed_from_args(mut args: impl Iterator<Item = String>) -> Option<String> {
+    while let Some(arg) = args.next() {
+        if arg == SEED_ARG {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(SEED_ARG).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+fn parse_seed(value: &str, source: &str) -> Option<u64> {
+    match value.trim().parse::<u64>() {
+        Ok(seed) => Some(seed),
+        Err(_) => {
+            warn!("Ignoring invalid world seed '{}' from {}", value, source);
+            None
+        }
+    }
+}
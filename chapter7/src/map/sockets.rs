@@ -1,92 +0,0 @@
-use bevy_procedural_tilemaps::prelude::*;
-
-pub struct TerrainSockets {
-    pub dirt: DirtLayerSockets,
-    pub void: Socket,
-    pub grass: GrassLayerSockets,
-    pub yellow_grass: YellowGrassLayerSockets,
-    pub water: WaterLayerSockets,
-    pub props: PropsLayerSockets, // Add this line
-}
-
-pub struct DirtLayerSockets {
-    pub layer_up: Socket,      // What can sit on top of dirt
-    pub layer_down: Socket,     // What dirt can sit on
-    pub material: Socket,       // What dirt connects to horizontally
-}
-
-pub struct GrassLayerSockets {
-    pub layer_up: Socket,
-    pub layer_down: Socket,
-    pub material: Socket,
-    pub void_and_grass: Socket,
-    pub grass_and_void: Socket,
-    pub grass_fill_up: Socket,
-}
-
-pub struct YellowGrassLayerSockets {
-    pub layer_up: Socket,
-    pub layer_down: Socket,
-    pub yellow_grass_fill_down: Socket,
-}
-
-pub struct WaterLayerSockets {
-    pub layer_up: Socket,
-    pub layer_down: Socket,
-    pub material: Socket,
-    pub void_and_water: Socket,
-    pub water_and_void: Socket,
-    pub ground_up: Socket,
-}
-
-pub struct PropsLayerSockets {
-    pub layer_up: Socket,
-    pub layer_down: Socket,
-    pub props_down: Socket,
-    pub big_tree_1_base: Socket,
-    pub big_tree_2_base: Socket,
-}
-
-
-
-pub fn create_sockets(socket_collection: &mut SocketCollection) -> TerrainSockets {
-    let mut new_socket = || -> Socket { socket_collection.create() };
-    
-    let sockets = TerrainSockets {
-        dirt: DirtLayerSockets {
-            layer_up: new_socket(),
-            material: new_socket(),
-            layer_down: new_socket(),
-        },
-        void: new_socket(),
-        grass: GrassLayerSockets {
-            layer_up: new_socket(),
-            material: new_socket(),
-            layer_down: new_socket(),
-            void_and_grass: new_socket(),
-            grass_and_void: new_socket(),
-            grass_fill_up: new_socket(),
-        },
-        yellow_grass: YellowGrassLayerSockets {
-            layer_up: new_socket(),
-            layer_down: new_socket(),
-            yellow_grass_fill_down: new_socket(),
-        },
-        water: WaterLayerSockets {
-            layer_up: new_socket(),
-            layer_down: new_socket(),
-            material: new_socket(),
-            void_and_water: new_socket(),
-            water_and_void: new_socket(),
-            ground_up: new_socket(),
-        },
-        props: PropsLayerSockets {
-            layer_up: new_socket(),
-            layer_down: new_socket(),
-            props_down: new_socket(),
-            big_tree_1_base: new_socket(),
-            big_tree_2_base: new_socket(),
-        },
-    };
-    sockets
-}
\ No newline at end of file
@@ -1,17 +1,96 @@
 use bevy::math::{URect, UVec2};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
 
+#[derive(Debug, Clone, Deserialize)]
 pub struct TilemapSprite {
-    pub name: &'static str,
+    pub name: String,
     pub pixel_x: u32,
     pub pixel_y: u32,
 }
 
+/// How the tiles playing the same animation line up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum AnimationPhase {
+    /// Every tile shows the same frame
+    #[default]
+    Synced,
+    /// Each tile starts at a frame picked from its position
+    PerTile,
+}
+
+/// Frames played by every tile spawned with `sprite`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TilemapAnimation {
+    pub sprite: String,
+    /// Sprite names, in order (a sprite may come back, e.g. for a ping-pong loop)
+    pub frames: Vec<String>,
+    /// Seconds per frame
+    pub frame_time: f32,
+    #[serde(default)]
+    pub phase: AnimationPhase,
+}
+
+/// Atlas layout loaded from `tile_layers/tilemap.ron`.
+#[derive(Asset, TypePath, Debug, Clone, Deserialize)]
 pub struct TilemapDefinition {
     pub tile_width: u32,
     pub tile_height: u32,
     pub atlas_width: u32,
     pub atlas_height: u32,
-    pub sprites: &'static [TilemapSprite],
+    pub sprites: Vec<TilemapSprite>,
+    #[serde(default)]
+    pub animations: Vec<TilemapAnimation>,
+}
+
+/// Problems found while validating a [`TilemapDefinition`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TilemapError {
+    /// Two sprites share the same name
+    DuplicateSprite(String),
+    /// A sprite rect does not fit inside the atlas image
+    SpriteOutOfBounds { name: String, rect: URect },
+    /// A sprite is referenced by the terrain rules but not defined in the atlas
+    MissingSprite(String),
+    /// Two animations play on the same sprite
+    DuplicateAnimation(String),
+    /// An animation has no frames or a frame time that isn't positive
+    InvalidAnimation(String),
+    /// An animation frame is not defined in the atlas
+    MissingFrame { animation: String, frame: String },
+}
+
+impl fmt::Display for TilemapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TilemapError::DuplicateSprite(name) => {
+                write!(f, "sprite '{}' is defined more than once", name)
+            }
+            TilemapError::SpriteOutOfBounds { name, rect } => write!(
+                f,
+                "sprite '{}' ({:?}..{:?}) falls outside the atlas",
+                name, rect.min, rect.max
+            ),
+            TilemapError::MissingSprite(name) => {
+                write!(f, "sprite '{}' is used by the rules but missing from the atlas", name)
+            }
+            TilemapError::DuplicateAnimation(name) => {
+                write!(f, "sprite '{}' has more than one animation", name)
+            }
+            TilemapError::InvalidAnimation(name) => write!(
+                f,
+                "animation of sprite '{}' needs at least one frame and a positive frame time",
+                name
+            ),
+            TilemapError::MissingFrame { animation, frame } => write!(
+                f,
+                "animation of sprite '{}' uses frame '{}', missing from the atlas",
+                animation, frame
+            ),
+        }
+    }
 }
 
 impl TilemapDefinition {
@@ -27,323 +106,63 @@ impl TilemapDefinition {
         self.sprites.iter().position(|sprite| sprite.name == name)
     }
 
+    /// Index of the animation playing on `sprite`, if any.
+    pub fn animation_index(&self, sprite: &str) -> Option<usize> {
+        self.animations.iter().position(|animation| animation.sprite == sprite)
+    }
+
     pub fn sprite_rect(&self, index: usize) -> URect {
         let sprite = &self.sprites[index];
         let min = UVec2::new(sprite.pixel_x, sprite.pixel_y);
         URect::from_corners(min, min + self.tile_size())
     }
-}
 
-pub const TILEMAP: TilemapDefinition = TilemapDefinition {
-    tile_width: 32,
-    tile_height: 32,
-    atlas_width: 256,
-    atlas_height: 320,
-    sprites: &[
-          TilemapSprite {
-            name: "dirt",
-            pixel_x: 128,
-            pixel_y: 0,
-        },
-        TilemapSprite {
-            name: "green_grass",
-            pixel_x: 160,
-            pixel_y: 0,
-        },
-        TilemapSprite {
-            name: "green_grass_corner_in_tl",
-            pixel_x: 192,
-            pixel_y: 0,
-        },
-        TilemapSprite {
-            name: "green_grass_corner_in_tr",
-            pixel_x: 224,
-            pixel_y: 0,
-        },
-        TilemapSprite {
-            name: "green_grass_corner_in_bl",
-            pixel_x: 192,
-            pixel_y: 32,
-        },
-        TilemapSprite {
-            name: "green_grass_corner_in_br",
-            pixel_x: 224,
-            pixel_y: 32,
-        },
-        TilemapSprite {
-            name: "green_grass_corner_out_tl",
-            pixel_x: 0,
-            pixel_y: 64,
-        },
-        TilemapSprite {
-            name: "green_grass_corner_out_tr",
-            pixel_x: 32,
-            pixel_y: 64,
-        },
-        TilemapSprite {
-            name: "green_grass_corner_out_bl",
-            pixel_x: 0,
-            pixel_y: 96,
-        },
-        TilemapSprite {
-            name: "green_grass_corner_out_br",
-            pixel_x: 32,
-            pixel_y: 96,
-        },
-        TilemapSprite {
-            name: "green_grass_side_t",
-            pixel_x: 64,
-            pixel_y: 64,
-        },
-        TilemapSprite {
-            name: "green_grass_side_r",
-            pixel_x: 96,
-            pixel_y: 64,
-        },
-        TilemapSprite {
-            name: "green_grass_side_l",
-            pixel_x: 64,
-            pixel_y: 96,
-        },
-        TilemapSprite {
-            name: "green_grass_side_b",
-            pixel_x: 96,
-            pixel_y: 96,
-        },
-        TilemapSprite {
-            name: "yellow_grass",
-            pixel_x: 0,
-            pixel_y: 256,
-        },
-        TilemapSprite {
-            name: "yellow_grass_corner_in_tl",
-            pixel_x: 32,
-            pixel_y: 256,
-        },
-        TilemapSprite {
-            name: "yellow_grass_corner_in_tr",
-            pixel_x: 64,
-            pixel_y: 256,
-        },
-        TilemapSprite {
-            name: "yellow_grass_corner_in_bl",
-            pixel_x: 32,
-            pixel_y: 288,
-        },
-        TilemapSprite {
-            name: "yellow_grass_corner_in_br",
-            pixel_x: 64,
-            pixel_y: 288,
-        },
-        TilemapSprite {
-            name: "yellow_grass_corner_out_tl",
-            pixel_x: 96,
-            pixel_y: 256,
-        },
-        TilemapSprite {
-            name: "yellow_grass_corner_out_tr",
-            pixel_x: 128,
-            pixel_y: 256,
-        },
-        TilemapSprite {
-            name: "yellow_grass_corner_out_bl",
-            pixel_x: 96,
-            pixel_y: 288,
-        },
-        TilemapSprite {
-            name: "yellow_grass_corner_out_br",
-            pixel_x: 128,
-            pixel_y: 288,
-        },
-        TilemapSprite {
-            name: "yellow_grass_side_t",
-            pixel_x: 160,
-            pixel_y: 256,
-        },
-        TilemapSprite {
-            name: "yellow_grass_side_r",
-            pixel_x: 192,
-            pixel_y: 256,
-        },
-        TilemapSprite {
-            name: "yellow_grass_side_l",
-            pixel_x: 160,
-            pixel_y: 288,
-        },
-        TilemapSprite {
-            name: "yellow_grass_side_b",
-            pixel_x: 192,
-            pixel_y: 288,
-        },
-        TilemapSprite {
-            name: "water",
-            pixel_x: 32,
-            pixel_y: 192,
-        },
-        TilemapSprite {
-            name: "water_corner_in_tl",
-            pixel_x: 64,
-            pixel_y: 192,
-        },
-        TilemapSprite {
-            name: "water_corner_in_tr",
-            pixel_x: 96,
-            pixel_y: 192,
-        },
-        TilemapSprite {
-            name: "water_corner_in_bl",
-            pixel_x: 64,
-            pixel_y: 224,
-        },
-        TilemapSprite {
-            name: "water_corner_in_br",
-            pixel_x: 96,
-            pixel_y: 224,
-        },
-        TilemapSprite {
-            name: "water_corner_out_tl",
-            pixel_x: 128,
-            pixel_y: 192,
-        },
-        TilemapSprite {
-            name: "water_corner_out_tr",
-            pixel_x: 160,
-            pixel_y: 192,
-        },
-        TilemapSprite {
-            name: "water_corner_out_bl",
-            pixel_x: 128,
-            pixel_y: 224,
-        },
-        TilemapSprite {
-            name: "water_corner_out_br",
-            pixel_x: 160,
-            pixel_y: 224,
-        },
-        TilemapSprite {
-            name: "water_side_t",
-            pixel_x: 192,
-            pixel_y: 192,
-        },
-        TilemapSprite {
-            name: "water_side_r",
-            pixel_x: 224,
-            pixel_y: 192,
-        },
-        TilemapSprite {
-            name: "water_side_l",
-            pixel_x: 192,
-            pixel_y: 224,
-        },
-        TilemapSprite {
-            name: "water_side_b",
-            pixel_x: 224,
-            pixel_y: 224,
-        },
-        TilemapSprite {
-            name: "big_tree_1_tl",
-            pixel_x: 0,
-            pixel_y: 0,
-        },
-        TilemapSprite {
-            name: "big_tree_1_tr",
-            pixel_x: 32,
-            pixel_y: 0,
-        },
-        TilemapSprite {
-            name: "big_tree_1_bl",
-            pixel_x: 0,
-            pixel_y: 32,
-        },
-        TilemapSprite {
-            name: "big_tree_1_br",
-            pixel_x: 32,
-            pixel_y: 32,
-        },
-        TilemapSprite {
-            name: "big_tree_2_tl",
-            pixel_x: 64,
-            pixel_y: 0,
-        },
-        TilemapSprite {
-            name: "big_tree_2_tr",
-            pixel_x: 96,
-            pixel_y: 0,
-        },
-        TilemapSprite {
-            name: "big_tree_2_bl",
-            pixel_x: 64,
-            pixel_y: 32,
-        },
-        TilemapSprite {
-            name: "big_tree_2_br",
-            pixel_x: 96,
-            pixel_y: 32,
-        },
-        TilemapSprite {
-            name: "plant_1",
-            pixel_x: 128,
-            pixel_y: 64,
-        },
-        TilemapSprite {
-            name: "plant_2",
-            pixel_x: 160,
-            pixel_y: 64,
-        },
-        TilemapSprite {
-            name: "plant_3",
-            pixel_x: 192,
-            pixel_y: 64,
-        },
-        TilemapSprite {
-            name: "plant_4",
-            pixel_x: 224,
-            pixel_y: 64,
-        },
-        TilemapSprite {
-            name: "rock_1",
-            pixel_x: 0,
-            pixel_y: 128,
-        },
-        TilemapSprite {
-            name: "rock_2",
-            pixel_x: 32,
-            pixel_y: 128,
-        },
-        TilemapSprite {
-            name: "rock_3",
-            pixel_x: 64,
-            pixel_y: 128,
-        },
-        TilemapSprite {
-            name: "rock_4",
-            pixel_x: 96,
-            pixel_y: 128,
-        },
-        TilemapSprite {
-            name: "small_tree_top",
-            pixel_x: 128,
-            pixel_y: 128,
-        },
-        TilemapSprite {
-            name: "small_tree_bottom",
-            pixel_x: 128,
-            pixel_y: 160,
-        },
-        TilemapSprite {
-            name: "tree_stump_1",
-            pixel_x: 192,
-            pixel_y: 128,
-        },
-        TilemapSprite {
-            name: "tree_stump_2",
-            pixel_x: 224,
-            pixel_y: 128,
-        },
-        TilemapSprite {
-            name: "tree_stump_3",
-            pixel_x: 0,
-            pixel_y: 192,
-        },
-    ]
-};
\ No newline at end of file
+    /// Check the atlas for duplicate names, out-of-bounds rects, broken animations and
+    /// sprites that are `referenced` elsewhere but not defined here.
+    pub fn validate<'a>(&self, referenced: impl IntoIterator<Item = &'a str>) -> Vec<TilemapError> {
+        let mut errors = Vec::new();
+        let mut names = HashSet::new();
+        let atlas = self.atlas_size();
+
+        for (index, sprite) in self.sprites.iter().enumerate() {
+            if !names.insert(sprite.name.as_str()) {
+                errors.push(TilemapError::DuplicateSprite(sprite.name.clone()));
+            }
+
+            let rect = self.sprite_rect(index);
+            if rect.max.x > atlas.x || rect.max.y > atlas.y {
+                errors.push(TilemapError::SpriteOutOfBounds {
+                    name: sprite.name.clone(),
+                    rect,
+                });
+            }
+        }
+
+        let mut animated = HashSet::new();
+        for animation in &self.animations {
+            if !animated.insert(animation.sprite.as_str()) {
+                errors.push(TilemapError::DuplicateAnimation(animation.sprite.clone()));
+            }
+            if animation.frames.is_empty() || animation.frame_time.is_nan() || animation.frame_time <= 0.0 {
+                errors.push(TilemapError::InvalidAnimation(animation.sprite.clone()));
+            }
+            for frame in &animation.frames {
+                if !names.contains(frame.as_str()) {
+                    errors.push(TilemapError::MissingFrame {
+                        animation: animation.sprite.clone(),
+                        frame: frame.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut missing = HashSet::new();
+        for name in referenced {
+            if !names.contains(name) && missing.insert(name) {
+                errors.push(TilemapError::MissingSprite(name.to_string()));
+            }
+        }
+
+        errors
+    }
+}
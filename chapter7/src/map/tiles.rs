@@ -0,0 +1,134 @@
+// src/map/tiles.rs
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::collision::TileType;
+use crate::config::map::CHUNK_SIZE;
+use crate::map::assets::SpawnableAsset;
+use crate::map::chunks::{chunk_min_cell, ChunkNodes};
+
+/// Layer, then row (lower first), then column, see [`tile_priority`].
+pub type TilePriority = (i32, i32, i32);
+
+/// Priority of an asset giving its cell a tile type, when several assets cover the
+/// same cell: the highest layer wins (node z level plus the asset's z offset), then the
+/// node lowest on the map (it is drawn in front), then the leftmost node. This is also
+/// the order tiles are drawn in, without depending on sprite z values.
+pub fn tile_priority(z: u32, node: IVec2, offset_z: i32) -> TilePriority {
+    (z as i32 + offset_z, -node.y, node.x)
+}
+
+/// How far (in cells) assets with a tile type reach from their node. Changing a node
+/// can change the tile type of cells this far away.
+pub fn tile_reach(assets: &[Vec<SpawnableAsset>]) -> i32 {
+    assets
+        .iter()
+        .flatten()
+        .filter(|asset| asset.tile_type().is_some())
+        .map(|asset| asset.grid_offset().dx.abs().max(asset.grid_offset().dy.abs()))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Tile type of each cell of the rectangle starting at cell `min`, row by row from the
+/// bottom, read from the generated nodes (see [`tile_priority`]). Assets of nodes just
+/// outside the rectangle that overhang into it count too. Cells without any tile type,
+/// or in chunks that aren't generated, are `None`.
+pub fn tile_types(
+    generated: &HashMap<IVec2, ChunkNodes>,
+    layers: u32,
+    assets: &[Vec<SpawnableAsset>],
+    min: IVec2,
+    size: UVec2,
+) -> Vec<Option<TileType>> {
+    let size = size.as_ivec2();
+    let mut tiles: Vec<Option<(TileType, TilePriority)>> = vec![None; (size.x * size.y) as usize];
+
+    let reach = tile_reach(assets);
+
+    let chunk_size = IVec2::splat(CHUNK_SIZE as i32);
+    for y in min.y - reach..min.y + size.y + reach {
+        for x in min.x - reach..min.x + size.x + reach {
+            let node = IVec2::new(x, y);
+            let coord = node.div_euclid(chunk_size);
+            let Some(nodes) = generated.get(&coord) else {
+                continue;
+            };
+            let local = (node - chunk_min_cell(coord)).as_uvec2();
+
+            for z in 0..layers {
+                let Some(instance) = nodes.get(local.x, local.y, z) else {
+                    continue;
+                };
+                for asset in assets.get(instance.model_index).into_iter().flatten() {
+                    let Some(tile_type) = asset.tile_type() else {
+                        continue;
+                    };
+                    let offset = asset.grid_offset();
+                    let cell = node + IVec2::new(offset.dx, offset.dy) - min;
+                    if cell.x < 0 || cell.y < 0 || cell.x >= size.x || cell.y >= size.y {
+                        continue;
+                    }
+
+                    let priority = tile_priority(z, node, offset.dz);
+                    let tile = &mut tiles[(cell.y * size.x + cell.x) as usize];
+                    // Among the assets of one node, the last one wins
+                    if tile.is_none_or(|(_, current)| priority >= current) {
+                        *tile = Some((tile_type, priority));
+                    }
+                }
+            }
+        }
+    }
+
+    tiles.into_iter().map(|tile| tile.map(|(tile_type, _)| tile_type)).collect()
+}
+
+/// Node (cell and layer) whose asset gives `cell` its tile type in [`tile_types`], with
+/// that tile type. It may be a neighbouring node whose asset overhangs into `cell`.
+/// `None` if no asset gives the cell a tile type.
+pub fn tile_source(
+    generated: &HashMap<IVec2, ChunkNodes>,
+    layers: u32,
+    assets: &[Vec<SpawnableAsset>],
+    cell: IVec2,
+) -> Option<(IVec2, u32, TileType)> {
+    let reach = tile_reach(assets);
+    let chunk_size = IVec2::splat(CHUNK_SIZE as i32);
+    let mut source: Option<((IVec2, u32, TileType), TilePriority)> = None;
+
+    for y in cell.y - reach..=cell.y + reach {
+        for x in cell.x - reach..=cell.x + reach {
+            let node = IVec2::new(x, y);
+            let coord = node.div_euclid(chunk_size);
+            let Some(nodes) = generated.get(&coord) else {
+                continue;
+            };
+            let local = (node - chunk_min_cell(coord)).as_uvec2();
+
+            for z in 0..layers {
+                let Some(instance) = nodes.get(local.x, local.y, z) else {
+                    continue;
+                };
+                for asset in assets.get(instance.model_index).into_iter().flatten() {
+                    let Some(tile_type) = asset.tile_type() else {
+                        continue;
+                    };
+                    let offset = asset.grid_offset();
+                    if node + IVec2::new(offset.dx, offset.dy) != cell {
+                        continue;
+                    }
+
+                    // Same order as `tile_types`: among the assets of one node, the last one wins
+                    let priority = tile_priority(z, node, offset.dz);
+                    if source.is_none_or(|(_, current)| priority >= current) {
+                        source = Some(((node, z, tile_type), priority));
+                    }
+                }
+            }
+        }
+    }
+
+    source.map(|(source, _)| source)
+}
@@ -0,0 +1,43 @@
+use bevy::prelude::*;
+
+use crate::map::seed::WorldSeed;
+
+#[derive(Component)]
+pub struct GameOverScreen;
+
+pub fn spawn_game_over_screen(mut commands: Commands, seed: Res<WorldSeed>) {
+    commands.spawn((
+        GameOverScreen,
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.2, 0.0, 0.0, 0.7)),
+    )).with_children(|parent| {
+        parent.spawn((
+            Text::new(format!("GAME OVER\n\nPress R to try this world again\nPress F5 for a new world\n\nWorld seed: {}", seed.0)),
+            TextFont {
+                font_size: 36.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            TextLayout::new_with_justify(Justify::Center),
+        ));
+    });
+
+    info!("Game over screen spawned");
+}
+
+pub fn despawn_game_over_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<GameOverScreen>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    info!("Game over screen despawned");
+}
@@ -6,4 +6,6 @@ pub enum GameState {
     Loading,
     Playing,
     Paused,
+    /// The player died; waiting for a restart
+    GameOver,
 }
\ No newline at end of file
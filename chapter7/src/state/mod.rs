@@ -1,10 +1,15 @@
+mod game_over;
 mod game_state;
 mod loading;
 mod pause;
 
+use bevy::asset::LoadState;
 use bevy::prelude::*;
 use crate::characters::spawn::CharactersListResource;
 use crate::characters::config::CharactersList;
+use crate::map::assets::TilemapDefinitionResource;
+use crate::map::rules::{TerrainRules, TerrainRulesResource};
+use crate::map::tilemap::TilemapDefinition;
 
 pub use game_state::GameState;
 
@@ -19,6 +24,7 @@ impl Plugin for StatePlugin {
             .add_systems(OnEnter(GameState::Loading), loading::spawn_loading_screen)
             .add_systems(Update, (
                 check_assets_loaded,
+                exit_on_failed_assets,
                 loading::animate_loading,
             ).run_if(in_state(GameState::Loading)))
             .add_systems(OnExit(GameState::Loading), (
@@ -27,6 +33,9 @@ impl Plugin for StatePlugin {
                 // Pause state systems
             .add_systems(OnEnter(GameState::Paused), pause::spawn_pause_menu)
             .add_systems(OnExit(GameState::Paused), pause::despawn_pause_menu)
+            // Game over screen, until the world restarts
+            .add_systems(OnEnter(GameState::GameOver), game_over::spawn_game_over_screen)
+            .add_systems(OnExit(GameState::GameOver), game_over::despawn_game_over_screen)
             
             // Pause toggle (works in Playing or Paused states)
             .add_systems(Update, 
@@ -38,18 +47,55 @@ impl Plugin for StatePlugin {
 fn check_assets_loaded(
     characters_list_res: Option<Res<CharactersListResource>>,
     characters_lists: Res<Assets<CharactersList>>,
+    tilemap_res: Option<Res<TilemapDefinitionResource>>,
+    tilemaps: Res<Assets<TilemapDefinition>>,
+    terrain_rules_res: Option<Res<TerrainRulesResource>>,
+    terrain_rules: Res<Assets<TerrainRules>>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    let Some(res) = characters_list_res else {
+    let (Some(res), Some(tilemap_res), Some(terrain_rules_res)) =
+        (characters_list_res, tilemap_res, terrain_rules_res)
+    else {
         return;
     };
     
-    if characters_lists.get(&res.handle).is_some() {
+    if characters_lists.get(&res.handle).is_some()
+        && tilemaps.get(&tilemap_res.handle).is_some()
+        && terrain_rules.get(&terrain_rules_res.handle).is_some()
+    {
         info!("Assets loaded, transitioning to Playing!");
         next_state.set(GameState::Playing);
     }
 }
 
+/// Quit with an error when the characters, the tilemap or the terrain rules can't be
+/// loaded (missing file, invalid RON), instead of staying on the loading screen forever.
+fn exit_on_failed_assets(
+    asset_server: Res<AssetServer>,
+    characters_list_res: Option<Res<CharactersListResource>>,
+    tilemap_res: Option<Res<TilemapDefinitionResource>>,
+    terrain_rules_res: Option<Res<TerrainRulesResource>>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    let handles = [
+        characters_list_res.map(|res| res.handle.id().untyped()),
+        tilemap_res.map(|res| res.handle.id().untyped()),
+        terrain_rules_res.map(|res| res.handle.id().untyped()),
+    ];
+
+    let mut failed = false;
+    for id in handles.into_iter().flatten() {
+        if let Some(LoadState::Failed(err)) = asset_server.get_load_state(id) {
+            let path = asset_server.get_path(id).map(|path| path.to_string()).unwrap_or_default();
+            error!("Could not load {}: {}", path, err);
+            failed = true;
+        }
+    }
+    if failed {
+        exit.write(AppExit::error());
+    }
+}
+
 fn toggle_pause(
     input: Res<ButtonInput<KeyCode>>,
     current_state: Res<State<GameState>>,
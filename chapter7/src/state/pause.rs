@@ -1,9 +1,27 @@
 use bevy::prelude::*;
 
+use crate::characters::input::Player;
+use crate::map::biomes::Biomes;
+use crate::map::seed::WorldSeed;
+
 #[derive(Component)]
 pub struct PauseMenu;
 
-pub fn spawn_pause_menu(mut commands: Commands) {
+pub fn spawn_pause_menu(
+    mut commands: Commands,
+    seed: Res<WorldSeed>,
+    biomes: Biomes,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    let mut status = format!("World seed: {}", seed.0);
+    let biome = player_query
+        .single()
+        .ok()
+        .and_then(|transform| biomes.at(transform.translation.truncate()));
+    if let Some(biome) = biome {
+        status.push_str(&format!("\nBiome: {}", biome.name));
+    }
+
     commands.spawn((
         PauseMenu,
         Node {
@@ -16,7 +34,7 @@ pub fn spawn_pause_menu(mut commands: Commands) {
         BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
     )).with_children(|parent| {
         parent.spawn((
-            Text::new("PAUSED\n\nPress ESC to resume"),
+            Text::new(format!("PAUSED\n\nPress ESC to resume\nPress R to restart this world\nPress F5 for a new world\nPress F6 to save the map, F9 to load it\n\n{}", status)),
             TextFont {
                 font_size: 36.0,
                 ..default()